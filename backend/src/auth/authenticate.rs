@@ -19,6 +19,15 @@ pub struct AuthorizatePayload {
     pub password: String,
 }
 
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AuthenticateOutcome {
+    Authenticated,
+
+    /// The password is correct, but the TOTP code is required to finish (see `totp::verify`)
+    TwoFactorRequired,
+}
+
 #[derive(ApiError, Debug, Error)]
 pub enum AuthorizateError {
     #[error("the account does not exist")]
@@ -45,7 +54,7 @@ pub async fn authenticate(
     mut state: ServerState,
     cookies: Cookies,
     payload: AuthorizatePayload,
-) -> Result<(), AuthorizateError> {
+) -> Result<AuthenticateOutcome, AuthorizateError> {
     let Some(user) = Query::find_user_by_email(&state.db, &payload.email).await? else {
         return Err(AuthorizateError::AccountNotExists);
     };
@@ -59,14 +68,19 @@ pub async fn authenticate(
         return Err(AuthorizateError::InvalidPassword);
     }
 
+    if user.totp_enabled {
+        super::totp::set_pending_token(&user.id, &state.redis, cookies).await?;
+        return Ok(AuthenticateOutcome::TwoFactorRequired);
+    }
+
     super::set_session_token(&mut state.random, &user.id, &state.redis, cookies).await?;
-    Ok(())
+    Ok(AuthenticateOutcome::Authenticated)
 }
 
 pub async fn authenticate_route(
     State(state): State<ServerState>,
     cookies: Cookies,
     Json(payload): Json<AuthorizatePayload>,
-) -> Result<(), AuthorizateError> {
-    authenticate(state, cookies, payload).await
+) -> Result<Json<AuthenticateOutcome>, AuthorizateError> {
+    authenticate(state, cookies, payload).await.map(Json)
 }
@@ -1,7 +1,7 @@
 use std::mem;
 
 use axum::{routing::post, Router};
-use rand_chacha::{rand_core::RngCore, ChaCha8Rng};
+use rand_chacha::rand_core::RngCore;
 use redis::RedisResult;
 use tower_cookies::Cookies;
 use uuid::Uuid;
@@ -14,28 +14,36 @@ use crate::{
 
 pub mod authenticate;
 pub mod oauth;
+pub mod passkey;
 pub mod register;
+pub mod totp;
 
 const SESSION_TOKEN_EXPIRED: u64 = 10800; // In seconds, 3 hours
 
 pub fn routes() -> Router<ServerState> {
     Router::new()
         .nest("/oauth", oauth::routes())
+        .nest("/passkey", passkey::routes())
+        .nest("/totp", totp::routes())
         .route("/authenticate", post(authenticate::authenticate_route))
         .route("/register", post(register::register_route))
 }
 
+fn generate_token(random: &mut impl RngCore) -> u128 {
+    let mut pool = [0u8; mem::size_of::<u128>()];
+    random.fill_bytes(&mut pool);
+
+    // Endian doesn't matter here
+    u128::from_le_bytes(pool)
+}
+
 async fn set_session_token(
-    random: &mut ChaCha8Rng,
+    random: &mut impl RngCore,
     client_id: &Uuid,
     redis_client: &redis::Client,
     cookies: Cookies,
 ) -> RedisResult<()> {
-    let mut pool = [0u8; mem::size_of::<u128>()];
-    random.fill_bytes(&mut pool);
-
-    // Endian doesn't matter here
-    let token = u128::from_le_bytes(pool);
+    let token = generate_token(random);
     session::insert_token(
         redis_client,
         token.to_string(),
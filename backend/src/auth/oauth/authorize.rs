@@ -0,0 +1,257 @@
+use ::redis::{Client, RedisError};
+use api_error_derive::ApiError;
+use axum::{
+    extract::{Path, Query, State},
+    response::Redirect,
+};
+use common::{is_safe_return_to, DEFAULT_RETURN_TO};
+use oauth2::{
+    basic::BasicErrorResponseType, AuthorizationCode, CsrfToken, PkceCodeChallenge,
+    PkceCodeVerifier, RequestTokenError, StandardErrorResponse, TokenResponse,
+};
+use rand_chacha::ChaCha8Rng;
+use sea_orm::{DatabaseConnection, DbErr};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use service::{query::Query as ServiceQuery, RegistrationType};
+use thiserror::Error;
+use tower_cookies::Cookies;
+
+use super::{
+    id_token::IdTokenError,
+    provider::{ClaimsSource, OAuthProviders},
+    OAUTH_STATE_EXPIRED,
+};
+use crate::{
+    cookies::{
+        self, REGISTRATION_EMAIL_TOKEN, REGISTRATION_PROVIDER_TOKEN, REGISTRATION_TYPE_TOKEN,
+        RETURN_TO_TOKEN,
+    },
+    redis::oauth::{self, OAuthState},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorizeRequest {
+    return_to: Option<String>,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum AuthorizeError {
+    #[error("unknown OAuth provider")]
+    #[status_code(NOT_FOUND)]
+    UnknownProvider,
+
+    #[error("the return path leaves the site")]
+    #[status_code(BAD_REQUEST)]
+    InvalidReturnTo,
+
+    #[error("redis error ({0})")]
+    RedisError(#[from] RedisError),
+}
+
+pub async fn authorize(
+    Path(provider): Path<String>,
+    Query(query): Query<AuthorizeRequest>,
+    State(providers): State<OAuthProviders>,
+    State(redis): State<Client>,
+) -> Result<Redirect, AuthorizeError> {
+    let provider = providers
+        .get(&provider)
+        .ok_or(AuthorizeError::UnknownProvider)?;
+
+    if query
+        .return_to
+        .as_ref()
+        .is_some_and(|path| !is_safe_return_to(path))
+    {
+        return Err(AuthorizeError::InvalidReturnTo);
+    }
+
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let nonce = CsrfToken::new_random();
+
+    let (auth_url, crsf_token) = provider
+        .client
+        .authorize_url(CsrfToken::new_random)
+        .add_scopes(provider.scopes.iter().cloned())
+        .add_extra_param("nonce", nonce.secret())
+        .set_pkce_challenge(pkce_challenge)
+        .url();
+
+    oauth::insert_state(
+        &redis,
+        crsf_token,
+        OAuthState {
+            provider: provider.name.clone(),
+            pkce_verifier: pkce_verifier.secret().to_owned(),
+            nonce: nonce.secret().to_owned(),
+            return_to: query.return_to,
+        },
+        OAUTH_STATE_EXPIRED,
+    )
+    .await?;
+
+    Ok(Redirect::to(auth_url.as_str()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthRequest {
+    code: String,
+    state: String,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum AuthorizedError {
+    #[error("unknown OAuth provider")]
+    #[status_code(NOT_FOUND)]
+    UnknownProvider,
+
+    #[error("invalid/expired OAuth state")]
+    #[status_code(BAD_REQUEST)]
+    InvalidState,
+
+    #[error("the token response has no ID token")]
+    #[status_code(BAD_REQUEST)]
+    MissingIdToken,
+
+    #[error("invalid ID token ({0})")]
+    #[status_code(BAD_REQUEST)]
+    InvalidIdToken(#[from] IdTokenError),
+
+    #[error("the claims have no email")]
+    #[status_code(BAD_REQUEST)]
+    MissingEmailClaim,
+
+    #[error("the email is not verified by the provider")]
+    #[status_code(BAD_REQUEST)]
+    UnverifiedEmail,
+
+    #[error("redis error ({0})")]
+    RedisError(#[from] RedisError),
+
+    #[error("request token error ({0})")]
+    RequestTokenError(
+        #[from]
+        RequestTokenError<
+            oauth2::reqwest::Error<reqwest::Error>,
+            StandardErrorResponse<BasicErrorResponseType>,
+        >,
+    ),
+
+    #[error("reqwest error ({0})")]
+    Reqwest(#[from] reqwest::Error),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn authorized(
+    Path(provider): Path<String>,
+    Query(query): Query<AuthRequest>,
+    cookies: Cookies,
+    State(providers): State<OAuthProviders>,
+    State(mut random): State<ChaCha8Rng>,
+    State(redis): State<Client>,
+    State(reqwest): State<reqwest::Client>,
+    State(db): State<DatabaseConnection>,
+) -> Result<Redirect, AuthorizedError> {
+    let provider = providers
+        .get(&provider)
+        .ok_or(AuthorizedError::UnknownProvider)?;
+
+    let state = oauth::take_state(&redis, CsrfToken::new(query.state))
+        .await?
+        .filter(|state| state.provider == provider.name)
+        .ok_or(AuthorizedError::InvalidState)?;
+
+    let token = provider
+        .client
+        .exchange_code(AuthorizationCode::new(query.code))
+        .set_pkce_verifier(PkceCodeVerifier::new(state.pkce_verifier))
+        .request_async(oauth2::reqwest::async_http_client)
+        .await?;
+
+    let claims = match &provider.claims_source {
+        ClaimsSource::IdToken(verifier) => {
+            let id_token = token
+                .extra_fields()
+                .id_token
+                .as_deref()
+                .ok_or(AuthorizedError::MissingIdToken)?;
+
+            verifier.verify(&reqwest, id_token, &state.nonce).await?
+        }
+        ClaimsSource::Userinfo(userinfo_url) => {
+            reqwest
+                .get(userinfo_url)
+                .bearer_auth(token.access_token().secret())
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Map<String, Value>>()
+                .await?
+        }
+    };
+
+    let email = provider
+        .email(&claims)
+        .ok_or(AuthorizedError::MissingEmailClaim)?
+        .to_owned();
+
+    if !provider.is_email_verified(&claims) {
+        return Err(AuthorizedError::UnverifiedEmail);
+    }
+
+    let token_to_revoke = match token.refresh_token() {
+        Some(val) => val.into(),
+        None => token.access_token().into(),
+    };
+
+    // Not every provider supports the revocation (RFC 7009)
+    if let Ok(request) = provider.client.revoke_token(token_to_revoke) {
+        if let Err(err) = request
+            .request_async(oauth2::reqwest::async_http_client)
+            .await
+        {
+            tracing::warn!(provider = provider.name, "failed to revoke token ({err})");
+        }
+    }
+
+    let Some(user) = ServiceQuery::find_user_by_email(&db, &email).await? else {
+        cookies.add(cookies::create_secure_cookie(
+            REGISTRATION_EMAIL_TOKEN,
+            email,
+        ));
+        cookies.add(cookies::create_secure_cookie(
+            REGISTRATION_TYPE_TOKEN,
+            RegistrationType::OAuth.to_string(),
+        ));
+        cookies.add(cookies::create_secure_cookie(
+            REGISTRATION_PROVIDER_TOKEN,
+            provider.name.clone(),
+        ));
+
+        // Will be used after the registration
+        if let Some(return_to) = state.return_to {
+            cookies.add(cookies::create_secure_cookie(RETURN_TO_TOKEN, return_to));
+        }
+
+        return Ok(Redirect::to("/registration_details"));
+    };
+
+    // The provider only replaces the password, the second factor is still required
+    if user.totp_enabled {
+        crate::auth::totp::set_pending_token(&user.id, &redis, cookies).await?;
+
+        return Ok(Redirect::to(&match state.return_to {
+            Some(return_to) => format!("/two_factor?return_to={}", urlencoding::encode(&return_to)),
+            None => "/two_factor".to_owned(),
+        }));
+    }
+
+    crate::auth::set_session_token(&mut random, &user.id, &redis, cookies).await?;
+    Ok(Redirect::to(
+        state.return_to.as_deref().unwrap_or(DEFAULT_RETURN_TO),
+    ))
+}
@@ -0,0 +1,197 @@
+use std::{
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+use jsonwebtoken::{
+    jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm, PublicKeyUse},
+    Algorithm, DecodingKey, Validation,
+};
+use reqwest::Client as ReqwestClient;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+const JWKS_CACHE_DURATION: Duration = Duration::from_secs(3600); // 1 hour
+const JWKS_MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(60); // 1 minute
+
+#[derive(Debug, Error)]
+pub enum IdTokenError {
+    #[error("jwt error ({0})")]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+
+    #[error("failed to fetch JWKS ({0})")]
+    Reqwest(#[from] reqwest::Error),
+
+    #[error("unsupported signing algorithm ({0:?})")]
+    UnsupportedAlgorithm(Algorithm),
+
+    #[error("unknown signing key")]
+    UnknownKey,
+
+    #[error("the signing key isn't for {0:?}")]
+    KeyMismatch(Algorithm),
+
+    #[error("invalid nonce")]
+    InvalidNonce,
+}
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// Verifies the OpenID Connect ID tokens
+/// (https://openid.net/specs/openid-connect-core-1_0.html#IDTokenValidation)
+pub struct IdTokenVerifier {
+    issuer: String,
+    client_id: String,
+    jwks_url: String,
+    jwks: RwLock<Option<CachedJwks>>,
+}
+
+impl IdTokenVerifier {
+    pub fn new(issuer: String, client_id: String, jwks_url: String) -> Self {
+        Self {
+            issuer,
+            client_id,
+            jwks_url,
+            jwks: RwLock::new(None),
+        }
+    }
+
+    pub async fn verify(
+        &self,
+        reqwest: &ReqwestClient,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<Map<String, Value>, IdTokenError> {
+        let header = jsonwebtoken::decode_header(id_token)?;
+
+        // Symmetric algorithms would accept the public key as a HMAC secret
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(IdTokenError::UnsupportedAlgorithm(header.alg));
+        }
+
+        // The key is found by its id, the algorithm of the token must be the one of the key
+        let jwk = self.key(reqwest, header.kid.as_deref()).await?;
+        if !is_key_for(&jwk, header.alg) {
+            return Err(IdTokenError::KeyMismatch(header.alg));
+        }
+        let key = DecodingKey::from_jwk(&jwk)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud"]);
+
+        let claims =
+            jsonwebtoken::decode::<Map<String, Value>>(id_token, &key, &validation)?.claims;
+
+        if claims.get("nonce").and_then(Value::as_str) != Some(nonce) {
+            return Err(IdTokenError::InvalidNonce);
+        }
+
+        Ok(claims)
+    }
+
+    async fn key(&self, reqwest: &ReqwestClient, kid: Option<&str>) -> Result<Jwk, IdTokenError> {
+        let cached = self.jwks.read().unwrap().as_ref().map(|jwks| {
+            (
+                jwks.fetched_at.elapsed(),
+                find_key(&jwks.keys, kid).cloned(),
+            )
+        });
+
+        match cached {
+            Some((age, Some(jwk))) if age < JWKS_CACHE_DURATION => return Ok(jwk),
+            // The provider could rotate its keys, but don't let unknown key ids hammer it
+            Some((age, None)) if age < JWKS_MIN_REFRESH_INTERVAL => {
+                return Err(IdTokenError::UnknownKey)
+            }
+            _ => (),
+        }
+
+        let keys = reqwest
+            .get(&self.jwks_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JwkSet>()
+            .await?;
+
+        let key = find_key(&keys, kid).cloned();
+
+        *self.jwks.write().unwrap() = Some(CachedJwks {
+            keys,
+            fetched_at: Instant::now(),
+        });
+
+        key.ok_or(IdTokenError::UnknownKey)
+    }
+}
+
+fn find_key<'a>(keys: &'a JwkSet, kid: Option<&str>) -> Option<&'a Jwk> {
+    match kid {
+        Some(kid) => keys.find(kid),
+        None if keys.keys.len() == 1 => keys.keys.first(),
+        None => None,
+    }
+}
+
+/// The key type and curve follow from the algorithm. The algorithm of the key is optional
+/// in the JWKS, when set it must be the same.
+fn is_key_for(jwk: &Jwk, alg: Algorithm) -> bool {
+    if jwk.common.public_key_use == Some(PublicKeyUse::Encryption) {
+        return false;
+    }
+
+    if jwk
+        .common
+        .key_algorithm
+        .is_some_and(|key_alg| key_alg != key_algorithm(alg))
+    {
+        return false;
+    }
+
+    match (&jwk.algorithm, alg) {
+        (
+            AlgorithmParameters::RSA(_),
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512,
+        ) => true,
+        (AlgorithmParameters::EllipticCurve(params), Algorithm::ES256) => {
+            params.curve == EllipticCurve::P256
+        }
+        (AlgorithmParameters::EllipticCurve(params), Algorithm::ES384) => {
+            params.curve == EllipticCurve::P384
+        }
+        (AlgorithmParameters::OctetKeyPair(params), Algorithm::EdDSA) => {
+            params.curve == EllipticCurve::Ed25519
+        }
+        _ => false,
+    }
+}
+
+fn key_algorithm(alg: Algorithm) -> KeyAlgorithm {
+    match alg {
+        Algorithm::HS256 => KeyAlgorithm::HS256,
+        Algorithm::HS384 => KeyAlgorithm::HS384,
+        Algorithm::HS512 => KeyAlgorithm::HS512,
+        Algorithm::ES256 => KeyAlgorithm::ES256,
+        Algorithm::ES384 => KeyAlgorithm::ES384,
+        Algorithm::RS256 => KeyAlgorithm::RS256,
+        Algorithm::RS384 => KeyAlgorithm::RS384,
+        Algorithm::RS512 => KeyAlgorithm::RS512,
+        Algorithm::PS256 => KeyAlgorithm::PS256,
+        Algorithm::PS384 => KeyAlgorithm::PS384,
+        Algorithm::PS512 => KeyAlgorithm::PS512,
+        Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+    }
+}
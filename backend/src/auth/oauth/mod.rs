@@ -1,11 +1,15 @@
-use axum::Router;
+use axum::{routing::get, Router};
 
 use crate::state::ServerState;
 
-pub mod google;
+pub mod authorize;
+pub mod id_token;
+pub mod provider;
 
 const OAUTH_STATE_EXPIRED: u64 = 600; // Seconds, 10 minutes
 
 pub fn routes() -> Router<ServerState> {
-    Router::new().nest("/google", google::routes())
+    Router::new()
+        .route("/:provider", get(authorize::authorize))
+        .route("/:provider/authorized", get(authorize::authorized))
 }
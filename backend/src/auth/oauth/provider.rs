@@ -0,0 +1,232 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{bail, Context};
+use oauth2::{
+    basic::{
+        BasicErrorResponse, BasicRevocationErrorResponse, BasicTokenIntrospectionResponse,
+        BasicTokenType,
+    },
+    AuthUrl, Client, ClientId, ClientSecret, ExtraTokenFields, RedirectUrl, RevocationUrl, Scope,
+    StandardRevocableToken, StandardTokenResponse, TokenUrl,
+};
+use reqwest::Client as ReqwestClient;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::id_token::IdTokenVerifier;
+use crate::environment::OAuthProviderEnvironment;
+
+const DISCOVERY_PATH: &str = "/.well-known/openid-configuration";
+
+/// Subset of the OpenID Connect discovery document
+/// (https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderMetadata)
+#[derive(Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: Option<String>,
+    revocation_endpoint: Option<String>,
+    jwks_uri: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IdTokenFields {
+    pub id_token: Option<String>,
+}
+
+impl ExtraTokenFields for IdTokenFields {}
+
+pub type OAuthTokenResponse = StandardTokenResponse<IdTokenFields, BasicTokenType>;
+
+pub type OAuthClient = Client<
+    BasicErrorResponse,
+    OAuthTokenResponse,
+    BasicTokenType,
+    BasicTokenIntrospectionResponse,
+    StandardRevocableToken,
+    BasicRevocationErrorResponse,
+>;
+
+pub enum ClaimsSource {
+    /// OpenID Connect providers, the claims are taken from the verified ID token
+    IdToken(IdTokenVerifier),
+
+    /// Plain OAuth2 providers, the claims are requested from the userinfo endpoint
+    Userinfo(String),
+}
+
+pub struct OAuthProvider {
+    pub name: String,
+    pub client: OAuthClient,
+    pub scopes: Vec<Scope>,
+    pub claims_source: ClaimsSource,
+    pub email_claim: String,
+    pub email_verified_claim: String,
+    pub email_verified_by_default: bool,
+}
+
+impl OAuthProvider {
+    pub async fn new(
+        reqwest: &ReqwestClient,
+        redirect_url: &str,
+        environment: &OAuthProviderEnvironment,
+    ) -> anyhow::Result<Self> {
+        let metadata = match &environment.issuer {
+            Some(issuer) => Some(discover(reqwest, issuer).await?),
+            None => None,
+        };
+
+        // Explicitly configured endpoints take priority over the discovered ones
+        let auth_url = environment
+            .auth_url
+            .clone()
+            .or_else(|| {
+                metadata
+                    .as_ref()
+                    .map(|val| val.authorization_endpoint.clone())
+            })
+            .context("authorization endpoint")?;
+        let token_url = environment
+            .token_url
+            .clone()
+            .or_else(|| metadata.as_ref().map(|val| val.token_endpoint.clone()))
+            .context("token endpoint")?;
+        let revocation_url = environment.revocation_url.clone().or_else(|| {
+            metadata
+                .as_ref()
+                .and_then(|val| val.revocation_endpoint.clone())
+        });
+
+        let mut scopes = environment.scopes.clone();
+
+        let claims_source = match metadata {
+            Some(ProviderMetadata {
+                issuer,
+                jwks_uri: Some(jwks_uri),
+                ..
+            }) => {
+                // The ID token is issued only for the "openid" scope
+                if !scopes.iter().any(|scope| scope == "openid") {
+                    scopes.push("openid".to_owned());
+                }
+
+                ClaimsSource::IdToken(IdTokenVerifier::new(
+                    issuer,
+                    environment.client_id.clone(),
+                    jwks_uri,
+                ))
+            }
+            metadata => ClaimsSource::Userinfo(
+                environment
+                    .userinfo_url
+                    .clone()
+                    .or_else(|| metadata.and_then(|val| val.userinfo_endpoint))
+                    .with_context(|| {
+                        format!(
+                            "{} provider has neither JWKS nor userinfo endpoint",
+                            environment.name
+                        )
+                    })?,
+            ),
+        };
+
+        let mut client = OAuthClient::new(
+            ClientId::new(environment.client_id.clone()),
+            Some(ClientSecret::new(environment.client_secret.clone())),
+            AuthUrl::new(auth_url).context("Invalid authorization endpoint URL")?,
+            Some(TokenUrl::new(token_url).context("Invalid token endpoint URL")?),
+        )
+        .set_redirect_uri(
+            RedirectUrl::new(format!(
+                "{redirect_url}/api/auth/oauth/{}/authorized",
+                environment.name
+            ))
+            .context("Invalid redirect URL")?,
+        );
+
+        if let Some(revocation_url) = revocation_url {
+            client = client.set_revocation_uri(
+                RevocationUrl::new(revocation_url).context("Invalid revocation endpoint URL")?,
+            );
+        }
+
+        Ok(Self {
+            name: environment.name.clone(),
+            client,
+            scopes: scopes.into_iter().map(Scope::new).collect(),
+            claims_source,
+            email_claim: environment.email_claim.clone(),
+            email_verified_claim: environment.email_verified_claim.clone(),
+            email_verified_by_default: environment.email_verified_by_default,
+        })
+    }
+
+    pub fn email<'a>(&self, claims: &'a Map<String, Value>) -> Option<&'a str> {
+        claims.get(&self.email_claim)?.as_str()
+    }
+
+    /// The missing claim is unverified unless the provider is configured to trust it
+    pub fn is_email_verified(&self, claims: &Map<String, Value>) -> bool {
+        // Some providers serialize it as a string
+        match claims.get(&self.email_verified_claim) {
+            Some(Value::Bool(val)) => *val,
+            Some(Value::String(val)) => val == "true",
+            None => self.email_verified_by_default,
+            _ => false,
+        }
+    }
+}
+
+async fn discover(reqwest: &ReqwestClient, issuer: &str) -> anyhow::Result<ProviderMetadata> {
+    let issuer = issuer.trim_end_matches('/');
+
+    let metadata = reqwest
+        .get(format!("{issuer}{DISCOVERY_PATH}"))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .with_context(|| format!("Failed to fetch the {issuer} discovery document"))?
+        .json::<ProviderMetadata>()
+        .await
+        .with_context(|| format!("Failed to parse the {issuer} discovery document"))?;
+
+    // https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderConfigurationValidation
+    if metadata.issuer.trim_end_matches('/') != issuer {
+        bail!(
+            "Issuer mismatch in the discovery document (expected {issuer}, got {})",
+            metadata.issuer
+        );
+    }
+
+    Ok(metadata)
+}
+
+#[derive(Clone, Default)]
+pub struct OAuthProviders(Arc<HashMap<String, OAuthProvider>>);
+
+impl OAuthProviders {
+    pub async fn new(
+        reqwest: &ReqwestClient,
+        redirect_url: &str,
+        environments: &[OAuthProviderEnvironment],
+    ) -> anyhow::Result<Self> {
+        let mut providers = HashMap::with_capacity(environments.len());
+
+        for environment in environments {
+            let provider = OAuthProvider::new(reqwest, redirect_url, environment)
+                .await
+                .with_context(|| {
+                    format!("Failed to configure the {} provider", environment.name)
+                })?;
+
+            providers.insert(provider.name.clone(), provider);
+        }
+
+        Ok(Self(Arc::new(providers)))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OAuthProvider> {
+        self.0.get(name)
+    }
+}
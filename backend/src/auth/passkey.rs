@@ -0,0 +1,419 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use api_error_derive::ApiError;
+use axum::{extract::State, routing::post, Json, Router};
+use common::{MAX_PASSKEY_NAME_SIZE, MAX_USER_EMAIL_SIZE};
+use rand_chacha::rand_core::{OsRng, RngCore};
+use redis::RedisError;
+use sea_orm::DbErr;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use service::{
+    mutation::{CreatePasskeyData, Mutation},
+    query::Query,
+};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+use validator::Validate;
+use webauthn_rs::{
+    prelude::{
+        CreationChallengeResponse, CredentialID, Passkey, PasskeyAuthentication,
+        PasskeyRegistration, PublicKeyCredential, RegisterPublicKeyCredential,
+        RequestChallengeResponse, Url, WebauthnError,
+    },
+    Webauthn, WebauthnBuilder,
+};
+
+use crate::{
+    redis::webauthn, session::SessionContext, state::ServerState, validator::ValidatedJson,
+};
+
+const CHALLENGE_EXPIRED: u64 = 300; // In seconds, 5 minutes
+const RP_NAME: &str = "Simple Messenger";
+
+pub fn routes() -> Router<ServerState> {
+    Router::new()
+        .route("/register/start", post(register_start))
+        .route("/register/finish", post(register_finish))
+        .route("/authenticate/start", post(authenticate_start))
+        .route("/authenticate/finish", post(authenticate_finish))
+}
+
+/// The relying party origin is the site URL, the id defaults to its host
+fn relying_party(redirect_url: &str, rp_id: Option<&str>) -> anyhow::Result<(Url, String)> {
+    let rp_origin = Url::parse(redirect_url).context("REDIRECT_URL must be a valid URL")?;
+    let rp_id = match rp_id {
+        Some(val) => val.to_owned(),
+        None => rp_origin
+            .host_str()
+            .context("REDIRECT_URL must have a host")?
+            .to_owned(),
+    };
+
+    Ok((rp_origin, rp_id))
+}
+
+pub fn create_webauthn(redirect_url: &str, rp_id: Option<&str>) -> anyhow::Result<Webauthn> {
+    let (rp_origin, rp_id) = relying_party(redirect_url, rp_id)?;
+
+    WebauthnBuilder::new(&rp_id, &rp_origin)
+        .context("WEBAUTHN_RP_ID must be a registrable suffix of the REDIRECT_URL host")?
+        .rp_name(RP_NAME)
+        .timeout(Duration::from_secs(CHALLENGE_EXPIRED))
+        .build()
+        .context("Failed to initialize Webauthn")
+}
+
+/// Answers the emails without passkeys like the others, so the accounts can't be enumerated
+#[derive(Clone)]
+pub struct PasskeyDecoy {
+    key: [u8; 32],
+    rp_id: String,
+}
+
+impl PasskeyDecoy {
+    pub fn new(redirect_url: &str, rp_id: Option<&str>) -> anyhow::Result<Self> {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+
+        Ok(Self {
+            key,
+            rp_id: relying_party(redirect_url, rp_id)?.1,
+        })
+    }
+
+    // The credential stays the same for the email, like a real passkey would
+    fn challenge(&self, email: &str) -> Result<RequestChallengeResponse, serde_json::Error> {
+        let credential_id = Sha256::new()
+            .chain_update(self.key)
+            .chain_update(email.to_lowercase())
+            .finalize();
+
+        let mut challenge = vec![0u8; 32];
+        OsRng.fill_bytes(&mut challenge);
+
+        serde_json::from_value(json!({
+            "publicKey": {
+                "challenge": CredentialID::from(challenge),
+                "timeout": CHALLENGE_EXPIRED * 1000,
+                "rpId": self.rp_id,
+                "allowCredentials": [{
+                    "type": "public-key",
+                    "id": CredentialID::from(credential_id[..16].to_vec()),
+                }],
+                "userVerification": "required",
+            },
+        }))
+    }
+}
+
+/// The key of the ceremony state. `ServerState::random` is copied with the state, so the
+/// concurrent requests would get the same id from it.
+fn challenge_id() -> String {
+    super::generate_token(&mut OsRng).to_string()
+}
+
+/// Sent to the browser, the `challenge_id` must be returned with the signed credential
+#[derive(Serialize)]
+pub struct ChallengeResponse<T> {
+    pub challenge_id: String,
+    pub options: T,
+}
+
+#[derive(Deserialize, Serialize)]
+struct RegistrationState {
+    user_id: Uuid,
+    registration: PasskeyRegistration,
+}
+
+#[derive(Deserialize, Serialize)]
+struct AuthenticationState {
+    user_id: Uuid,
+    authentication: PasskeyAuthentication,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum RegisterStartError {
+    #[error("user not found")]
+    #[status_code(NOT_FOUND)]
+    UserNotFound,
+
+    #[error("webauthn error ({0})")]
+    Webauthn(#[from] WebauthnError),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+
+    #[error("redis error ({0})")]
+    Redis(#[from] RedisError),
+}
+
+pub async fn register_start(
+    State(state): State<ServerState>,
+    session: SessionContext,
+) -> Result<Json<ChallengeResponse<CreationChallengeResponse>>, RegisterStartError> {
+    let user = Query::find_user_by_id(&state.db, session.user_id)
+        .await?
+        .ok_or(RegisterStartError::UserNotFound)?;
+
+    // Don't register the same authenticator twice
+    let exclude_credentials = Query::find_passkeys_by_user_id(&state.db, user.id)
+        .await?
+        .into_iter()
+        .map(|passkey| CredentialID::from(passkey.credential_id))
+        .collect();
+
+    let (options, registration) = state.webauthn.start_passkey_registration(
+        user.id,
+        &user.email,
+        &user.name,
+        Some(exclude_credentials),
+    )?;
+
+    let challenge_id = challenge_id();
+    webauthn::insert_state(
+        &state.redis,
+        challenge_id.clone(),
+        RegistrationState {
+            user_id: user.id,
+            registration,
+        },
+        CHALLENGE_EXPIRED,
+    )
+    .await?;
+
+    Ok(Json(ChallengeResponse {
+        challenge_id,
+        options,
+    }))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct RegisterFinishPayload {
+    pub challenge_id: String,
+
+    #[validate(length(min = 1, max = "MAX_PASSKEY_NAME_SIZE"))]
+    pub name: String,
+
+    pub credential: RegisterPublicKeyCredential,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum RegisterFinishError {
+    #[error("invalid/expired challenge")]
+    #[status_code(BAD_REQUEST)]
+    InvalidChallenge,
+
+    #[error("invalid credential ({0})")]
+    #[status_code(BAD_REQUEST)]
+    InvalidCredential(#[from] WebauthnError),
+
+    #[error("serde json error ({0})")]
+    SerdeJson(#[from] serde_json::Error),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+
+    #[error("redis error ({0})")]
+    Redis(#[from] RedisError),
+}
+
+pub async fn register_finish(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    ValidatedJson(payload): ValidatedJson<RegisterFinishPayload>,
+) -> Result<(), RegisterFinishError> {
+    let registration_state =
+        webauthn::take_state::<RegistrationState>(&state.redis, &payload.challenge_id)
+            .await?
+            .filter(|registration_state| registration_state.user_id == session.user_id)
+            .ok_or(RegisterFinishError::InvalidChallenge)?;
+
+    let passkey = state
+        .webauthn
+        .finish_passkey_registration(&payload.credential, &registration_state.registration)?;
+
+    Mutation::create_passkey(
+        &state.db,
+        CreatePasskeyData {
+            user_id: session.user_id,
+            credential_id: passkey.cred_id().0.clone(),
+            name: payload.name,
+            passkey: serde_json::to_value(&passkey)?,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[derive(Deserialize, Validate)]
+pub struct AuthenticateStartPayload {
+    #[validate(email, length(max = "MAX_USER_EMAIL_SIZE"))]
+    pub email: String,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum AuthenticateStartError {
+    #[error("webauthn error ({0})")]
+    Webauthn(#[from] WebauthnError),
+
+    #[error("serde json error ({0})")]
+    SerdeJson(#[from] serde_json::Error),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+
+    #[error("redis error ({0})")]
+    Redis(#[from] RedisError),
+}
+
+pub async fn authenticate_start(
+    State(state): State<ServerState>,
+    ValidatedJson(payload): ValidatedJson<AuthenticateStartPayload>,
+) -> Result<Json<ChallengeResponse<RequestChallengeResponse>>, AuthenticateStartError> {
+    let user = Query::find_user_by_email(&state.db, &payload.email).await?;
+
+    let passkeys = match &user {
+        Some(user) => Query::find_passkeys_by_user_id(&state.db, user.id)
+            .await?
+            .into_iter()
+            .map(|passkey| serde_json::from_value::<Passkey>(passkey.passkey))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
+    };
+
+    // No state is stored, the finish step fails like for an expired challenge
+    let Some(user) = user.filter(|_| !passkeys.is_empty()) else {
+        let options = state.passkey_decoy.challenge(&payload.email)?;
+
+        return Ok(Json(ChallengeResponse {
+            challenge_id: challenge_id(),
+            options,
+        }));
+    };
+
+    let (options, authentication) = state.webauthn.start_passkey_authentication(&passkeys)?;
+
+    let challenge_id = challenge_id();
+    webauthn::insert_state(
+        &state.redis,
+        challenge_id.clone(),
+        AuthenticationState {
+            user_id: user.id,
+            authentication,
+        },
+        CHALLENGE_EXPIRED,
+    )
+    .await?;
+
+    Ok(Json(ChallengeResponse {
+        challenge_id,
+        options,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct AuthenticateFinishPayload {
+    pub challenge_id: String,
+    pub credential: PublicKeyCredential,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum AuthenticateFinishError {
+    #[error("invalid/expired challenge")]
+    #[status_code(BAD_REQUEST)]
+    InvalidChallenge,
+
+    #[error("invalid credential ({0})")]
+    #[status_code(BAD_REQUEST)]
+    InvalidCredential(#[from] WebauthnError),
+
+    #[error("the passkey was removed")]
+    #[status_code(BAD_REQUEST)]
+    UnknownPasskey,
+
+    #[error("serde json error ({0})")]
+    SerdeJson(#[from] serde_json::Error),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+
+    #[error("redis error ({0})")]
+    Redis(#[from] RedisError),
+}
+
+/// Passkeys require the user verification, so the TOTP step is skipped
+pub async fn authenticate_finish(
+    State(state): State<ServerState>,
+    cookies: Cookies,
+    Json(payload): Json<AuthenticateFinishPayload>,
+) -> Result<(), AuthenticateFinishError> {
+    let authentication_state =
+        webauthn::take_state::<AuthenticationState>(&state.redis, &payload.challenge_id)
+            .await?
+            .ok_or(AuthenticateFinishError::InvalidChallenge)?;
+
+    let result = state
+        .webauthn
+        .finish_passkey_authentication(&payload.credential, &authentication_state.authentication)?;
+
+    let stored = Query::find_passkeys_by_user_id(&state.db, authentication_state.user_id)
+        .await?
+        .into_iter()
+        .find(|passkey| passkey.credential_id == result.cred_id().0)
+        .ok_or(AuthenticateFinishError::UnknownPasskey)?;
+
+    let mut passkey: Passkey = serde_json::from_value(stored.passkey)?;
+    let updated = match passkey.update_credential(&result) {
+        Some(true) => Some(serde_json::to_value(&passkey)?),
+        _ => None,
+    };
+
+    Mutation::update_passkey_usage(&state.db, stored.id, updated).await?;
+
+    super::set_session_token(
+        &mut OsRng,
+        &authentication_state.user_id,
+        &state.redis,
+        cookies,
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_challenge_id() {
+        assert_ne!(challenge_id(), challenge_id());
+    }
+
+    #[test]
+    fn test_decoy_challenge() {
+        let decoy = PasskeyDecoy::new("https://localhost:3000", None).unwrap();
+
+        let options = |decoy: &PasskeyDecoy, email| {
+            serde_json::to_value(decoy.challenge(email).unwrap()).unwrap()["publicKey"].clone()
+        };
+        let credential = |email| options(&decoy, email)["allowCredentials"][0]["id"].clone();
+
+        let first = credential("a@a.com");
+        assert!(first.is_string());
+        assert_eq!(first, credential("A@a.com"));
+        assert_ne!(first, credential("b@a.com"));
+
+        // The decoy is copied with the server state, the challenges must differ anyway
+        let cloned = decoy.clone();
+        assert_ne!(
+            options(&decoy, "a@a.com")["challenge"],
+            options(&cloned, "a@a.com")["challenge"]
+        );
+    }
+}
@@ -0,0 +1,477 @@
+use std::{
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use api_error_derive::ApiError;
+use axum::{extract::State, routing::post, Json, Router};
+use chacha20poly1305::{
+    aead::{Aead, Payload},
+    AeadCore, ChaCha20Poly1305, Key, KeyInit, Nonce,
+};
+use entity::user;
+use rand_chacha::rand_core::{OsRng, RngCore};
+use redis::{RedisError, RedisResult};
+use sea_orm::DbErr;
+use serde::{Deserialize, Serialize};
+use service::{mutation::Mutation, query::Query};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use totp_rs::{Algorithm, TotpUrlError, TOTP};
+use tower_cookies::{Cookie, Cookies};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    cookies::{self, TWO_FACTOR_TOKEN},
+    redis::two_factor,
+    session::SessionContext,
+    state::ServerState,
+    validator::ValidatedJson,
+};
+
+const PENDING_TOKEN_EXPIRED: u64 = 300; // In seconds, 5 minutes
+const MAX_FAILED_ATTEMPTS: u64 = 5;
+
+const ISSUER: &str = "Simple Messenger";
+const SECRET_SIZE: usize = 20; // 160 bits, RFC 4226
+const NONCE_SIZE: usize = 12;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_SIZE: usize = 10;
+// 32 symbols without the ambiguous 0/O and 1/I
+const RECOVERY_CODE_ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+pub fn routes() -> Router<ServerState> {
+    Router::new()
+        .route("/enroll", post(enroll))
+        .route("/confirm", post(confirm))
+        .route("/verify", post(verify_route))
+}
+
+/// Encrypts the TOTP secrets at rest
+#[derive(Clone)]
+pub struct TotpCipher(ChaCha20Poly1305);
+
+impl TotpCipher {
+    pub fn new(hex_key: &str) -> anyhow::Result<Self> {
+        let key = hex::decode(hex_key.trim()).context("TOTP_ENCRYPTION_KEY must be hex encoded")?;
+
+        if key.len() != 32 {
+            bail!("TOTP_ENCRYPTION_KEY must be 32 bytes long");
+        }
+
+        Ok(Self(ChaCha20Poly1305::new(Key::from_slice(&key))))
+    }
+
+    // The user id is used as the associated data, so the secret can't be moved to another user
+    fn encrypt(&self, user_id: &Uuid, secret: &[u8]) -> Vec<u8> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let ciphertext = self
+            .0
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: secret,
+                    aad: user_id.as_bytes(),
+                },
+            )
+            .expect("TOTP secret encryption");
+
+        [nonce.as_slice(), &ciphertext].concat()
+    }
+
+    fn decrypt(&self, user_id: &Uuid, encrypted: &[u8]) -> Option<Vec<u8>> {
+        if encrypted.len() < NONCE_SIZE {
+            return None;
+        }
+
+        let (nonce, ciphertext) = encrypted.split_at(NONCE_SIZE);
+
+        self.0
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: user_id.as_bytes(),
+                },
+            )
+            .ok()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UserTotpError {
+    #[error("failed to decrypt the TOTP secret")]
+    Decryption,
+
+    #[error("totp error ({0})")]
+    Totp(#[from] TotpUrlError),
+}
+
+fn create_totp(secret: Vec<u8>, email: String) -> Result<TOTP, TotpUrlError> {
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some(ISSUER.to_owned()),
+        email,
+    )
+}
+
+fn user_totp(cipher: &TotpCipher, user: &user::Model) -> Result<Option<TOTP>, UserTotpError> {
+    let Some(encrypted) = &user.totp_secret else {
+        return Ok(None);
+    };
+
+    let secret = cipher
+        .decrypt(&user.id, encrypted)
+        .ok_or(UserTotpError::Decryption)?;
+
+    Ok(Some(create_totp(secret, user.email.clone())?))
+}
+
+/// Returns the time step the code belongs to, the caller must reject the already used steps
+fn check_code(totp: &TOTP, code: &str, time: u64) -> Option<i64> {
+    let code: String = code.chars().filter(|ch| !ch.is_whitespace()).collect();
+
+    if !code.chars().all(|ch| ch.is_ascii_digit()) {
+        return None;
+    }
+
+    // Every step in the skew window is checked on its own to know which one matched
+    let exact = TOTP {
+        skew: 0,
+        ..totp.clone()
+    };
+    let current = time / totp.step;
+
+    (current.saturating_sub(totp.skew.into())..=current + u64::from(totp.skew))
+        .find(|step| exact.check(&code, step * totp.step))
+        .map(|step| step as i64)
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn generate_recovery_code(random: &mut impl RngCore) -> String {
+    let mut code = String::with_capacity(RECOVERY_CODE_SIZE + 1);
+
+    for idx in 0..RECOVERY_CODE_SIZE {
+        if idx == RECOVERY_CODE_SIZE / 2 {
+            code.push('-');
+        }
+
+        // 32 divides 2^32, so there is no modulo bias
+        let symbol =
+            RECOVERY_CODE_ALPHABET[random.next_u32() as usize % RECOVERY_CODE_ALPHABET.len()];
+        code.push(symbol.into());
+    }
+
+    code
+}
+
+// Recovery codes have enough entropy, so a slow password hash isn't needed
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|ch| ch.is_ascii_alphanumeric())
+        .map(|ch| ch.to_ascii_uppercase())
+        .collect();
+
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+/// The token keys the user and the failed attempts, so it's drawn from the OS instead of
+/// `ServerState::random`, which is copied with the state
+pub(super) async fn set_pending_token(
+    user_id: &Uuid,
+    redis_client: &redis::Client,
+    cookies: Cookies,
+) -> RedisResult<()> {
+    let token = super::generate_token(&mut OsRng);
+    two_factor::insert_pending_token(
+        redis_client,
+        token.to_string(),
+        user_id.to_string(),
+        PENDING_TOKEN_EXPIRED,
+    )
+    .await?;
+
+    cookies.add(cookies::create_secure_cookie(
+        TWO_FACTOR_TOKEN,
+        token.to_string(),
+    ));
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct EnrollResponse {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum EnrollError {
+    #[error("two-factor authentication is already enabled")]
+    #[status_code(BAD_REQUEST)]
+    AlreadyEnabled,
+
+    #[error("user not found")]
+    #[status_code(NOT_FOUND)]
+    UserNotFound,
+
+    #[error("totp error ({0})")]
+    Totp(#[from] TotpUrlError),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+pub async fn enroll(
+    State(state): State<ServerState>,
+    session: SessionContext,
+) -> Result<Json<EnrollResponse>, EnrollError> {
+    let user = Query::find_user_by_id(&state.db, session.user_id)
+        .await?
+        .ok_or(EnrollError::UserNotFound)?;
+
+    if user.totp_enabled {
+        return Err(EnrollError::AlreadyEnabled);
+    }
+
+    let mut secret = vec![0u8; SECRET_SIZE];
+    OsRng.fill_bytes(&mut secret);
+
+    let encrypted = state.totp_cipher.encrypt(&user.id, &secret);
+    let totp = create_totp(secret, user.email)?;
+
+    Mutation::set_user_totp_secret(&state.db, user.id, encrypted).await?;
+
+    Ok(Json(EnrollResponse {
+        secret: totp.get_secret_base32(),
+        provisioning_uri: totp.get_url(),
+    }))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CodePayload {
+    #[validate(length(min = 1, max = 32))]
+    pub code: String,
+}
+
+#[derive(Serialize)]
+pub struct ConfirmResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum ConfirmError {
+    #[error("two-factor authentication is already enabled")]
+    #[status_code(BAD_REQUEST)]
+    AlreadyEnabled,
+
+    #[error("the enrollment was not started")]
+    #[status_code(BAD_REQUEST)]
+    NotEnrolled,
+
+    #[error("invalid code")]
+    #[status_code(BAD_REQUEST)]
+    InvalidCode,
+
+    #[error("user not found")]
+    #[status_code(NOT_FOUND)]
+    UserNotFound,
+
+    #[error("user totp error ({0})")]
+    UserTotp(#[from] UserTotpError),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+pub async fn confirm(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    ValidatedJson(payload): ValidatedJson<CodePayload>,
+) -> Result<Json<ConfirmResponse>, ConfirmError> {
+    let user = Query::find_user_by_id(&state.db, session.user_id)
+        .await?
+        .ok_or(ConfirmError::UserNotFound)?;
+
+    if user.totp_enabled {
+        return Err(ConfirmError::AlreadyEnabled);
+    }
+
+    let totp = user_totp(&state.totp_cipher, &user)?.ok_or(ConfirmError::NotEnrolled)?;
+
+    let totp_step =
+        check_code(&totp, &payload.code, unix_time()).ok_or(ConfirmError::InvalidCode)?;
+
+    let recovery_codes: Vec<_> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code(&mut OsRng))
+        .collect();
+
+    Mutation::enable_user_totp(
+        &state.db,
+        user.id,
+        totp_step,
+        recovery_codes
+            .iter()
+            .map(|code| hash_recovery_code(code))
+            .collect(),
+    )
+    .await?;
+
+    Ok(Json(ConfirmResponse { recovery_codes }))
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum VerifyError {
+    #[error("request should have the pending two-factor token cookie")]
+    #[status_code(UNAUTHORIZED)]
+    NoPendingToken,
+
+    #[error("invalid/expired pending two-factor token")]
+    #[status_code(UNAUTHORIZED)]
+    InvalidPendingToken,
+
+    #[error("invalid code")]
+    #[status_code(BAD_REQUEST)]
+    InvalidCode,
+
+    #[error("uuid error ({0})")]
+    Uuid(#[from] uuid::Error),
+
+    #[error("user totp error ({0})")]
+    UserTotp(#[from] UserTotpError),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+
+    #[error("redis error ({0})")]
+    Redis(#[from] RedisError),
+}
+
+/// The second login step, accepts either the TOTP code or an unused recovery code
+pub async fn verify(
+    mut state: ServerState,
+    cookies: Cookies,
+    code: &str,
+) -> Result<(), VerifyError> {
+    let token = cookies
+        .get(TWO_FACTOR_TOKEN)
+        .map(|cookie| cookie.value().to_owned())
+        .ok_or(VerifyError::NoPendingToken)?;
+
+    let user_id = two_factor::get_pending_token(&state.redis, &token)
+        .await?
+        .ok_or(VerifyError::InvalidPendingToken)?;
+
+    let user = Query::find_user_by_id(&state.db, Uuid::from_str(&user_id)?)
+        .await?
+        .filter(|user| user.totp_enabled)
+        .ok_or(VerifyError::InvalidPendingToken)?;
+
+    let totp = user_totp(&state.totp_cipher, &user)?.ok_or(VerifyError::InvalidPendingToken)?;
+
+    // A TOTP code stays valid for the whole skew window, so it's accepted only once
+    let is_valid = match check_code(&totp, code, unix_time()) {
+        Some(totp_step) => Mutation::use_totp_step(&state.db, user.id, totp_step).await?,
+        None => Mutation::use_recovery_code(&state.db, user.id, &hash_recovery_code(code)).await?,
+    };
+
+    if !is_valid {
+        let attempts =
+            two_factor::increment_failed_attempts(&state.redis, &token, PENDING_TOKEN_EXPIRED)
+                .await?;
+
+        // Don't let the 6 digit codes be brute forced, the password is required again
+        if attempts >= MAX_FAILED_ATTEMPTS {
+            two_factor::remove_pending_token(&state.redis, &token).await?;
+            cookies.remove(Cookie::build(TWO_FACTOR_TOKEN).path("/").build());
+        }
+
+        return Err(VerifyError::InvalidCode);
+    }
+
+    two_factor::remove_pending_token(&state.redis, &token).await?;
+    cookies.remove(Cookie::build(TWO_FACTOR_TOKEN).path("/").build());
+
+    super::set_session_token(&mut state.random, &user.id, &state.redis, cookies).await?;
+    Ok(())
+}
+
+pub async fn verify_route(
+    State(state): State<ServerState>,
+    cookies: Cookies,
+    ValidatedJson(payload): ValidatedJson<CodePayload>,
+) -> Result<(), VerifyError> {
+    verify(state, cookies, &payload.code).await
+}
+
+#[cfg(test)]
+mod tests {
+    use rand_chacha::{rand_core::SeedableRng, ChaCha8Rng};
+
+    use super::*;
+
+    #[test]
+    fn test_recovery_code() {
+        let mut random = ChaCha8Rng::seed_from_u64(0);
+        let code = generate_recovery_code(&mut random);
+
+        assert_eq!(code.len(), RECOVERY_CODE_SIZE + 1);
+        assert_eq!(code.chars().nth(RECOVERY_CODE_SIZE / 2), Some('-'));
+        assert_ne!(code, generate_recovery_code(&mut random));
+
+        // Users can type the code without the dash and in lowercase
+        let typed = code.replace('-', " ").to_lowercase();
+        assert_eq!(hash_recovery_code(&code), hash_recovery_code(&typed));
+    }
+
+    #[test]
+    fn test_check_code() {
+        let totp = create_totp(vec![7; SECRET_SIZE], "a@a.com".to_owned()).unwrap();
+        let time = 1_700_000_000;
+        let step = (time / totp.step) as i64;
+
+        let code = totp.generate(time);
+        assert_eq!(check_code(&totp, &code, time), Some(step));
+        assert_eq!(check_code(&totp, &format!(" {code} "), time), Some(step));
+
+        // The previous and the next codes are accepted within the skew, with their own steps
+        let previous = totp.generate(time - totp.step);
+        assert_eq!(check_code(&totp, &previous, time), Some(step - 1));
+        let next = totp.generate(time + totp.step);
+        assert_eq!(check_code(&totp, &next, time), Some(step + 1));
+
+        let expired = totp.generate(time - 2 * totp.step);
+        assert_eq!(check_code(&totp, &expired, time), None);
+        assert_eq!(check_code(&totp, "12a456", time), None);
+    }
+
+    #[test]
+    fn test_cipher() {
+        let cipher = TotpCipher::new(&"11".repeat(32)).unwrap();
+        let user_id = Uuid::from_u128(1);
+
+        let encrypted = cipher.encrypt(&user_id, b"secret");
+        assert_eq!(
+            cipher.decrypt(&user_id, &encrypted).as_deref(),
+            Some(&b"secret"[..])
+        );
+        assert!(cipher.decrypt(&Uuid::from_u128(2), &encrypted).is_none());
+
+        assert!(TotpCipher::new("11").is_err());
+    }
+}
@@ -0,0 +1,26 @@
+use std::io;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+pub mod local;
+
+/// Storage of the uploaded files, the keys are generated by the server
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, key: &str, data: Vec<u8>) -> io::Result<()>;
+
+    /// `None` if there is no blob with the key
+    async fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Deleting a missing blob isn't an error
+    async fn delete(&self, key: &str) -> io::Result<()>;
+}
+
+pub fn attachment_key(attachment_id: Uuid) -> String {
+    format!("attachments/{attachment_id}")
+}
+
+pub fn thumbnail_key(attachment_id: Uuid) -> String {
+    format!("thumbnails/{attachment_id}")
+}
@@ -0,0 +1,386 @@
+use std::io::{self, Cursor};
+
+use api_error_derive::ApiError;
+use axum::{
+    extract::{multipart::MultipartError, Multipart, Path, State},
+    http::header::{
+        CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_SECURITY_POLICY, CONTENT_TYPE,
+        X_CONTENT_TYPE_OPTIONS,
+    },
+    response::{IntoResponse, Response},
+    Json,
+};
+use common::{
+    message::Attachment, ATTACHMENT_FILE_TYPES, MAX_ATTACHMENT_NAME_SIZE, MAX_ATTACHMENT_SIZE,
+};
+use entity::attachment;
+use image::{
+    io::{Limits, Reader as ImageReader},
+    ImageFormat, ImageOutputFormat,
+};
+use sea_orm::DbErr;
+use service::{
+    mutation::{CreateAttachmentData, Mutation},
+    query::Query,
+};
+use thiserror::Error;
+use tokio::task;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    blob::{attachment_key, thumbnail_key},
+    session::SessionContext,
+    state::ServerState,
+};
+
+/// The multipart boundaries and headers around the file
+pub const MAX_UPLOAD_BODY_SIZE: usize = MAX_ATTACHMENT_SIZE + 64 * 1024;
+
+const THUMBNAIL_SIZE: u32 = 320; // In pixels, the longer side
+const MAX_IMAGE_DIMENSION: u32 = 10_000; // Decompression bombs
+const THUMBNAIL_CONTENT_TYPE: &str = "image/png";
+const DEFAULT_ATTACHMENT_NAME: &str = "file";
+
+/// The attachments are never changed, so the browsers can keep them
+const ATTACHMENT_CACHE_CONTROL: &str = "private, max-age=604800, immutable";
+
+pub fn attachment_view(model: attachment::Model) -> Attachment {
+    Attachment {
+        id: model.id,
+        name: model.name,
+        content_type: model.content_type,
+        size: model.size.try_into().unwrap_or_default(),
+        has_thumbnail: model.has_thumbnail,
+    }
+}
+
+fn image_content_type(format: ImageFormat) -> Option<&'static str> {
+    match format {
+        ImageFormat::Gif => Some("image/gif"),
+        ImageFormat::Jpeg => Some("image/jpeg"),
+        ImageFormat::Png => Some("image/png"),
+        ImageFormat::WebP => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Only the last path component, without the control characters and truncated to the limit
+fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|ch| !ch.is_control())
+        .take(MAX_ATTACHMENT_NAME_SIZE)
+        .collect();
+
+    match name.trim() {
+        "" | "." | ".." => DEFAULT_ATTACHMENT_NAME.to_owned(),
+        name => name.to_owned(),
+    }
+}
+
+fn create_thumbnail(data: &[u8], format: ImageFormat) -> image::ImageResult<Vec<u8>> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+
+    let thumbnail = reader.decode()?.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+
+    let mut output = Cursor::new(Vec::new());
+    thumbnail.write_to(&mut output, ImageOutputFormat::Png)?;
+    Ok(output.into_inner())
+}
+
+pub struct Upload {
+    pub name: String,
+
+    /// As sent by the client, the images are detected by the content instead
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum UploadAttachmentError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("the file field is missing")]
+    #[status_code(BAD_REQUEST)]
+    MissingFile,
+
+    #[error("the file is too large")]
+    #[status_code(PAYLOAD_TOO_LARGE)]
+    TooLarge,
+
+    #[error("the file type isn't supported")]
+    #[status_code(UNSUPPORTED_MEDIA_TYPE)]
+    UnsupportedType,
+
+    #[error("the image can't be decoded")]
+    #[status_code(BAD_REQUEST)]
+    InvalidImage,
+
+    #[error("multipart error ({0})")]
+    #[status_code(BAD_REQUEST)]
+    Multipart(#[from] MultipartError),
+
+    #[error("blob store error ({0})")]
+    Blob(#[from] io::Error),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// Stores a pending upload, it's attached to the message by its id on send
+pub async fn upload_attachment(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    upload: Upload,
+) -> Result<Attachment, UploadAttachmentError> {
+    if upload.data.len() > MAX_ATTACHMENT_SIZE {
+        return Err(UploadAttachmentError::TooLarge);
+    }
+
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(UploadAttachmentError::NotMember)?;
+
+    let image_format = image::guess_format(&upload.data)
+        .ok()
+        .filter(|format| image_content_type(*format).is_some());
+
+    let content_type = match image_format {
+        Some(format) => image_content_type(format).unwrap_or_default().to_owned(),
+        None => {
+            let content_type = upload
+                .content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase();
+
+            if !ATTACHMENT_FILE_TYPES.contains(&content_type.as_str()) {
+                return Err(UploadAttachmentError::UnsupportedType);
+            }
+
+            content_type
+        }
+    };
+
+    let data = upload.data;
+    let (data, thumbnail) = match image_format {
+        Some(format) => {
+            let (data, thumbnail) = task::spawn_blocking(move || {
+                let thumbnail = create_thumbnail(&data, format);
+                (data, thumbnail)
+            })
+            .await
+            .map_err(|_| UploadAttachmentError::InvalidImage)?;
+
+            let thumbnail = thumbnail.map_err(|_| UploadAttachmentError::InvalidImage)?;
+            (data, Some(thumbnail))
+        }
+        None => (data, None),
+    };
+
+    let attachment = Mutation::create_attachment(
+        &state.db,
+        CreateAttachmentData {
+            channel_id,
+            uploader_id: user_id,
+            name: sanitize_name(&upload.name),
+            content_type,
+            size: data.len().try_into().unwrap_or(i64::MAX),
+            has_thumbnail: thumbnail.is_some(),
+        },
+    )
+    .await?;
+
+    let mut stored = state.blobs.put(&attachment_key(attachment.id), data).await;
+    if let (Ok(()), Some(thumbnail)) = (&stored, thumbnail) {
+        stored = state
+            .blobs
+            .put(&thumbnail_key(attachment.id), thumbnail)
+            .await;
+    }
+
+    if let Err(err) = stored {
+        // The row without the blobs would be a broken download
+        if let Err(err) = Mutation::delete_attachments(&state.db, vec![attachment.id]).await {
+            error!(attachment_id = %attachment.id, "failed to delete the attachment ({err})");
+        }
+
+        return Err(err.into());
+    }
+
+    Ok(attachment_view(attachment))
+}
+
+pub async fn upload_attachment_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> Result<Json<Attachment>, UploadAttachmentError> {
+    while let Some(mut field) = multipart.next_field().await? {
+        if field.name() != Some("file") {
+            continue;
+        }
+
+        let name = field.file_name().unwrap_or_default().to_owned();
+        let content_type = field.content_type().unwrap_or_default().to_owned();
+
+        // Stops reading as soon as the limit is exceeded
+        let mut data = Vec::new();
+        while let Some(chunk) = field.chunk().await? {
+            if data.len() + chunk.len() > MAX_ATTACHMENT_SIZE {
+                return Err(UploadAttachmentError::TooLarge);
+            }
+
+            data.extend_from_slice(&chunk);
+        }
+
+        let upload = Upload {
+            name,
+            content_type,
+            data,
+        };
+
+        return upload_attachment(state, session.user_id, channel_id, upload)
+            .await
+            .map(Json);
+    }
+
+    Err(UploadAttachmentError::MissingFile)
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum DownloadAttachmentError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("attachment not found")]
+    #[status_code(NOT_FOUND)]
+    AttachmentNotFound,
+
+    #[error("blob store error ({0})")]
+    Blob(#[from] io::Error),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// The pending uploads are visible only to the uploader,
+/// the attachments of the deleted messages to nobody
+pub async fn download_attachment(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    attachment_id: Uuid,
+    thumbnail: bool,
+) -> Result<Response, DownloadAttachmentError> {
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(DownloadAttachmentError::NotMember)?;
+
+    let attachment = Query::find_attachment(&state.db, channel_id, attachment_id)
+        .await?
+        .filter(|attachment| !thumbnail || attachment.has_thumbnail)
+        .ok_or(DownloadAttachmentError::AttachmentNotFound)?;
+
+    match attachment.message_id {
+        Some(message_id) => {
+            Query::find_message(&state.db, channel_id, message_id)
+                .await?
+                .filter(|message| message.deleted_at.is_none())
+                .ok_or(DownloadAttachmentError::AttachmentNotFound)?;
+        }
+        None if attachment.uploader_id != user_id => {
+            return Err(DownloadAttachmentError::AttachmentNotFound);
+        }
+        None => (),
+    }
+
+    let key = match thumbnail {
+        true => thumbnail_key(attachment.id),
+        false => attachment_key(attachment.id),
+    };
+
+    let data = state
+        .blobs
+        .get(&key)
+        .await?
+        .ok_or(DownloadAttachmentError::AttachmentNotFound)?;
+
+    let (content_type, disposition) = match thumbnail {
+        true => (THUMBNAIL_CONTENT_TYPE.to_owned(), "inline"),
+        false if attachment.content_type.starts_with("image/") => {
+            (attachment.content_type, "inline")
+        }
+        false => (attachment.content_type, "attachment"),
+    };
+
+    let headers = [
+        (CONTENT_TYPE, content_type),
+        (
+            CONTENT_DISPOSITION,
+            format!(
+                "{disposition}; filename*=UTF-8''{}",
+                urlencoding::encode(&attachment.name)
+            ),
+        ),
+        (X_CONTENT_TYPE_OPTIONS, "nosniff".to_owned()),
+        (
+            CONTENT_SECURITY_POLICY,
+            "default-src 'none'; sandbox".to_owned(),
+        ),
+        (CACHE_CONTROL, ATTACHMENT_CACHE_CONTROL.to_owned()),
+    ];
+
+    Ok((headers, data).into_response())
+}
+
+pub async fn download_attachment_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, DownloadAttachmentError> {
+    download_attachment(state, session.user_id, channel_id, attachment_id, false).await
+}
+
+pub async fn download_thumbnail_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<Response, DownloadAttachmentError> {
+    download_attachment(state, session.user_id, channel_id, attachment_id, true).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_name() {
+        assert_eq!(sanitize_name("photo.png"), "photo.png");
+        assert_eq!(sanitize_name("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_name("C:\\Users\\a\\report.pdf"), "report.pdf");
+        assert_eq!(sanitize_name("a\nb\u{0}.txt"), "ab.txt");
+        assert_eq!(sanitize_name(""), DEFAULT_ATTACHMENT_NAME);
+        assert_eq!(sanitize_name("dir/.."), DEFAULT_ATTACHMENT_NAME);
+        assert_eq!(
+            sanitize_name(&"a".repeat(MAX_ATTACHMENT_NAME_SIZE + 1)).len(),
+            MAX_ATTACHMENT_NAME_SIZE
+        );
+    }
+}
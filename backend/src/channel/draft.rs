@@ -0,0 +1,105 @@
+use api_error_derive::ApiError;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use common::{message::Draft, MAX_MESSAGE_CONTENT_SIZE};
+use sea_orm::DbErr;
+use serde::Deserialize;
+use service::{mutation::Mutation, query::Query};
+use thiserror::Error;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{session::SessionContext, state::ServerState, validator::ValidatedJson};
+
+#[derive(ApiError, Debug, Error)]
+pub enum DraftError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+async fn check_member(
+    state: &ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<(), DraftError> {
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(DraftError::NotMember)?;
+
+    Ok(())
+}
+
+pub async fn draft(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<Option<Draft>, DraftError> {
+    check_member(&state, user_id, channel_id).await?;
+
+    let draft = Query::find_draft(&state.db, user_id, channel_id).await?;
+
+    Ok(draft.map(|draft| Draft {
+        content: draft.content,
+        updated_at: draft.updated_at,
+    }))
+}
+
+pub async fn draft_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+) -> Result<Json<Option<Draft>>, DraftError> {
+    draft(state, session.user_id, channel_id).await.map(Json)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SaveDraftPayload {
+    /// The empty content deletes the draft
+    #[validate(length(max = "MAX_MESSAGE_CONTENT_SIZE"))]
+    pub content: String,
+}
+
+/// Replaces the draft, so the repeated saves while typing are cheap
+pub async fn save_draft(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    payload: SaveDraftPayload,
+) -> Result<(), DraftError> {
+    check_member(&state, user_id, channel_id).await?;
+
+    Ok(Mutation::save_draft(&state.db, user_id, channel_id, payload.content).await?)
+}
+
+pub async fn save_draft_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<SaveDraftPayload>,
+) -> Result<(), DraftError> {
+    save_draft(state, session.user_id, channel_id, payload).await
+}
+
+pub async fn delete_draft(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<(), DraftError> {
+    check_member(&state, user_id, channel_id).await?;
+
+    Ok(Mutation::delete_draft(&state.db, user_id, channel_id).await?)
+}
+
+pub async fn delete_draft_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+) -> Result<(), DraftError> {
+    delete_draft(state, session.user_id, channel_id).await
+}
@@ -0,0 +1,244 @@
+use api_error_derive::ApiError;
+use axum::{
+    extract::{Path, State},
+    routing::post,
+    Json, Router,
+};
+use chrono::{Duration, Utc};
+use common::{
+    channel::{Channel, ChannelInvite, CreatedInvite, InviteUse},
+    MAX_INVITE_EXPIRY_DAYS, MAX_INVITE_USES,
+};
+use entity::{channel_invite, channel_invite_use, sea_orm_active_enums::ChannelRole};
+use rand_chacha::rand_core::{OsRng, RngCore};
+use sea_orm::DbErr;
+use serde::Deserialize;
+use service::{
+    mutation::{CreateInviteData, JoinByInviteError, Mutation},
+    query::Query,
+};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{session::SessionContext, state::ServerState, validator::ValidatedJson};
+
+const INVITE_TOKEN_SIZE: usize = 32;
+
+/// The join by the token, the channel isn't known before it
+pub fn routes() -> Router<ServerState> {
+    Router::new().route("/:token/join", post(join_by_invite_route))
+}
+
+// The tokens are random, so a slow password hash isn't needed
+fn hash_invite_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn invite_view(
+    (invite, uses): (channel_invite::Model, Vec<channel_invite_use::Model>),
+) -> ChannelInvite {
+    ChannelInvite {
+        id: invite.id,
+        created_by: invite.created_by,
+        created_at: invite.created_at,
+        expires_at: invite.expires_at,
+        max_uses: invite.max_uses.map(i32::unsigned_abs),
+        use_count: invite.use_count.unsigned_abs(),
+        revoked_at: invite.revoked_at,
+        uses: uses
+            .into_iter()
+            .map(|invite_use| InviteUse {
+                user_id: invite_use.user_id,
+                used_at: invite_use.used_at,
+            })
+            .collect(),
+    }
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum InviteError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("only the channel owner can manage the invites")]
+    #[status_code(FORBIDDEN)]
+    NotOwner,
+
+    #[error("the public channels are joined without an invite")]
+    #[status_code(BAD_REQUEST)]
+    PublicChannel,
+
+    #[error("invite not found or already revoked")]
+    #[status_code(NOT_FOUND)]
+    InviteNotFound,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// Checks that the user owns the private channel
+async fn check_owner(
+    state: &ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<(), InviteError> {
+    let member = Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(InviteError::NotMember)?;
+    if member.role != ChannelRole::Owner {
+        return Err(InviteError::NotOwner);
+    }
+
+    // The membership references the channel
+    let channel = Query::find_channel_by_id(&state.db, channel_id)
+        .await?
+        .ok_or(InviteError::NotMember)?;
+    if !channel.is_private {
+        return Err(InviteError::PublicChannel);
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateInvitePayload {
+    /// Never expires without it
+    #[validate(range(min = 1, max = "MAX_INVITE_EXPIRY_DAYS"))]
+    pub expires_in_days: Option<u32>,
+
+    /// Unlimited without it
+    #[validate(range(min = 1, max = "MAX_INVITE_USES"))]
+    pub max_uses: Option<u32>,
+}
+
+/// The token is in the response only, the link can't be shown again
+pub async fn create_invite(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    payload: CreateInvitePayload,
+) -> Result<CreatedInvite, InviteError> {
+    check_owner(&state, user_id, channel_id).await?;
+
+    let mut random = [0u8; INVITE_TOKEN_SIZE];
+    OsRng.fill_bytes(&mut random);
+    let token = hex::encode(random);
+
+    let invite = Mutation::create_invite(
+        &state.db,
+        CreateInviteData {
+            channel_id,
+            created_by: user_id,
+            token_hash: hash_invite_token(&token),
+            expires_at: payload
+                .expires_in_days
+                .map(|days| Utc::now().naive_utc() + Duration::days(days.into())),
+            max_uses: payload.max_uses.map(|max_uses| max_uses as i32),
+        },
+    )
+    .await?;
+
+    Ok(CreatedInvite {
+        invite: invite_view((invite, Vec::new())),
+        token,
+    })
+}
+
+pub async fn create_invite_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CreateInvitePayload>,
+) -> Result<Json<CreatedInvite>, InviteError> {
+    create_invite(state, session.user_id, channel_id, payload)
+        .await
+        .map(Json)
+}
+
+/// All the invites of the channel, including the revoked and the used up ones
+pub async fn invites(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<Vec<ChannelInvite>, InviteError> {
+    check_owner(&state, user_id, channel_id).await?;
+
+    let invites = Query::find_channel_invites(&state.db, channel_id).await?;
+    Ok(invites.into_iter().map(invite_view).collect())
+}
+
+pub async fn invites_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+) -> Result<Json<Vec<ChannelInvite>>, InviteError> {
+    invites(state, session.user_id, channel_id).await.map(Json)
+}
+
+/// The members who joined with the invite stay in the channel
+pub async fn revoke_invite(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    invite_id: Uuid,
+) -> Result<(), InviteError> {
+    check_owner(&state, user_id, channel_id).await?;
+
+    if !Mutation::revoke_invite(&state.db, channel_id, invite_id).await? {
+        return Err(InviteError::InviteNotFound);
+    }
+
+    Ok(())
+}
+
+pub async fn revoke_invite_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, invite_id)): Path<(Uuid, Uuid)>,
+) -> Result<(), InviteError> {
+    revoke_invite(state, session.user_id, channel_id, invite_id).await
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum InviteJoinError {
+    #[error("invite not found, revoked, expired or used up")]
+    #[status_code(NOT_FOUND)]
+    InviteNotFound,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+impl From<JoinByInviteError> for InviteJoinError {
+    fn from(err: JoinByInviteError) -> Self {
+        match err {
+            JoinByInviteError::Db(err) => Self::Db(err),
+            JoinByInviteError::InviteNotFound => Self::InviteNotFound,
+        }
+    }
+}
+
+/// Adds the user to the channel of the invite, a member just gets the channel back
+pub async fn join_by_invite(
+    state: ServerState,
+    user_id: Uuid,
+    token: String,
+) -> Result<Channel, InviteJoinError> {
+    let channel =
+        Mutation::join_channel_by_invite(&state.db, &hash_invite_token(&token), user_id).await?;
+
+    Ok(super::channel_view(channel))
+}
+
+pub async fn join_by_invite_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(token): Path<String>,
+) -> Result<Json<Channel>, InviteJoinError> {
+    join_by_invite(state, session.user_id, token)
+        .await
+        .map(Json)
+}
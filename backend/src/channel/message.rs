@@ -0,0 +1,657 @@
+use std::collections::HashMap;
+
+use api_error_derive::ApiError;
+use axum::{
+    extract::{Path, Query as QueryParams, State},
+    Json,
+};
+use common::{
+    message::{
+        Attachment, Message, MessageKind as MessageKindView, MessageRevision, Reaction, Thread,
+    },
+    realtime::ChannelEvent,
+    DEFAULT_MESSAGE_HISTORY_LIMIT, MAX_MESSAGE_ATTACHMENTS, MAX_MESSAGE_CONTENT_SIZE,
+    MAX_MESSAGE_HISTORY_LIMIT,
+};
+use entity::{message, sea_orm_active_enums::MessageKind};
+use sea_orm::{DbConn, DbErr};
+use serde::Deserialize;
+use service::{
+    markdown,
+    mutation::{
+        CreateMessageData, CreateMessageError, EditMessageData, EditMessageError, Mutation,
+    },
+    query::Query,
+};
+use thiserror::Error;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::attachment::attachment_view;
+use crate::{
+    link_preview, realtime, session::SessionContext, state::ServerState, validator::ValidatedJson,
+};
+
+/// The deleted messages are returned as tombstones without the content
+pub fn message_view(model: message::Model) -> Message {
+    let (content, content_html) = match model.deleted_at {
+        Some(_) => (String::new(), String::new()),
+        None => {
+            // The messages sent before the rendering was stored
+            let content_html = model
+                .content_html
+                .unwrap_or_else(|| markdown::render(&model.content).html);
+
+            (model.content, content_html)
+        }
+    };
+
+    Message {
+        id: model.id,
+        channel_id: model.channel_id,
+        seq: model.seq,
+        sender_id: model.sender_id,
+        kind: match model.kind {
+            MessageKind::User => MessageKindView::User,
+            MessageKind::System => MessageKindView::System,
+        },
+        content,
+        content_html,
+        created_at: model.created_at,
+        edited_at: model.edited_at,
+        deleted_at: model.deleted_at,
+        parent_id: model.parent_id,
+        reply_count: model.reply_count.try_into().unwrap_or_default(),
+        last_reply_at: model.last_reply_at,
+        reactions: Vec::new(),
+        attachments: Vec::new(),
+        link_previews: Vec::new(),
+    }
+}
+
+/// Attaches the files, the link previews and the reaction counts as seen by the user
+pub(crate) async fn messages_view(
+    db: &DbConn,
+    user_id: Uuid,
+    models: Vec<message::Model>,
+) -> Result<Vec<Message>, DbErr> {
+    let message_ids: Vec<Uuid> = models.iter().map(|model| model.id).collect();
+
+    let mut reactions: HashMap<Uuid, Vec<Reaction>> = HashMap::new();
+    for count in Query::find_reaction_counts(db, &message_ids, user_id).await? {
+        reactions
+            .entry(count.message_id)
+            .or_default()
+            .push(Reaction {
+                emoji: count.emoji,
+                count: count.count.try_into().unwrap_or_default(),
+                reacted: count.reacted,
+            });
+    }
+
+    let mut attachments: HashMap<Uuid, Vec<Attachment>> = HashMap::new();
+    for attachment in Query::find_message_attachments(db, &message_ids).await? {
+        if let Some(message_id) = attachment.message_id {
+            attachments
+                .entry(message_id)
+                .or_default()
+                .push(attachment_view(attachment));
+        }
+    }
+
+    let contents: Vec<&str> = models
+        .iter()
+        .map(|model| match model.deleted_at {
+            Some(_) => "",
+            None => model.content.as_str(),
+        })
+        .collect();
+    let link_previews = link_preview::find_previews(db, &contents).await?;
+
+    Ok(models
+        .into_iter()
+        .zip(link_previews)
+        .map(|(model, link_previews)| {
+            let reactions = reactions.remove(&model.id).unwrap_or_default();
+            let attachments = attachments.remove(&model.id).unwrap_or_default();
+            let mut message = message_view(model);
+            if !message.is_deleted() {
+                message.reactions = reactions;
+                message.attachments = attachments;
+                message.link_previews = link_previews;
+            }
+
+            message
+        })
+        .collect())
+}
+
+#[derive(Deserialize, Validate)]
+pub struct MessagesRequest {
+    /// The last message of the previous page
+    pub before: Option<Uuid>,
+
+    #[validate(range(min = 1, max = "MAX_MESSAGE_HISTORY_LIMIT"))]
+    pub limit: Option<u64>,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum MessagesError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("validation error")]
+    #[status_code(BAD_REQUEST)]
+    ValidationError(#[from] validator::ValidationErrors),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// The channel history, the newest messages first
+pub async fn messages(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    request: MessagesRequest,
+) -> Result<Vec<Message>, MessagesError> {
+    request.validate()?;
+
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(MessagesError::NotMember)?;
+
+    let messages = Query::find_channel_messages(
+        &state.db,
+        channel_id,
+        request.before,
+        request.limit.unwrap_or(DEFAULT_MESSAGE_HISTORY_LIMIT),
+    )
+    .await?;
+
+    Ok(messages_view(&state.db, user_id, messages).await?)
+}
+
+pub async fn messages_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    QueryParams(request): QueryParams<MessagesRequest>,
+) -> Result<Json<Vec<Message>>, MessagesError> {
+    messages(state, session.user_id, channel_id, request)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CatchUpRequest {
+    /// The newest number the client has
+    pub after_seq: i64,
+
+    #[validate(range(min = 1, max = "MAX_MESSAGE_HISTORY_LIMIT"))]
+    pub limit: Option<u64>,
+}
+
+/// The messages and replies sent after `after_seq`, the oldest first, for the catch-up after
+/// a reconnect. The edits and deletions of the older messages aren't included.
+pub async fn catch_up(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    request: CatchUpRequest,
+) -> Result<Vec<Message>, MessagesError> {
+    request.validate()?;
+
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(MessagesError::NotMember)?;
+
+    let messages = Query::find_channel_messages_after(
+        &state.db,
+        channel_id,
+        request.after_seq,
+        request.limit.unwrap_or(DEFAULT_MESSAGE_HISTORY_LIMIT),
+    )
+    .await?;
+
+    Ok(messages_view(&state.db, user_id, messages).await?)
+}
+
+pub async fn catch_up_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    QueryParams(request): QueryParams<CatchUpRequest>,
+) -> Result<Json<Vec<Message>>, MessagesError> {
+    catch_up(state, session.user_id, channel_id, request)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct EditMessagePayload {
+    #[validate(length(min = 1, max = "MAX_MESSAGE_CONTENT_SIZE"))]
+    pub content: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SendMessagePayload {
+    /// Can be empty if there are attachments
+    #[validate(length(max = "MAX_MESSAGE_CONTENT_SIZE"))]
+    pub content: String,
+
+    /// The root message of the thread to reply to
+    pub parent_id: Option<Uuid>,
+
+    /// The uploads from `upload_attachment`
+    #[serde(default)]
+    #[validate(length(max = "MAX_MESSAGE_ATTACHMENTS"))]
+    pub attachment_ids: Vec<Uuid>,
+
+    /// Generated by the client once per message, the retries with it get the sent message
+    pub nonce: Option<Uuid>,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum SendMessageError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("the thread root message not found")]
+    #[status_code(BAD_REQUEST)]
+    ParentNotFound,
+
+    #[error("the message has neither content nor attachments")]
+    #[status_code(BAD_REQUEST)]
+    EmptyMessage,
+
+    #[error("the attachment not found or already sent")]
+    #[status_code(BAD_REQUEST)]
+    AttachmentNotFound,
+
+    #[error("the nonce was already used for a message in another channel")]
+    #[status_code(CONFLICT)]
+    NonceReused,
+
+    #[error("create message error ({0})")]
+    CreateMessage(CreateMessageError),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+impl From<CreateMessageError> for SendMessageError {
+    fn from(err: CreateMessageError) -> Self {
+        match err {
+            CreateMessageError::ParentNotFound => Self::ParentNotFound,
+            CreateMessageError::AttachmentNotFound => Self::AttachmentNotFound,
+            other => Self::CreateMessage(other),
+        }
+    }
+}
+
+pub async fn send_message(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    payload: SendMessagePayload,
+) -> Result<Message, SendMessageError> {
+    if payload.content.trim().is_empty() && payload.attachment_ids.is_empty() {
+        return Err(SendMessageError::EmptyMessage);
+    }
+
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(SendMessageError::NotMember)?;
+
+    if let Some(nonce) = payload.nonce {
+        if let Some(message) = find_sent(&state, user_id, channel_id, nonce).await? {
+            return Ok(message);
+        }
+    }
+
+    let result = Mutation::create_message(
+        &state.db,
+        CreateMessageData {
+            id: None,
+            nonce: payload.nonce,
+            sender_id: user_id,
+            channel_id,
+            urls: link_preview::extract_urls(&payload.content),
+            content: payload.content,
+            parent_id: payload.parent_id,
+            attachment_ids: payload.attachment_ids,
+        },
+    )
+    .await;
+
+    let message = match (result, payload.nonce) {
+        (Err(CreateMessageError::DuplicateNonce), Some(nonce)) => {
+            // The concurrent retry published it
+            return find_sent(&state, user_id, channel_id, nonce)
+                .await?
+                .ok_or(SendMessageError::NonceReused);
+        }
+        (result, _) => result?,
+    };
+
+    // The channel composer keeps the draft, the thread replies have none
+    if message.parent_id.is_none() {
+        Mutation::delete_draft(&state.db, user_id, channel_id).await?;
+    }
+
+    Ok(publish_created(&state, message).await?)
+}
+
+/// The message of an earlier attempt, as it was returned to it
+async fn find_sent(
+    state: &ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    nonce: Uuid,
+) -> Result<Option<Message>, SendMessageError> {
+    let Some(model) = Query::find_message_by_nonce(&state.db, user_id, nonce).await? else {
+        return Ok(None);
+    };
+
+    if model.channel_id != channel_id {
+        return Err(SendMessageError::NonceReused);
+    }
+
+    let message = messages_view(&state.db, user_id, vec![model])
+        .await?
+        .remove(0);
+
+    Ok(Some(message))
+}
+
+/// Sends the new message to the channel as seen by the sender, then unfurls its links
+pub(crate) async fn publish_created(
+    state: &ServerState,
+    model: message::Model,
+) -> Result<Message, DbErr> {
+    let message = messages_view(&state.db, model.sender_id, vec![model])
+        .await?
+        .remove(0);
+    realtime::publish(
+        &state.redis,
+        message.channel_id,
+        ChannelEvent::MessageCreated(message.clone()),
+    )
+    .await;
+
+    link_preview::spawn_unfurl(
+        state.clone(),
+        message.channel_id,
+        message.id,
+        &message.content,
+    );
+
+    Ok(message)
+}
+
+pub async fn send_message_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<SendMessagePayload>,
+) -> Result<Json<Message>, SendMessageError> {
+    send_message(state, session.user_id, channel_id, payload)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ThreadRequest {
+    /// The last reply of the previous page
+    pub after: Option<Uuid>,
+
+    #[validate(range(min = 1, max = "MAX_MESSAGE_HISTORY_LIMIT"))]
+    pub limit: Option<u64>,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum ThreadError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("the thread root message not found")]
+    #[status_code(NOT_FOUND)]
+    RootNotFound,
+
+    #[error("validation error")]
+    #[status_code(BAD_REQUEST)]
+    ValidationError(#[from] validator::ValidationErrors),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+pub async fn thread(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    root_id: Uuid,
+    request: ThreadRequest,
+) -> Result<Thread, ThreadError> {
+    request.validate()?;
+
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(ThreadError::NotMember)?;
+
+    let root = Query::find_message(&state.db, channel_id, root_id)
+        .await?
+        .filter(|message| message.parent_id.is_none())
+        .ok_or(ThreadError::RootNotFound)?;
+
+    let replies = Query::find_thread_replies(
+        &state.db,
+        &root,
+        request.after,
+        request.limit.unwrap_or(DEFAULT_MESSAGE_HISTORY_LIMIT),
+    )
+    .await?;
+
+    let messages = [root].into_iter().chain(replies).collect();
+    let mut messages = messages_view(&state.db, user_id, messages)
+        .await?
+        .into_iter();
+
+    Ok(Thread {
+        root: messages.next().expect("the thread root"),
+        replies: messages.collect(),
+    })
+}
+
+pub async fn thread_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+    QueryParams(request): QueryParams<ThreadRequest>,
+) -> Result<Json<Thread>, ThreadError> {
+    thread(state, session.user_id, channel_id, message_id, request)
+        .await
+        .map(Json)
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum EditError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("message not found")]
+    #[status_code(NOT_FOUND)]
+    MessageNotFound,
+
+    #[error("only the sender can edit the message")]
+    #[status_code(FORBIDDEN)]
+    NotSender,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+impl From<EditMessageError> for EditError {
+    fn from(err: EditMessageError) -> Self {
+        match err {
+            EditMessageError::Db(err) => Self::Db(err),
+            EditMessageError::MessageNotFound => Self::MessageNotFound,
+            EditMessageError::NotSender => Self::NotSender,
+        }
+    }
+}
+
+pub async fn edit_message(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+    payload: EditMessagePayload,
+) -> Result<Message, EditError> {
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(EditError::NotMember)?;
+
+    let message = Mutation::edit_message(
+        &state.db,
+        EditMessageData {
+            message_id,
+            channel_id,
+            editor_id: user_id,
+            urls: link_preview::extract_urls(&payload.content),
+            content: payload.content,
+        },
+    )
+    .await?;
+
+    let message = message_view(message);
+    realtime::publish(
+        &state.redis,
+        channel_id,
+        ChannelEvent::MessageEdited(message.clone()),
+    )
+    .await;
+
+    link_preview::spawn_unfurl(state, channel_id, message.id, &message.content);
+
+    Ok(message)
+}
+
+pub async fn edit_message_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<EditMessagePayload>,
+) -> Result<Json<Message>, EditError> {
+    edit_message(state, session.user_id, channel_id, message_id, payload)
+        .await
+        .map(Json)
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum DeleteError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("message not found")]
+    #[status_code(NOT_FOUND)]
+    MessageNotFound,
+
+    #[error("only the sender and the channel moderators can delete the message")]
+    #[status_code(FORBIDDEN)]
+    NotAllowed,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+pub async fn delete_message(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+) -> Result<(), DeleteError> {
+    let member = Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(DeleteError::NotMember)?;
+
+    // The system messages are a part of the channel history
+    let message = Query::find_message(&state.db, channel_id, message_id)
+        .await?
+        .filter(|message| message.deleted_at.is_none() && message.kind == MessageKind::User)
+        .ok_or(DeleteError::MessageNotFound)?;
+
+    if message.sender_id != user_id && !super::is_moderator(&member.role) {
+        return Err(DeleteError::NotAllowed);
+    }
+
+    // Someone else could delete it in between
+    let message = Mutation::delete_message(&state.db, message.id, user_id)
+        .await?
+        .ok_or(DeleteError::MessageNotFound)?;
+
+    realtime::publish(
+        &state.redis,
+        channel_id,
+        ChannelEvent::MessageDeleted(message_view(message)),
+    )
+    .await;
+
+    Ok(())
+}
+
+pub async fn delete_message_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<(), DeleteError> {
+    delete_message(state, session.user_id, channel_id, message_id).await
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum MessageRevisionsError {
+    #[error("only the channel admins can see the edit history")]
+    #[status_code(FORBIDDEN)]
+    NotAdmin,
+
+    #[error("message not found")]
+    #[status_code(NOT_FOUND)]
+    MessageNotFound,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// The previous contents of the message, the oldest first
+pub async fn message_revisions_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<MessageRevision>>, MessageRevisionsError> {
+    Query::find_channel_member(&state.db, channel_id, session.user_id)
+        .await?
+        .filter(|member| super::is_admin(&member.role))
+        .ok_or(MessageRevisionsError::NotAdmin)?;
+
+    let message = Query::find_message(&state.db, channel_id, message_id)
+        .await?
+        .ok_or(MessageRevisionsError::MessageNotFound)?;
+
+    let revisions = Query::find_message_revisions(&state.db, message.id).await?;
+
+    Ok(Json(
+        revisions
+            .into_iter()
+            .map(|revision| MessageRevision {
+                content: revision.content,
+                created_at: revision.created_at,
+            })
+            .collect(),
+    ))
+}
@@ -0,0 +1,370 @@
+use api_error_derive::ApiError;
+use axum::{
+    extract::{DefaultBodyLimit, Path, Query as QueryParams, State},
+    routing::{delete, get, patch, post, put},
+    Json, Router,
+};
+use common::{
+    channel::{Channel, ChannelDetails, ChannelMember, ChannelRole as ChannelRoleView},
+    MAX_CHANNEL_NAME_SIZE, MAX_USER_NAME_SIZE,
+};
+use entity::{channel, channel_member, sea_orm_active_enums::ChannelRole, user};
+use sea_orm::DbErr;
+use serde::Deserialize;
+use service::{mutation::Mutation, query::Query};
+use thiserror::Error;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{session::SessionContext, state::ServerState, validator::ValidatedJson};
+
+pub mod attachment;
+pub mod draft;
+pub mod invite;
+pub mod message;
+pub mod pin;
+pub mod profile;
+pub mod reaction;
+pub mod read;
+pub mod retention;
+pub mod scheduled;
+
+const MEMBER_SUGGESTIONS_LIMIT: u64 = 10;
+
+pub fn routes() -> Router<ServerState> {
+    Router::new()
+        .route("/", get(read::channels_route).post(create_channel_route))
+        .route("/:channel_id", get(channel_route))
+        .route("/:channel_id/join", post(join_channel_route))
+        .route("/:channel_id/profile", put(profile::set_profile_route))
+        .route(
+            "/:channel_id/invites",
+            get(invite::invites_route).post(invite::create_invite_route),
+        )
+        .route(
+            "/:channel_id/invites/:invite_id",
+            delete(invite::revoke_invite_route),
+        )
+        .route("/:channel_id/read", post(read::mark_read_route))
+        .route(
+            "/:channel_id/retention",
+            put(retention::set_retention_route),
+        )
+        .route(
+            "/:channel_id/legal-hold",
+            put(retention::set_legal_hold_route),
+        )
+        .route(
+            "/:channel_id/draft",
+            get(draft::draft_route)
+                .put(draft::save_draft_route)
+                .delete(draft::delete_draft_route),
+        )
+        .route(
+            "/:channel_id/messages",
+            get(message::messages_route).post(message::send_message_route),
+        )
+        .route("/:channel_id/catch-up", get(message::catch_up_route))
+        .route(
+            "/:channel_id/messages/:message_id",
+            patch(message::edit_message_route).delete(message::delete_message_route),
+        )
+        .route(
+            "/:channel_id/messages/:message_id/revisions",
+            get(message::message_revisions_route),
+        )
+        .route(
+            "/:channel_id/messages/:message_id/thread",
+            get(message::thread_route),
+        )
+        .route(
+            "/:channel_id/messages/:message_id/pin",
+            put(pin::pin_message_route).delete(pin::unpin_message_route),
+        )
+        .route(
+            "/:channel_id/messages/:message_id/reactions/:emoji",
+            put(reaction::add_reaction_route).delete(reaction::remove_reaction_route),
+        )
+        .route(
+            "/:channel_id/scheduled",
+            get(scheduled::scheduled_messages_route).post(scheduled::schedule_message_route),
+        )
+        .route(
+            "/:channel_id/scheduled/:scheduled_id",
+            patch(scheduled::edit_scheduled_message_route)
+                .delete(scheduled::cancel_scheduled_message_route),
+        )
+        .route(
+            "/:channel_id/attachments",
+            post(attachment::upload_attachment_route)
+                .layer(DefaultBodyLimit::max(attachment::MAX_UPLOAD_BODY_SIZE)),
+        )
+        .route(
+            "/:channel_id/attachments/:attachment_id",
+            get(attachment::download_attachment_route),
+        )
+        .route(
+            "/:channel_id/attachments/:attachment_id/thumbnail",
+            get(attachment::download_thumbnail_route),
+        )
+        .route("/:channel_id/members", get(members_route))
+        .route("/:channel_id/members/search", get(search_members_route))
+}
+
+/// Can manage the channel and see the message history
+pub fn is_admin(role: &ChannelRole) -> bool {
+    matches!(role, ChannelRole::Owner | ChannelRole::Admin)
+}
+
+/// Can moderate the messages of the other members
+pub fn is_moderator(role: &ChannelRole) -> bool {
+    is_admin(role) || matches!(role, ChannelRole::Moderator)
+}
+
+fn role_view(role: ChannelRole) -> ChannelRoleView {
+    match role {
+        ChannelRole::Owner => ChannelRoleView::Owner,
+        ChannelRole::Admin => ChannelRoleView::Admin,
+        ChannelRole::Moderator => ChannelRoleView::Moderator,
+        ChannelRole::Member => ChannelRoleView::Member,
+    }
+}
+
+/// The members without the user are skipped
+fn member_view(
+    (member, user): (channel_member::Model, Option<user::Model>),
+) -> Option<ChannelMember> {
+    Some(ChannelMember {
+        user_id: member.user_id,
+        name: user?.name,
+        role: role_view(member.role),
+    })
+}
+
+pub(crate) fn channel_view(model: channel::Model) -> Channel {
+    Channel {
+        id: model.id,
+        name: model.name,
+        created_at: model.created_at,
+        is_private: model.is_private,
+        topic: model.topic,
+        description: model.description,
+        icon: model.icon,
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct CreateChannelPayload {
+    #[validate(length(min = 1, max = "MAX_CHANNEL_NAME_SIZE"))]
+    pub name: String,
+
+    #[serde(default)]
+    pub is_private: bool,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum CreateChannelError {
+    #[error("channel with the same name already exists")]
+    #[status_code(BAD_REQUEST)]
+    ChannelWithSameNameAlreadyExists,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+pub async fn create_channel(
+    state: ServerState,
+    user_id: Uuid,
+    payload: CreateChannelPayload,
+) -> Result<Channel, CreateChannelError> {
+    if Query::find_channel_by_name(&state.db, &payload.name)
+        .await?
+        .is_some()
+    {
+        return Err(CreateChannelError::ChannelWithSameNameAlreadyExists);
+    }
+
+    let channel =
+        Mutation::create_channel(&state.db, payload.name, payload.is_private, user_id).await?;
+    Ok(channel_view(channel))
+}
+
+pub async fn create_channel_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    ValidatedJson(payload): ValidatedJson<CreateChannelPayload>,
+) -> Result<Json<Channel>, CreateChannelError> {
+    create_channel(state, session.user_id, payload)
+        .await
+        .map(Json)
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum ChannelError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// The channel with its pins and retention policy
+pub async fn channel(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<ChannelDetails, ChannelError> {
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(ChannelError::NotMember)?;
+
+    // The membership references the channel
+    let channel = Query::find_channel_by_id(&state.db, channel_id)
+        .await?
+        .ok_or(ChannelError::NotMember)?;
+
+    let pins = pin::pins_view(&state.db, user_id, channel_id).await?;
+
+    Ok(ChannelDetails {
+        retention: retention::retention_view(&channel),
+        last_seq: channel.last_seq,
+        channel: channel_view(channel),
+        pins,
+    })
+}
+
+pub async fn channel_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+) -> Result<Json<ChannelDetails>, ChannelError> {
+    channel(state, session.user_id, channel_id).await.map(Json)
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum JoinChannelError {
+    #[error("channel not found")]
+    #[status_code(NOT_FOUND)]
+    ChannelNotFound,
+
+    #[error("the channel is private")]
+    #[status_code(FORBIDDEN)]
+    PrivateChannel,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+pub async fn join_channel(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<Channel, JoinChannelError> {
+    let channel = Query::find_channel_by_id(&state.db, channel_id)
+        .await?
+        .ok_or(JoinChannelError::ChannelNotFound)?;
+    if channel.is_private {
+        return Err(JoinChannelError::PrivateChannel);
+    }
+
+    Mutation::join_channel(&state.db, channel.id, user_id).await?;
+    Ok(channel_view(channel))
+}
+
+pub async fn join_channel_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+) -> Result<Json<Channel>, JoinChannelError> {
+    join_channel(state, session.user_id, channel_id)
+        .await
+        .map(Json)
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum MembersError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+pub async fn members(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<Vec<ChannelMember>, MembersError> {
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(MembersError::NotMember)?;
+
+    let members = Query::find_channel_members(&state.db, channel_id).await?;
+
+    Ok(members.into_iter().filter_map(member_view).collect())
+}
+
+pub async fn members_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+) -> Result<Json<Vec<ChannelMember>>, MembersError> {
+    members(state, session.user_id, channel_id).await.map(Json)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SearchMembersRequest {
+    #[validate(length(max = "MAX_USER_NAME_SIZE"))]
+    pub prefix: String,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum SearchMembersError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("validation error")]
+    #[status_code(BAD_REQUEST)]
+    ValidationError(#[from] validator::ValidationErrors),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// The mention autocomplete, the members with the name starting with the prefix
+pub async fn search_members(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    request: SearchMembersRequest,
+) -> Result<Vec<ChannelMember>, SearchMembersError> {
+    request.validate()?;
+
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(SearchMembersError::NotMember)?;
+
+    let members = Query::find_channel_members_by_name_prefix(
+        &state.db,
+        channel_id,
+        &request.prefix,
+        MEMBER_SUGGESTIONS_LIMIT,
+    )
+    .await?;
+
+    Ok(members.into_iter().filter_map(member_view).collect())
+}
+
+pub async fn search_members_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    QueryParams(request): QueryParams<SearchMembersRequest>,
+) -> Result<Json<Vec<ChannelMember>>, SearchMembersError> {
+    search_members(state, session.user_id, channel_id, request)
+        .await
+        .map(Json)
+}
@@ -0,0 +1,175 @@
+use api_error_derive::ApiError;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use common::{
+    message::{Message, PinnedMessage},
+    realtime::ChannelEvent,
+    MAX_CHANNEL_PINS,
+};
+use entity::pinned_message;
+use sea_orm::{DbConn, DbErr};
+use service::{
+    mutation::{Mutation, PinMessageError},
+    query::Query,
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::message::messages_view;
+use crate::{realtime, session::SessionContext, state::ServerState};
+
+fn pin_view(pin: pinned_message::Model, message: Message) -> PinnedMessage {
+    PinnedMessage {
+        message,
+        pinned_by: pin.pinned_by,
+        pinned_at: pin.pinned_at,
+    }
+}
+
+/// The pins of the channel as seen by the user, the newest first
+pub(crate) async fn pins_view(
+    db: &DbConn,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<Vec<PinnedMessage>, DbErr> {
+    let (pins, models): (Vec<_>, Vec<_>) = Query::find_channel_pins(db, channel_id)
+        .await?
+        .into_iter()
+        .unzip();
+    let messages = messages_view(db, user_id, models).await?;
+
+    Ok(pins
+        .into_iter()
+        .zip(messages)
+        .map(|(pin, message)| pin_view(pin, message))
+        .collect())
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum PinError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("only the channel moderators can pin the messages")]
+    #[status_code(FORBIDDEN)]
+    NotModerator,
+
+    #[error("message not found")]
+    #[status_code(NOT_FOUND)]
+    MessageNotFound,
+
+    #[error("the message is already pinned")]
+    #[status_code(BAD_REQUEST)]
+    AlreadyPinned,
+
+    #[error("the message isn't pinned")]
+    #[status_code(NOT_FOUND)]
+    NotPinned,
+
+    #[error("the channel has the maximum number of pins")]
+    #[status_code(BAD_REQUEST)]
+    TooManyPins,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+impl From<PinMessageError> for PinError {
+    fn from(err: PinMessageError) -> Self {
+        match err {
+            PinMessageError::Db(err) => Self::Db(err),
+            PinMessageError::MessageNotFound => Self::MessageNotFound,
+            PinMessageError::AlreadyPinned => Self::AlreadyPinned,
+            PinMessageError::TooManyPins => Self::TooManyPins,
+        }
+    }
+}
+
+async fn check_moderator(
+    state: &ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<(), PinError> {
+    let member = Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(PinError::NotMember)?;
+
+    if !super::is_moderator(&member.role) {
+        return Err(PinError::NotModerator);
+    }
+
+    Ok(())
+}
+
+/// Up to `MAX_CHANNEL_PINS` per channel, the deleted messages are unpinned
+pub async fn pin_message(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+) -> Result<PinnedMessage, PinError> {
+    check_moderator(&state, user_id, channel_id).await?;
+
+    let pin =
+        Mutation::pin_message(&state.db, channel_id, message_id, user_id, MAX_CHANNEL_PINS).await?;
+
+    let message = Query::find_message(&state.db, channel_id, message_id)
+        .await?
+        .ok_or(PinError::MessageNotFound)?;
+    let message = messages_view(&state.db, user_id, vec![message])
+        .await?
+        .remove(0);
+
+    let pin = pin_view(pin, message);
+    realtime::publish(
+        &state.redis,
+        channel_id,
+        ChannelEvent::MessagePinned(pin.clone()),
+    )
+    .await;
+
+    Ok(pin)
+}
+
+pub async fn pin_message_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<PinnedMessage>, PinError> {
+    pin_message(state, session.user_id, channel_id, message_id)
+        .await
+        .map(Json)
+}
+
+pub async fn unpin_message(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+) -> Result<(), PinError> {
+    check_moderator(&state, user_id, channel_id).await?;
+
+    if !Mutation::unpin_message(&state.db, channel_id, message_id).await? {
+        return Err(PinError::NotPinned);
+    }
+
+    realtime::publish(
+        &state.redis,
+        channel_id,
+        ChannelEvent::MessageUnpinned { message_id },
+    )
+    .await;
+
+    Ok(())
+}
+
+pub async fn unpin_message_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, message_id)): Path<(Uuid, Uuid)>,
+) -> Result<(), PinError> {
+    unpin_message(state, session.user_id, channel_id, message_id).await
+}
@@ -0,0 +1,138 @@
+use api_error_derive::ApiError;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use common::{
+    channel::Channel, is_valid_reaction_emoji, realtime::ChannelEvent,
+    MAX_CHANNEL_DESCRIPTION_SIZE, MAX_CHANNEL_TOPIC_SIZE,
+};
+use sea_orm::DbErr;
+use serde::Deserialize;
+use service::{
+    mutation::{ChannelProfileData, Mutation},
+    query::Query,
+};
+use thiserror::Error;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::message::message_view;
+use crate::{realtime, session::SessionContext, state::ServerState, validator::ValidatedJson};
+
+/// The blank fields are cleared
+fn non_blank(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_owned())
+        .filter(|value| !value.is_empty())
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SetProfilePayload {
+    #[validate(length(max = "MAX_CHANNEL_TOPIC_SIZE"))]
+    pub topic: Option<String>,
+
+    #[validate(length(max = "MAX_CHANNEL_DESCRIPTION_SIZE"))]
+    pub description: Option<String>,
+
+    pub icon: Option<String>,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum ProfileError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("only the channel admins can change the topic, the description and the icon")]
+    #[status_code(FORBIDDEN)]
+    NotAdmin,
+
+    #[error("the icon must be a single emoji")]
+    #[status_code(BAD_REQUEST)]
+    InvalidIcon,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// Replaces the topic, the description and the icon, the members see the changes
+/// in a system message
+pub async fn set_profile(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    payload: SetProfilePayload,
+) -> Result<Channel, ProfileError> {
+    let profile = ChannelProfileData {
+        topic: non_blank(payload.topic),
+        description: non_blank(payload.description),
+        icon: non_blank(payload.icon),
+    };
+    if profile
+        .icon
+        .as_deref()
+        .is_some_and(|icon| !is_valid_reaction_emoji(icon))
+    {
+        return Err(ProfileError::InvalidIcon);
+    }
+
+    let member = Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(ProfileError::NotMember)?;
+    if !super::is_admin(&member.role) {
+        return Err(ProfileError::NotAdmin);
+    }
+
+    // The membership references the channel
+    let (channel, message) =
+        Mutation::update_channel_profile(&state.db, channel_id, user_id, profile)
+            .await?
+            .ok_or(ProfileError::NotMember)?;
+    let channel = super::channel_view(channel);
+
+    // Nothing changed without the message
+    if let Some(message) = message {
+        realtime::publish(
+            &state.redis,
+            channel_id,
+            ChannelEvent::MessageCreated(message_view(message)),
+        )
+        .await;
+
+        realtime::publish(
+            &state.redis,
+            channel_id,
+            ChannelEvent::ChannelUpdated(channel.clone()),
+        )
+        .await;
+    }
+
+    Ok(channel)
+}
+
+pub async fn set_profile_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<SetProfilePayload>,
+) -> Result<Json<Channel>, ProfileError> {
+    set_profile(state, session.user_id, channel_id, payload)
+        .await
+        .map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_fields_are_cleared() {
+        assert_eq!(non_blank(None), None);
+        assert_eq!(non_blank(Some("  ".to_owned())), None);
+        assert_eq!(
+            non_blank(Some(" Topic ".to_owned())),
+            Some("Topic".to_owned())
+        );
+    }
+}
@@ -0,0 +1,119 @@
+use api_error_derive::ApiError;
+use axum::extract::{Path, State};
+use common::{is_valid_reaction_emoji, realtime::ChannelEvent};
+use entity::sea_orm_active_enums::MessageKind;
+use sea_orm::DbErr;
+use service::{mutation::Mutation, query::Query};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{realtime, session::SessionContext, state::ServerState};
+
+#[derive(ApiError, Debug, Error)]
+pub enum ReactionError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("message not found")]
+    #[status_code(NOT_FOUND)]
+    MessageNotFound,
+
+    #[error("the reaction must be a single emoji")]
+    #[status_code(BAD_REQUEST)]
+    InvalidEmoji,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+async fn check_message(
+    state: &ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+    emoji: &str,
+) -> Result<(), ReactionError> {
+    if !is_valid_reaction_emoji(emoji) {
+        return Err(ReactionError::InvalidEmoji);
+    }
+
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(ReactionError::NotMember)?;
+
+    // The system messages take no reactions
+    Query::find_message(&state.db, channel_id, message_id)
+        .await?
+        .filter(|message| message.deleted_at.is_none() && message.kind == MessageKind::User)
+        .ok_or(ReactionError::MessageNotFound)?;
+
+    Ok(())
+}
+
+/// Reacting twice with the same emoji is a no-op
+pub async fn add_reaction(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+    emoji: String,
+) -> Result<(), ReactionError> {
+    check_message(&state, user_id, channel_id, message_id, &emoji).await?;
+
+    if Mutation::add_reaction(&state.db, message_id, user_id, emoji.clone()).await? {
+        realtime::publish(
+            &state.redis,
+            channel_id,
+            ChannelEvent::ReactionAdded {
+                message_id,
+                user_id,
+                emoji,
+            },
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+pub async fn add_reaction_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, message_id, emoji)): Path<(Uuid, Uuid, String)>,
+) -> Result<(), ReactionError> {
+    add_reaction(state, session.user_id, channel_id, message_id, emoji).await
+}
+
+pub async fn remove_reaction(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    message_id: Uuid,
+    emoji: String,
+) -> Result<(), ReactionError> {
+    check_message(&state, user_id, channel_id, message_id, &emoji).await?;
+
+    if Mutation::remove_reaction(&state.db, message_id, user_id, &emoji).await? {
+        realtime::publish(
+            &state.redis,
+            channel_id,
+            ChannelEvent::ReactionRemoved {
+                message_id,
+                user_id,
+                emoji,
+            },
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+pub async fn remove_reaction_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, message_id, emoji)): Path<(Uuid, Uuid, String)>,
+) -> Result<(), ReactionError> {
+    remove_reaction(state, session.user_id, channel_id, message_id, emoji).await
+}
@@ -0,0 +1,102 @@
+use api_error_derive::ApiError;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use common::{
+    channel::{Channel, ChannelSummary},
+    MAX_UNREAD_COUNT,
+};
+use sea_orm::DbErr;
+use serde::Deserialize;
+use service::{mutation::Mutation, query::Query};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{session::SessionContext, state::ServerState};
+
+#[derive(ApiError, Debug, Error)]
+pub enum ChannelsError {
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// The channels of the user with the unread messages and mentions counts
+pub async fn channels(
+    state: ServerState,
+    user_id: Uuid,
+) -> Result<Vec<ChannelSummary>, ChannelsError> {
+    let channels = Query::find_channel_unreads(&state.db, user_id, MAX_UNREAD_COUNT).await?;
+
+    Ok(channels
+        .into_iter()
+        .map(|channel| ChannelSummary {
+            channel: Channel {
+                id: channel.id,
+                name: channel.name,
+                created_at: channel.created_at,
+                is_private: channel.is_private,
+                topic: channel.topic,
+                description: channel.description,
+                icon: channel.icon,
+            },
+            unread_count: channel.unread_count.try_into().unwrap_or_default(),
+            mention_count: channel.mention_count.try_into().unwrap_or_default(),
+        })
+        .collect())
+}
+
+pub async fn channels_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+) -> Result<Json<Vec<ChannelSummary>>, ChannelsError> {
+    channels(state, session.user_id).await.map(Json)
+}
+
+#[derive(Deserialize)]
+pub struct MarkReadPayload {
+    /// The newest message the user has seen
+    pub message_id: Uuid,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum MarkReadError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("message not found")]
+    #[status_code(NOT_FOUND)]
+    MessageNotFound,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// Marking an older message than the already read one is a no-op
+pub async fn mark_read(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    payload: MarkReadPayload,
+) -> Result<(), MarkReadError> {
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(MarkReadError::NotMember)?;
+
+    let message = Query::find_message(&state.db, channel_id, payload.message_id)
+        .await?
+        .ok_or(MarkReadError::MessageNotFound)?;
+
+    Mutation::mark_channel_read(&state.db, user_id, &message).await?;
+    Ok(())
+}
+
+pub async fn mark_read_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    Json(payload): Json<MarkReadPayload>,
+) -> Result<(), MarkReadError> {
+    mark_read(state, session.user_id, channel_id, payload).await
+}
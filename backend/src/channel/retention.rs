@@ -0,0 +1,211 @@
+use api_error_derive::ApiError;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use common::{
+    channel::{ChannelRetention, RetentionPolicy},
+    realtime::ChannelEvent,
+    MAX_MESSAGE_TTL_SECS, MAX_RETENTION_DAYS, MIN_MESSAGE_TTL_SECS,
+};
+use entity::{channel, sea_orm_active_enums::ChannelRole};
+use sea_orm::DbErr;
+use serde::Deserialize;
+use service::{
+    mutation::{ChannelRetentionData, Mutation},
+    query::Query,
+};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{realtime, session::SessionContext, state::ServerState};
+
+/// Only one of the settings is set by the API, the retention period wins otherwise
+pub(crate) fn retention_view(model: &channel::Model) -> ChannelRetention {
+    let policy = match (model.retention_days, model.message_ttl_secs) {
+        (Some(days), _) => RetentionPolicy::Days {
+            days: days.unsigned_abs(),
+        },
+        (None, Some(secs)) => RetentionPolicy::MessageTtl {
+            secs: secs.unsigned_abs(),
+        },
+        (None, None) => RetentionPolicy::Forever,
+    };
+
+    ChannelRetention {
+        policy,
+        legal_hold: model.legal_hold,
+    }
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum RetentionError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("only the channel admins can change the retention policy")]
+    #[status_code(FORBIDDEN)]
+    NotAdmin,
+
+    #[error("only the channel owner can place or lift the legal hold")]
+    #[status_code(FORBIDDEN)]
+    NotOwner,
+
+    #[error("the retention period or the message TTL is out of range")]
+    #[status_code(BAD_REQUEST)]
+    InvalidPolicy,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+async fn member_role(
+    state: &ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<ChannelRole, RetentionError> {
+    let member = Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(RetentionError::NotMember)?;
+
+    Ok(member.role)
+}
+
+fn retention_data(policy: RetentionPolicy) -> Result<ChannelRetentionData, RetentionError> {
+    let retention_data = match policy {
+        RetentionPolicy::Forever => ChannelRetentionData {
+            retention_days: None,
+            message_ttl_secs: None,
+        },
+        RetentionPolicy::Days { days } => {
+            if !(1..=MAX_RETENTION_DAYS).contains(&days) {
+                return Err(RetentionError::InvalidPolicy);
+            }
+
+            ChannelRetentionData {
+                retention_days: Some(days as i32),
+                message_ttl_secs: None,
+            }
+        }
+        RetentionPolicy::MessageTtl { secs } => {
+            if !(MIN_MESSAGE_TTL_SECS..=MAX_MESSAGE_TTL_SECS).contains(&secs) {
+                return Err(RetentionError::InvalidPolicy);
+            }
+
+            ChannelRetentionData {
+                retention_days: None,
+                message_ttl_secs: Some(secs as i32),
+            }
+        }
+    };
+
+    Ok(retention_data)
+}
+
+async fn publish_retention(
+    state: &ServerState,
+    channel: Option<channel::Model>,
+) -> Result<ChannelRetention, RetentionError> {
+    // The membership references the channel
+    let channel = channel.ok_or(RetentionError::NotMember)?;
+    let retention = retention_view(&channel);
+
+    realtime::publish(
+        &state.redis,
+        channel.id,
+        ChannelEvent::RetentionChanged(retention.clone()),
+    )
+    .await;
+
+    Ok(retention)
+}
+
+#[derive(Deserialize)]
+pub struct SetRetentionPayload {
+    pub policy: RetentionPolicy,
+}
+
+/// Applies to the sent messages too, except the TTL which is set on send
+pub async fn set_retention(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    payload: SetRetentionPayload,
+) -> Result<ChannelRetention, RetentionError> {
+    let retention_data = retention_data(payload.policy)?;
+
+    if !super::is_admin(&member_role(&state, user_id, channel_id).await?) {
+        return Err(RetentionError::NotAdmin);
+    }
+
+    let channel = Mutation::set_channel_retention(&state.db, channel_id, retention_data).await?;
+    publish_retention(&state, channel).await
+}
+
+pub async fn set_retention_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    Json(payload): Json<SetRetentionPayload>,
+) -> Result<Json<ChannelRetention>, RetentionError> {
+    set_retention(state, session.user_id, channel_id, payload)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize)]
+pub struct SetLegalHoldPayload {
+    pub legal_hold: bool,
+}
+
+/// The admins can't lift the hold by changing the policy, it's the owner's call
+pub async fn set_legal_hold(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    payload: SetLegalHoldPayload,
+) -> Result<ChannelRetention, RetentionError> {
+    if member_role(&state, user_id, channel_id).await? != ChannelRole::Owner {
+        return Err(RetentionError::NotOwner);
+    }
+
+    let channel =
+        Mutation::set_channel_legal_hold(&state.db, channel_id, payload.legal_hold).await?;
+    publish_retention(&state, channel).await
+}
+
+pub async fn set_legal_hold_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    Json(payload): Json<SetLegalHoldPayload>,
+) -> Result<Json<ChannelRetention>, RetentionError> {
+    set_legal_hold(state, session.user_id, channel_id, payload)
+        .await
+        .map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retention_data() {
+        assert!(retention_data(RetentionPolicy::Forever).is_ok());
+        assert!(retention_data(RetentionPolicy::Days { days: 30 }).is_ok());
+        assert!(retention_data(RetentionPolicy::Days { days: 0 }).is_err());
+        assert!(retention_data(RetentionPolicy::Days {
+            days: MAX_RETENTION_DAYS + 1
+        })
+        .is_err());
+        assert!(retention_data(RetentionPolicy::MessageTtl {
+            secs: MIN_MESSAGE_TTL_SECS - 1
+        })
+        .is_err());
+        assert!(retention_data(RetentionPolicy::MessageTtl {
+            secs: MAX_MESSAGE_TTL_SECS
+        })
+        .is_ok());
+    }
+}
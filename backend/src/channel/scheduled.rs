@@ -0,0 +1,232 @@
+use api_error_derive::ApiError;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use common::{
+    message::ScheduledMessage, MAX_MESSAGE_CONTENT_SIZE, MAX_SCHEDULED_MESSAGES,
+    MAX_SCHEDULE_AHEAD_DAYS,
+};
+use entity::scheduled_message;
+use sea_orm::DbErr;
+use serde::Deserialize;
+use service::{
+    mutation::{EditScheduledMessageData, Mutation, ScheduleMessageData, ScheduleMessageError},
+    query::Query,
+};
+use thiserror::Error;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{session::SessionContext, state::ServerState, validator::ValidatedJson};
+
+fn scheduled_view(model: scheduled_message::Model) -> ScheduledMessage {
+    ScheduledMessage {
+        id: model.id,
+        channel_id: model.channel_id,
+        parent_id: model.parent_id,
+        content: model.content,
+        send_at: model.send_at,
+        created_at: model.created_at,
+    }
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum ScheduledError {
+    #[error("the user is not a member of the channel")]
+    #[status_code(FORBIDDEN)]
+    NotMember,
+
+    #[error("the message must be scheduled in the future, up to a year ahead")]
+    #[status_code(BAD_REQUEST)]
+    InvalidSendTime,
+
+    #[error("the thread root message not found")]
+    #[status_code(BAD_REQUEST)]
+    ParentNotFound,
+
+    #[error("the user has the maximum number of scheduled messages")]
+    #[status_code(BAD_REQUEST)]
+    TooManyScheduled,
+
+    #[error("the scheduled message not found or already being sent")]
+    #[status_code(NOT_FOUND)]
+    ScheduledNotFound,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+impl From<ScheduleMessageError> for ScheduledError {
+    fn from(err: ScheduleMessageError) -> Self {
+        match err {
+            ScheduleMessageError::Db(err) => Self::Db(err),
+            ScheduleMessageError::ParentNotFound => Self::ParentNotFound,
+            ScheduleMessageError::TooManyScheduled => Self::TooManyScheduled,
+        }
+    }
+}
+
+async fn check_member(
+    state: &ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<(), ScheduledError> {
+    Query::find_channel_member(&state.db, channel_id, user_id)
+        .await?
+        .ok_or(ScheduledError::NotMember)?;
+
+    Ok(())
+}
+
+fn check_send_at(send_at: NaiveDateTime) -> Result<(), ScheduledError> {
+    let now = Utc::now().naive_utc();
+
+    if send_at <= now || send_at > now + Duration::days(MAX_SCHEDULE_AHEAD_DAYS) {
+        return Err(ScheduledError::InvalidSendTime);
+    }
+
+    Ok(())
+}
+
+/// The pending messages of the user in the channel, the soonest first
+pub async fn scheduled_messages(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+) -> Result<Vec<ScheduledMessage>, ScheduledError> {
+    check_member(&state, user_id, channel_id).await?;
+
+    let scheduled = Query::find_scheduled_messages(&state.db, user_id, channel_id).await?;
+
+    Ok(scheduled.into_iter().map(scheduled_view).collect())
+}
+
+pub async fn scheduled_messages_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+) -> Result<Json<Vec<ScheduledMessage>>, ScheduledError> {
+    scheduled_messages(state, session.user_id, channel_id)
+        .await
+        .map(Json)
+}
+
+/// The attachments can't be scheduled, the pending uploads expire before
+#[derive(Deserialize, Validate)]
+pub struct ScheduleMessagePayload {
+    #[validate(length(min = 1, max = "MAX_MESSAGE_CONTENT_SIZE"))]
+    pub content: String,
+
+    /// The root message of the thread to reply to
+    pub parent_id: Option<Uuid>,
+
+    /// UTC
+    pub send_at: NaiveDateTime,
+}
+
+pub async fn schedule_message(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    payload: ScheduleMessagePayload,
+) -> Result<ScheduledMessage, ScheduledError> {
+    check_send_at(payload.send_at)?;
+    check_member(&state, user_id, channel_id).await?;
+
+    let scheduled = Mutation::schedule_message(
+        &state.db,
+        ScheduleMessageData {
+            sender_id: user_id,
+            channel_id,
+            content: payload.content,
+            parent_id: payload.parent_id,
+            send_at: payload.send_at,
+        },
+        MAX_SCHEDULED_MESSAGES,
+    )
+    .await?;
+
+    Ok(scheduled_view(scheduled))
+}
+
+pub async fn schedule_message_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path(channel_id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<ScheduleMessagePayload>,
+) -> Result<Json<ScheduledMessage>, ScheduledError> {
+    schedule_message(state, session.user_id, channel_id, payload)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, Validate)]
+pub struct EditScheduledMessagePayload {
+    #[validate(length(min = 1, max = "MAX_MESSAGE_CONTENT_SIZE"))]
+    pub content: String,
+
+    /// UTC
+    pub send_at: NaiveDateTime,
+}
+
+pub async fn edit_scheduled_message(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    scheduled_id: Uuid,
+    payload: EditScheduledMessagePayload,
+) -> Result<ScheduledMessage, ScheduledError> {
+    check_send_at(payload.send_at)?;
+    check_member(&state, user_id, channel_id).await?;
+
+    let scheduled = Mutation::edit_scheduled_message(
+        &state.db,
+        EditScheduledMessageData {
+            id: scheduled_id,
+            sender_id: user_id,
+            channel_id,
+            content: payload.content,
+            send_at: payload.send_at,
+        },
+    )
+    .await?
+    .ok_or(ScheduledError::ScheduledNotFound)?;
+
+    Ok(scheduled_view(scheduled))
+}
+
+pub async fn edit_scheduled_message_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, scheduled_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<EditScheduledMessagePayload>,
+) -> Result<Json<ScheduledMessage>, ScheduledError> {
+    edit_scheduled_message(state, session.user_id, channel_id, scheduled_id, payload)
+        .await
+        .map(Json)
+}
+
+pub async fn cancel_scheduled_message(
+    state: ServerState,
+    user_id: Uuid,
+    channel_id: Uuid,
+    scheduled_id: Uuid,
+) -> Result<(), ScheduledError> {
+    check_member(&state, user_id, channel_id).await?;
+
+    if !Mutation::cancel_scheduled_message(&state.db, scheduled_id, user_id, channel_id).await? {
+        return Err(ScheduledError::ScheduledNotFound);
+    }
+
+    Ok(())
+}
+
+pub async fn cancel_scheduled_message_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    Path((channel_id, scheduled_id)): Path<(Uuid, Uuid)>,
+) -> Result<(), ScheduledError> {
+    cancel_scheduled_message(state, session.user_id, channel_id, scheduled_id).await
+}
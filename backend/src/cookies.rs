@@ -6,7 +6,10 @@ use tower_cookies::{
 pub const REGISTRATION_EMAIL_TOKEN: &str = "registration_email";
 pub const REGISTRATION_TYPE_TOKEN: &str = "registration_type";
 pub const REGISTRATION_PASSWORD_TOKEN: &str = "registration_password";
+pub const REGISTRATION_PROVIDER_TOKEN: &str = "registration_provider";
+pub const RETURN_TO_TOKEN: &str = "return_to";
 pub const SESSION_TOKEN: &str = "session-token";
+pub const TWO_FACTOR_TOKEN: &str = "two-factor-token";
 
 pub fn create_secure_cookie(key: &'static str, value: String) -> Cookie {
     Cookie::build((key, value))
@@ -1,10 +1,14 @@
 use std::{
     env::{self, VarError},
     fs,
+    path::PathBuf,
 };
 
 use anyhow::{anyhow, bail, Context};
 
+const DEFAULT_MESSAGE_PURGE_AFTER_DAYS: u64 = 30;
+const DEFAULT_BLOB_STORAGE_PATH: &str = "data/blobs";
+
 pub struct Environment {
     pub redis_host: String,
     pub redis_password: String,
@@ -13,8 +17,38 @@ pub struct Environment {
     pub postgres_password: String,
 
     pub redirect_url: String,
-    pub google_client_id: String,
-    pub google_client_secret: String,
+    /// Defaults to the host of the `redirect_url`
+    pub webauthn_rp_id: Option<String>,
+    pub oauth_providers: Vec<OAuthProviderEnvironment>,
+
+    pub totp_encryption_key: String,
+
+    /// The content of the deleted messages is kept for moderation until then
+    pub message_purge_after_days: u64,
+
+    /// The directory of the uploaded attachments
+    pub blob_storage_path: PathBuf,
+}
+
+pub struct OAuthProviderEnvironment {
+    pub name: String,
+
+    /// Used for the OpenID Connect discovery, can be omitted when all endpoints are set explicitly
+    pub issuer: Option<String>,
+    pub auth_url: Option<String>,
+    pub token_url: Option<String>,
+    pub userinfo_url: Option<String>,
+    pub revocation_url: Option<String>,
+
+    pub client_id: String,
+    pub client_secret: String,
+    pub scopes: Vec<String>,
+    pub email_claim: String,
+
+    pub email_verified_claim: String,
+
+    /// The emails without the claim are trusted, only for providers that return verified emails
+    pub email_verified_by_default: bool,
 }
 
 impl Environment {
@@ -29,12 +63,76 @@ impl Environment {
             postgres_password: get_secret("POSTGRES_PASSWORD")?,
 
             redirect_url: get_env("REDIRECT_URL")?,
-            google_client_id: get_secret("GOOGLE_CLIENT_ID")?,
-            google_client_secret: get_secret("GOOGLE_CLIENT_SECRET")?,
+            webauthn_rp_id: get_optional_env("WEBAUTHN_RP_ID")?,
+            oauth_providers: get_optional_env("OAUTH_PROVIDERS")?
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(OAuthProviderEnvironment::new)
+                .collect::<anyhow::Result<_>>()?,
+
+            totp_encryption_key: get_secret("TOTP_ENCRYPTION_KEY")?,
+
+            message_purge_after_days: get_optional_env("MESSAGE_PURGE_AFTER_DAYS")?
+                .map(|val| val.parse())
+                .transpose()
+                .context("MESSAGE_PURGE_AFTER_DAYS must be a number of days")?
+                .unwrap_or(DEFAULT_MESSAGE_PURGE_AFTER_DAYS),
+
+            blob_storage_path: get_optional_env("BLOB_STORAGE_PATH")?
+                .unwrap_or_else(|| DEFAULT_BLOB_STORAGE_PATH.to_owned())
+                .into(),
         })
     }
 }
 
+impl OAuthProviderEnvironment {
+    fn new(name: &str) -> anyhow::Result<Self> {
+        let prefix = format!("OAUTH_{}", name.to_uppercase().replace('-', "_"));
+        let var = |key: &str| format!("{prefix}_{key}");
+
+        let provider = Self {
+            name: name.to_lowercase(),
+
+            issuer: get_optional_env(&var("ISSUER"))?,
+            auth_url: get_optional_env(&var("AUTH_URL"))?,
+            token_url: get_optional_env(&var("TOKEN_URL"))?,
+            userinfo_url: get_optional_env(&var("USERINFO_URL"))?,
+            revocation_url: get_optional_env(&var("REVOCATION_URL"))?,
+
+            client_id: get_secret(&var("CLIENT_ID"))?,
+            client_secret: get_secret(&var("CLIENT_SECRET"))?,
+            scopes: get_optional_env(&var("SCOPES"))?
+                .unwrap_or_else(|| "openid email".to_owned())
+                .split_whitespace()
+                .map(ToOwned::to_owned)
+                .collect(),
+            email_claim: get_optional_env(&var("EMAIL_CLAIM"))?
+                .unwrap_or_else(|| "email".to_owned()),
+            email_verified_claim: get_optional_env(&var("EMAIL_VERIFIED_CLAIM"))?
+                .unwrap_or_else(|| "email_verified".to_owned()),
+            email_verified_by_default: get_optional_env(&var("EMAIL_VERIFIED_BY_DEFAULT"))?
+                .map(|val| val.parse())
+                .transpose()
+                .with_context(|| {
+                    format!("{} must be true or false", var("EMAIL_VERIFIED_BY_DEFAULT"))
+                })?
+                .unwrap_or(false),
+        };
+
+        if provider.issuer.is_none()
+            && (provider.auth_url.is_none()
+                || provider.token_url.is_none()
+                || provider.userinfo_url.is_none())
+        {
+            bail!("{prefix}_ISSUER or all of the {prefix}_AUTH_URL, {prefix}_TOKEN_URL and {prefix}_USERINFO_URL must be set");
+        }
+
+        Ok(provider)
+    }
+}
+
 fn get_env(name: &str) -> anyhow::Result<String> {
     env::var(name).map_err(|err| match err {
         VarError::NotPresent => anyhow!("{name} must be set"),
@@ -7,17 +7,32 @@ use tracing::error;
 use uuid::Uuid;
 
 pub mod auth;
+pub mod blob;
+pub mod channel;
 pub mod cookies;
 pub mod environment;
+pub mod link_preview;
+pub mod mention;
+pub mod presence;
+pub mod realtime;
 pub mod redis;
+pub mod search;
 pub mod session;
 pub mod state;
+pub mod task;
 pub mod validator;
 
 pub const INTERNAL_SERVER_ERROR_STR: &str = "InternalServerError";
 
 pub fn routes() -> Router<ServerState> {
-    Router::new().nest("/auth", auth::routes())
+    Router::new()
+        .nest("/auth", auth::routes())
+        .nest("/channels", channel::routes())
+        .nest("/invites", channel::invite::routes())
+        .nest("/mentions", mention::routes())
+        .nest("/presence", presence::routes())
+        .nest("/realtime", realtime::routes())
+        .nest("/search", search::routes())
     // .layer(middleware::from_fn_with_state(state.clone(), session::mw_session_context_resolver))
 }
 
@@ -0,0 +1,347 @@
+use std::{
+    error::Error as StdError,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use hyper::client::connect::dns::Name;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    header::{ACCEPT, CONTENT_TYPE, LOCATION},
+    redirect::Policy,
+    Client, StatusCode, Url,
+};
+use thiserror::Error;
+use tokio::net;
+
+use super::html::{self, Metadata};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REDIRECTS: usize = 3;
+const MAX_BODY_SIZE: usize = 512 * 1024;
+
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("the address isn't public")]
+    ForbiddenAddress,
+
+    #[error("only the http and https URLs on the default ports are allowed")]
+    UnsupportedUrl,
+
+    #[error("too many redirects")]
+    TooManyRedirects,
+
+    #[error("unexpected status ({0})")]
+    Status(StatusCode),
+
+    #[error("not an HTML page")]
+    NotHtml,
+
+    #[error("reqwest error ({0})")]
+    Reqwest(#[from] reqwest::Error),
+}
+
+/// The client for the URLs from the messages. It connects only to the public addresses
+/// and follows the redirects manually, so each hop is checked. The proxies from the environment
+/// are ignored, a proxy would resolve the hosts itself past the address check.
+/// `allow_private` is for the tests with the local fixture server.
+pub fn client(allow_private: bool) -> reqwest::Result<Client> {
+    Client::builder()
+        .dns_resolver(Arc::new(PublicResolver { allow_private }))
+        .redirect(Policy::none())
+        .no_proxy()
+        .timeout(TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .user_agent(concat!("simple-messenger/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+struct PublicResolver {
+    allow_private: bool,
+}
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let allow_private = self.allow_private;
+
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| allow_private || is_public(addr.ip()))
+                .collect();
+
+            if addrs.is_empty() {
+                return Err(
+                    Box::new(FetchError::ForbiddenAddress) as Box<dyn StdError + Send + Sync>
+                );
+            }
+
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// `IpAddr::is_global` isn't stable yet
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        || a >= 240 // Reserved
+        || (a == 100 && (b & 0xc0) == 64) // Shared address space, 100.64.0.0/10
+        || (a == 192 && b == 0 && c == 0) // IETF protocol assignments
+        || (a == 198 && (b & 0xfe) == 18)) // Benchmarking, 198.18.0.0/15
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+
+    // IPv4-compatible, ::/96, including the unspecified and the loopback addresses
+    if segments[..6] == [0; 6] {
+        let [.., a, b, c, d] = ip.octets();
+        return is_public_v4(Ipv4Addr::new(a, b, c, d));
+    }
+
+    // 6to4, 2002::/16, is relayed to the embedded IPv4
+    if segments[0] == 0x2002 {
+        return is_public_v4(Ipv4Addr::from(
+            (u32::from(segments[1]) << 16) | u32::from(segments[2]),
+        ));
+    }
+
+    !(ip.is_multicast()
+        || (segments[0] & 0xfe00) == 0xfc00 // Unique local, fc00::/7
+        || (segments[0] & 0xffc0) == 0xfe80 // Link-local, fe80::/10
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8) // Documentation
+        || (segments[0] == 0x64 && segments[1] == 0xff9b)) // NAT64 can reach the private IPv4
+}
+
+/// The resolver doesn't see the IP literals, so they are checked here
+fn check_url(url: &Url, allow_private: bool) -> Result<(), FetchError> {
+    let is_default_port = !url.port().is_some_and(|port| port != 80 && port != 443);
+    if !matches!(url.scheme(), "http" | "https") || !(is_default_port || allow_private) {
+        return Err(FetchError::UnsupportedUrl);
+    }
+
+    let host = url.host_str().ok_or(FetchError::UnsupportedUrl)?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        if !allow_private && !is_public(ip) {
+            return Err(FetchError::ForbiddenAddress);
+        }
+    }
+
+    Ok(())
+}
+
+/// The page metadata, the image URL is absolute
+pub async fn fetch_metadata(
+    client: &Client,
+    url: &str,
+    allow_private: bool,
+) -> Result<Metadata, FetchError> {
+    let mut url = Url::parse(url).map_err(|_| FetchError::UnsupportedUrl)?;
+
+    let mut response = None;
+    for _ in 0..=MAX_REDIRECTS {
+        check_url(&url, allow_private)?;
+
+        let hop = client
+            .get(url.clone())
+            .header(ACCEPT, "text/html")
+            .send()
+            .await?;
+
+        if !hop.status().is_redirection() {
+            response = Some(hop);
+            break;
+        }
+
+        let location = hop
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or(FetchError::Status(hop.status()))?;
+        url = url.join(location).map_err(|_| FetchError::UnsupportedUrl)?;
+    }
+
+    let mut response = response.ok_or(FetchError::TooManyRedirects)?;
+    if !response.status().is_success() {
+        return Err(FetchError::Status(response.status()));
+    }
+
+    let is_html = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+    if !is_html {
+        return Err(FetchError::NotHtml);
+    }
+
+    // The rest of the body is dropped, the metadata is in the head
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_BODY_SIZE {
+            body.truncate(MAX_BODY_SIZE);
+            break;
+        }
+    }
+
+    let mut metadata = html::parse_metadata(&String::from_utf8_lossy(&body));
+    metadata.image_url = metadata
+        .image_url
+        .and_then(|image_url| url.join(&image_url).ok())
+        .filter(|image_url| matches!(image_url.scheme(), "http" | "https"))
+        .map(String::from);
+
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        http::header,
+        response::{Html, IntoResponse, Redirect},
+        routing::get,
+        Router,
+    };
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const PAGE: &str = r#"<html><head>
+        <meta property="og:title" content="Fixture">
+        <meta property="og:image" content="/cover.png">
+    </head><body></body></html>"#;
+
+    /// The local fixture server, returns its base URL
+    async fn serve() -> String {
+        let app = Router::new()
+            .route("/page", get(|| async { Html(PAGE) }))
+            .route("/redirect", get(|| async { Redirect::to("/page") }))
+            .route("/loop", get(|| async { Redirect::to("/loop") }))
+            .route(
+                "/file",
+                get(|| async { ([(header::CONTENT_TYPE, "text/plain")], PAGE).into_response() }),
+            )
+            .route(
+                "/large",
+                get(|| async { Html(format!("{PAGE}{}", " ".repeat(2 * MAX_BODY_SIZE))) }),
+            );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_fetch_metadata() {
+        let base = serve().await;
+        let client = client(true).unwrap();
+        let fetch = |path: &str| {
+            let url = format!("{base}{path}");
+            let client = client.clone();
+            async move { fetch_metadata(&client, &url, true).await }
+        };
+
+        let metadata = fetch("/page").await.unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Fixture"));
+        assert_eq!(
+            metadata.image_url.as_deref(),
+            Some(&*format!("{base}/cover.png"))
+        );
+
+        assert_eq!(fetch("/redirect").await.unwrap(), metadata);
+        assert_eq!(fetch("/large").await.unwrap(), metadata);
+        assert!(matches!(
+            fetch("/loop").await,
+            Err(FetchError::TooManyRedirects)
+        ));
+        assert!(matches!(fetch("/file").await, Err(FetchError::NotHtml)));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_private() {
+        let base = serve().await;
+        let client = client(false).unwrap();
+
+        let result = fetch_metadata(&client, &format!("{base}/page"), false).await;
+        assert!(matches!(result, Err(FetchError::UnsupportedUrl)));
+
+        for url in [
+            "http://127.0.0.1/",
+            "http://[::1]/",
+            "http://[::ffff:10.0.0.1]/",
+        ] {
+            let result = fetch_metadata(&client, url, false).await;
+            assert!(matches!(result, Err(FetchError::ForbiddenAddress)), "{url}");
+        }
+
+        // The resolver refuses the private addresses of the domains
+        assert!(fetch_metadata(&client, "http://localhost/", false)
+            .await
+            .is_err());
+        assert!(matches!(
+            fetch_metadata(&client, "file:///etc/passwd", false).await,
+            Err(FetchError::UnsupportedUrl)
+        ));
+    }
+
+    #[test]
+    fn test_is_public() {
+        for ip in [
+            "93.184.216.34",
+            "2606:2800:220:1:248:1893:25c8:1946",
+            "::93.184.216.34",
+            "2002:5db8:d822::1",
+        ] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+
+        for ip in [
+            "0.0.0.0",
+            "10.1.2.3",
+            "100.64.0.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "172.16.0.1",
+            "192.168.1.1",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "::ffff:127.0.0.1",
+            "::127.0.0.1",
+            "::10.0.0.1",
+            "::169.254.169.254",
+            "2002:7f00:1::1",
+            "2002:a00:1::",
+            "2002:c0a8:101::1",
+            "fc00::1",
+            "fe80::1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+}
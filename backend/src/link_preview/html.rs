@@ -0,0 +1,220 @@
+/// Only the head is scanned, the metadata is there
+const MAX_HEAD_SIZE: usize = 128 * 1024;
+
+const MAX_TITLE_SIZE: usize = 200; // In characters
+const MAX_DESCRIPTION_SIZE: usize = 500;
+const MAX_SITE_NAME_SIZE: usize = 100;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+
+    /// As written in the page, can be relative
+    pub image_url: Option<String>,
+    pub site_name: Option<String>,
+}
+
+/// The OpenGraph tags with the Twitter card and the plain HTML fallbacks
+pub fn parse_metadata(html: &str) -> Metadata {
+    let mut end = html.len().min(MAX_HEAD_SIZE);
+    while !html.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let html = &html[..end];
+    let lowercase = html.to_ascii_lowercase();
+    let head = match lowercase.find("</head") {
+        Some(idx) => &html[..idx],
+        None => html,
+    };
+
+    let mut properties = Vec::new();
+    let mut offset = 0;
+    while let Some(idx) = lowercase[offset..head.len()].find("<meta") {
+        let start = offset + idx + "<meta".len();
+        let Some(len) = head[start..].find('>') else {
+            break;
+        };
+
+        let attributes = parse_attributes(&head[start..start + len]);
+        let key = attributes
+            .iter()
+            .find(|(name, _)| name == "property" || name == "name")
+            .map(|(_, value)| value.to_ascii_lowercase());
+        let content = attributes
+            .into_iter()
+            .find(|(name, _)| name == "content")
+            .map(|(_, value)| value);
+
+        if let (Some(key), Some(content)) = (key, content) {
+            properties.push((key, content));
+        }
+
+        offset = start + len;
+    }
+
+    let property = |keys: &[&str], max_size| {
+        keys.iter().find_map(|key| {
+            properties
+                .iter()
+                .find(|(name, _)| name == key)
+                .and_then(|(_, content)| clean_text(content, max_size))
+        })
+    };
+
+    let title = property(&["og:title", "twitter:title"], MAX_TITLE_SIZE).or_else(|| {
+        let start = lowercase[..head.len()].find("<title")?;
+        let start = start + head[start..].find('>')? + 1;
+        let len = lowercase[start..head.len()].find("</title")?;
+
+        clean_text(&head[start..start + len], MAX_TITLE_SIZE)
+    });
+
+    Metadata {
+        title,
+        description: property(
+            &["og:description", "twitter:description", "description"],
+            MAX_DESCRIPTION_SIZE,
+        ),
+        image_url: property(&["og:image", "twitter:image"], usize::MAX),
+        site_name: property(&["og:site_name"], MAX_SITE_NAME_SIZE),
+    }
+}
+
+/// The names are lowercased, the values are decoded
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = tag;
+
+    loop {
+        rest = rest.trim_start_matches(|ch: char| ch.is_whitespace() || ch == '/');
+        if rest.is_empty() {
+            return attributes;
+        }
+
+        let name_len = rest
+            .find(|ch: char| ch.is_whitespace() || ch == '=' || ch == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_len].to_ascii_lowercase();
+        rest = rest[name_len..].trim_start();
+
+        let Some(value) = rest.strip_prefix('=') else {
+            attributes.push((name, String::new()));
+            continue;
+        };
+
+        let value = value.trim_start();
+        let (raw, next) = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let value = &value[1..];
+                let len = value.find(quote).unwrap_or(value.len());
+                (&value[..len], value.get(len + 1..).unwrap_or_default())
+            }
+            _ => {
+                let len = value.find(char::is_whitespace).unwrap_or(value.len());
+                (&value[..len], &value[len..])
+            }
+        };
+
+        attributes.push((name, decode_entities(raw)));
+        rest = next;
+    }
+}
+
+fn decode_entities(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(idx) = rest.find('&') {
+        decoded.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        let entity = rest[1..]
+            .find(';')
+            .filter(|len| *len <= 10)
+            .map(|len| &rest[1..len + 1]);
+
+        let ch = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = entity.strip_prefix('#')?;
+                let code = match code.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => code.parse().ok()?,
+                };
+
+                char::from_u32(code)
+            }
+        });
+
+        match (entity, ch) {
+            (Some(entity), Some(ch)) => {
+                decoded.push(ch);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Collapses the whitespace, `None` if nothing is left
+fn clean_text(value: &str, max_size: usize) -> Option<String> {
+    let text: String = decode_entities(value)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .filter(|ch| !ch.is_control())
+        .take(max_size)
+        .collect();
+
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_metadata() {
+        let html = r#"
+            <html><head>
+                <title>Fallback</title>
+                <meta property="og:title" content="Tom &amp; Jerry">
+                <META NAME='description' CONTENT='plain description'>
+                <meta property="og:image" content="/cover.png" />
+                <meta property=og:site_name content=Example>
+            </head><body><meta property="og:description" content="in the body"></body></html>
+        "#;
+
+        assert_eq!(
+            parse_metadata(html),
+            Metadata {
+                title: Some("Tom & Jerry".to_owned()),
+                description: Some("plain description".to_owned()),
+                image_url: Some("/cover.png".to_owned()),
+                site_name: Some("Example".to_owned()),
+            }
+        );
+
+        let html = "<head><title>\n  Just   a title &#x1F600;\n</title></head>";
+        assert_eq!(
+            parse_metadata(html).title.as_deref(),
+            Some("Just a title 😀")
+        );
+
+        assert_eq!(parse_metadata("not html & <meta"), Metadata::default());
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use common::{
+    message::LinkPreview, realtime::ChannelEvent, MAX_LINK_PREVIEWS, MAX_LINK_PREVIEW_URL_SIZE,
+};
+use entity::link_preview;
+use reqwest::Url;
+use sea_orm::{DbConn, DbErr};
+use service::{
+    mutation::{LinkPreviewData, Mutation},
+    query::Query,
+};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::{realtime, state::ServerState};
+
+pub mod fetch;
+pub mod html;
+
+/// The stored previews are fetched again after this
+const PREVIEW_TTL_HOURS: i64 = 24;
+
+/// The messages unfurled at once, the links of the others are skipped
+pub const MAX_CONCURRENT_UNFURLS: usize = 32;
+
+/// The distinct http(s) links of the content, at most `MAX_LINK_PREVIEWS`
+pub fn extract_urls(content: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+
+    for token in content.split(|c: char| c.is_whitespace() || c == '<' || c == '>') {
+        if urls.len() == MAX_LINK_PREVIEWS {
+            break;
+        }
+
+        let Some(start) = token.find("http://").or_else(|| token.find("https://")) else {
+            continue;
+        };
+
+        // "(https://example.com)." and the Markdown links "[text](https://example.com)"
+        let url = token[start..].trim_end_matches(|c: char| {
+            matches!(
+                c,
+                '.' | ',' | ';' | ':' | '!' | '?' | ')' | ']' | '\'' | '"' | '*' | '_'
+            )
+        });
+
+        if url.len() > MAX_LINK_PREVIEW_URL_SIZE || urls.iter().any(|other| other == url) {
+            continue;
+        }
+
+        if Url::parse(url).is_ok_and(|parsed| parsed.host_str().is_some()) {
+            urls.push(url.to_owned());
+        }
+    }
+
+    urls
+}
+
+/// The previews without a title or description aren't shown, they are the failed fetches
+pub fn link_preview_view(model: link_preview::Model) -> Option<LinkPreview> {
+    if model.title.is_none() && model.description.is_none() {
+        return None;
+    }
+
+    Some(LinkPreview {
+        url: model.url,
+        title: model.title,
+        description: model.description,
+        image_url: model.image_url,
+        site_name: model.site_name,
+    })
+}
+
+/// The stored previews of the links of each content, in the order of the links
+pub(crate) async fn find_previews(
+    db: &DbConn,
+    contents: &[&str],
+) -> Result<Vec<Vec<LinkPreview>>, DbErr> {
+    let urls: Vec<Vec<String>> = contents
+        .iter()
+        .map(|content| extract_urls(content))
+        .collect();
+
+    let previews: HashMap<String, LinkPreview> =
+        Query::find_link_previews(db, urls.iter().flatten().cloned().collect())
+            .await?
+            .into_iter()
+            .filter_map(link_preview_view)
+            .map(|preview| (preview.url.clone(), preview))
+            .collect();
+
+    Ok(urls
+        .into_iter()
+        .map(|urls| {
+            urls.iter()
+                .filter_map(|url| previews.get(url).cloned())
+                .collect()
+        })
+        .collect())
+}
+
+/// Fetches the missing and the expired previews in the background, then sends them to the channel.
+/// The failures are stored too, so a broken link isn't fetched on every message.
+pub fn spawn_unfurl(state: ServerState, channel_id: Uuid, message_id: Uuid, content: &str) {
+    let urls = extract_urls(content);
+    if urls.is_empty() {
+        return;
+    }
+
+    // A flood of links can't pile up the fetches
+    let Ok(permit) = state.unfurl_permits.clone().try_acquire_owned() else {
+        debug!(%message_id, "too many unfurls, the links are skipped");
+        return;
+    };
+
+    tokio::spawn(async move {
+        if let Err(err) = unfurl(&state, channel_id, message_id, urls).await {
+            error!(%message_id, "failed to unfurl the links ({err})");
+        }
+
+        drop(permit);
+    });
+}
+
+async fn unfurl(
+    state: &ServerState,
+    channel_id: Uuid,
+    message_id: Uuid,
+    urls: Vec<String>,
+) -> Result<(), DbErr> {
+    let expired_before = Utc::now().naive_utc() - Duration::hours(PREVIEW_TTL_HOURS);
+    let mut stored: HashMap<String, link_preview::Model> =
+        Query::find_link_previews(&state.db, urls.clone())
+            .await?
+            .into_iter()
+            .filter(|model| model.fetched_at > expired_before)
+            .map(|model| (model.url.clone(), model))
+            .collect();
+
+    let mut previews = Vec::new();
+    for url in urls {
+        let model = match stored.remove(&url) {
+            Some(model) => model,
+            None => {
+                let metadata = fetch::fetch_metadata(&state.link_preview, &url, false)
+                    .await
+                    .unwrap_or_else(|err| {
+                        debug!(url, "failed to fetch the link preview ({err})");
+                        Default::default()
+                    });
+
+                let preview_data = LinkPreviewData {
+                    url,
+                    title: metadata.title,
+                    description: metadata.description,
+                    image_url: metadata.image_url,
+                    site_name: metadata.site_name,
+                };
+
+                Mutation::save_link_preview(&state.db, preview_data).await?
+            }
+        };
+
+        previews.extend(link_preview_view(model));
+    }
+
+    if !previews.is_empty() {
+        realtime::publish(
+            &state.redis,
+            channel_id,
+            ChannelEvent::LinkPreviews {
+                message_id,
+                previews,
+            },
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_urls() {
+        let content = "see https://example.com/a, (http://example.org) and https://example.com/a";
+
+        assert_eq!(
+            extract_urls(content),
+            ["https://example.com/a", "http://example.org"]
+        );
+    }
+
+    #[test]
+    fn test_extract_urls_markdown() {
+        assert_eq!(
+            extract_urls("[docs](https://example.com/docs) **https://example.org**"),
+            ["https://example.com/docs", "https://example.org"]
+        );
+    }
+
+    #[test]
+    fn test_extract_urls_limit() {
+        let content = "https://a.example https://b.example https://c.example https://d.example";
+
+        assert_eq!(extract_urls(content).len(), MAX_LINK_PREVIEWS);
+    }
+
+    #[test]
+    fn test_extract_urls_invalid() {
+        assert!(extract_urls("https:// ftp://example.com http://").is_empty());
+    }
+}
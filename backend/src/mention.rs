@@ -0,0 +1,59 @@
+use api_error_derive::ApiError;
+use axum::{
+    extract::{Query as QueryParams, State},
+    routing::get,
+    Json, Router,
+};
+use common::{message::Message, DEFAULT_MESSAGE_HISTORY_LIMIT};
+use sea_orm::DbErr;
+use service::query::Query;
+use thiserror::Error;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    channel::message::{messages_view, MessagesRequest},
+    session::SessionContext,
+    state::ServerState,
+};
+
+pub fn routes() -> Router<ServerState> {
+    Router::new().route("/", get(mentions_route))
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum MentionsError {
+    #[error("validation error")]
+    #[status_code(BAD_REQUEST)]
+    ValidationError(#[from] validator::ValidationErrors),
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// The messages mentioning the user in all the channels, the newest first
+pub async fn mentions(
+    state: ServerState,
+    user_id: Uuid,
+    request: MessagesRequest,
+) -> Result<Vec<Message>, MentionsError> {
+    request.validate()?;
+
+    let messages = Query::find_mentions_of_user(
+        &state.db,
+        user_id,
+        request.before,
+        request.limit.unwrap_or(DEFAULT_MESSAGE_HISTORY_LIMIT),
+    )
+    .await?;
+
+    Ok(messages_view(&state.db, user_id, messages).await?)
+}
+
+pub async fn mentions_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    QueryParams(request): QueryParams<MessagesRequest>,
+) -> Result<Json<Vec<Message>>, MentionsError> {
+    mentions(state, session.user_id, request).await.map(Json)
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use api_error_derive::ApiError;
+use axum::{extract::State, routing::post, Json, Router};
+use common::{
+    presence::Presence, MAX_PRESENCE_BATCH_SIZE, PRESENCE_EXPIRY_SECS, PRESENCE_STALE_SECS,
+};
+use redis::RedisError;
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::error;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    redis::presence::{self, AWAY, ONLINE},
+    session::SessionContext,
+    state::ServerState,
+    validator::ValidatedJson,
+};
+
+pub fn routes() -> Router<ServerState> {
+    Router::new().route("/", post(presences_route))
+}
+
+/// Called by the websocket handler for each connection of the user, the presence is best
+/// effort, so the failures are only logged
+pub async fn connected(state: &ServerState, user_id: Uuid, connection_id: Uuid) {
+    heartbeat(state, user_id, connection_id, false).await;
+}
+
+pub async fn heartbeat(state: &ServerState, user_id: Uuid, connection_id: Uuid, away: bool) {
+    let status = if away { AWAY } else { ONLINE };
+
+    if let Err(err) = presence::heartbeat(
+        &state.redis,
+        user_id,
+        connection_id,
+        status,
+        PRESENCE_EXPIRY_SECS,
+    )
+    .await
+    {
+        error!(%user_id, "failed to refresh the presence ({err})");
+    }
+}
+
+pub async fn disconnected(state: &ServerState, user_id: Uuid, connection_id: Uuid) {
+    if let Err(err) = presence::disconnect(&state.redis, user_id, connection_id).await {
+        error!(%user_id, "failed to clear the presence ({err})");
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct PresencesPayload {
+    #[validate(length(max = "MAX_PRESENCE_BATCH_SIZE"))]
+    pub user_ids: Vec<Uuid>,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum PresencesError {
+    #[error("validation error")]
+    #[status_code(BAD_REQUEST)]
+    ValidationError(#[from] validator::ValidationErrors),
+
+    #[error("redis error ({0})")]
+    Redis(#[from] RedisError),
+}
+
+pub async fn presences(
+    state: ServerState,
+    payload: PresencesPayload,
+) -> Result<HashMap<Uuid, Presence>, PresencesError> {
+    payload.validate()?;
+
+    let statuses = presence::statuses(
+        &state.redis,
+        &payload.user_ids,
+        PRESENCE_STALE_SECS,
+        PRESENCE_EXPIRY_SECS,
+    )
+    .await?;
+
+    Ok(payload
+        .user_ids
+        .into_iter()
+        .zip(statuses)
+        .map(|(user_id, status)| {
+            let presence = match status {
+                Some(ONLINE) => Presence::Online,
+                Some(AWAY) => Presence::Away,
+                _ => Presence::Offline,
+            };
+
+            (user_id, presence)
+        })
+        .collect())
+}
+
+pub async fn presences_route(
+    State(state): State<ServerState>,
+    _session: SessionContext,
+    ValidatedJson(payload): ValidatedJson<PresencesPayload>,
+) -> Result<Json<HashMap<Uuid, Presence>>, PresencesError> {
+    presences(state, payload).await.map(Json)
+}
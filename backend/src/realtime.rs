@@ -0,0 +1,224 @@
+use std::{collections::HashMap, pin::pin, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{
+        ws::{Message as WsMessage, WebSocket},
+        State, WebSocketUpgrade,
+    },
+    response::Response,
+    routing::get,
+    Router,
+};
+use common::{
+    realtime::{ChannelEvent, ClientEvent, ServerEvent},
+    TYPING_INTERVAL_SECS,
+};
+use futures_util::{SinkExt, StreamExt};
+use redis::Client as RedisClient;
+use service::query::Query;
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::{self, Instant},
+};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::{
+    presence,
+    redis::{pubsub, typing},
+    session::SessionContext,
+    state::ServerState,
+};
+
+const EVENT_BUFFER_SIZE: usize = 1024;
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// A membership confirmed this recently is trusted, so the fan-out doesn't query on every event
+const MEMBERSHIP_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+pub fn routes() -> Router<ServerState> {
+    Router::new().route("/", get(connect))
+}
+
+/// Fans the channel events out to the websocket connections of this instance
+#[derive(Clone)]
+pub struct Realtime {
+    sender: broadcast::Sender<Arc<ServerEvent>>,
+}
+
+impl Realtime {
+    pub fn new(redis: RedisClient) -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER_SIZE);
+        tokio::spawn(forward_events(redis, sender.clone()));
+
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<ServerEvent>> {
+        self.sender.subscribe()
+    }
+}
+
+/// Publishes through Redis, so the clients connected to the other instances receive it too.
+/// The change is already saved, so the failure is only logged.
+pub async fn publish(redis: &RedisClient, channel_id: Uuid, event: ChannelEvent) {
+    let event = ServerEvent { channel_id, event };
+
+    if let Err(err) = pubsub::publish_channel_event(redis, &event).await {
+        error!(%channel_id, "failed to publish the channel event ({err})");
+    }
+}
+
+async fn forward_events(redis: RedisClient, sender: broadcast::Sender<Arc<ServerEvent>>) {
+    loop {
+        match pubsub::subscribe_channel_events(&redis).await {
+            Ok(events) => {
+                let mut events = pin!(events);
+
+                while let Some(event) = events.next().await {
+                    // There may be no connected clients
+                    let _ = sender.send(Arc::new(event));
+                }
+
+                warn!("the channel events subscription was closed");
+            }
+            Err(err) => error!("failed to subscribe to the channel events ({err})"),
+        }
+
+        time::sleep(RESUBSCRIBE_DELAY).await;
+    }
+}
+
+pub async fn connect(
+    ws: WebSocketUpgrade,
+    State(state): State<ServerState>,
+    session: SessionContext,
+) -> Response {
+    ws.on_upgrade(move |socket| handle_socket(socket, state, session.user_id))
+}
+
+async fn handle_socket(socket: WebSocket, state: ServerState, user_id: Uuid) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut events = state.realtime.subscribe();
+    // The subscribed channels with the time the membership was last confirmed
+    let mut channels = HashMap::new();
+
+    // The presence is kept per connection, each tab reports its own
+    let connection_id = Uuid::new_v4();
+    presence::connected(&state, user_id, connection_id).await;
+
+    loop {
+        tokio::select! {
+            message = receiver.next() => {
+                let Some(Ok(message)) = message else {
+                    break;
+                };
+
+                match message {
+                    WsMessage::Text(text) => {
+                        let Ok(event) = serde_json::from_str::<ClientEvent>(&text) else {
+                            continue;
+                        };
+
+                        handle_client_event(&state, user_id, connection_id, &mut channels, event).await;
+                    }
+                    WsMessage::Close(_) => break,
+                    _ => (),
+                }
+            }
+            event = events.recv() => {
+                let event = match event {
+                    Ok(val) => val,
+                    // The client should refetch the history after reconnecting
+                    Err(RecvError::Lagged(_)) => break,
+                    Err(RecvError::Closed) => break,
+                };
+
+                if !is_member(&state, user_id, &mut channels, event.channel_id).await {
+                    continue;
+                }
+
+                let text = serde_json::to_string(&*event).expect("ServerEvent serialization");
+                if sender.send(WsMessage::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    presence::disconnected(&state, user_id, connection_id).await;
+}
+
+async fn handle_client_event(
+    state: &ServerState,
+    user_id: Uuid,
+    connection_id: Uuid,
+    channels: &mut HashMap<Uuid, Instant>,
+    event: ClientEvent,
+) {
+    match event {
+        ClientEvent::Subscribe { channel_id } => {
+            match Query::find_channel_member(&state.db, channel_id, user_id).await {
+                Ok(Some(_)) => {
+                    channels.insert(channel_id, Instant::now());
+                }
+                Ok(None) => (),
+                Err(err) => error!(%channel_id, "failed to check the membership ({err})"),
+            }
+        }
+        ClientEvent::Unsubscribe { channel_id } => {
+            channels.remove(&channel_id);
+        }
+        ClientEvent::Typing { channel_id } => {
+            if is_member(state, user_id, channels, channel_id).await {
+                announce_typing(state, user_id, channel_id).await;
+            }
+        }
+        ClientEvent::Heartbeat { away } => {
+            presence::heartbeat(state, user_id, connection_id, away).await
+        }
+    }
+}
+
+/// Rechecks the membership of a subscribed channel once it's older than
+/// `MEMBERSHIP_CHECK_INTERVAL`, the channel is dropped after the user left it
+async fn is_member(
+    state: &ServerState,
+    user_id: Uuid,
+    channels: &mut HashMap<Uuid, Instant>,
+    channel_id: Uuid,
+) -> bool {
+    match channels.get(&channel_id) {
+        Some(checked_at) if checked_at.elapsed() < MEMBERSHIP_CHECK_INTERVAL => return true,
+        Some(_) => (),
+        None => return false,
+    }
+
+    match Query::find_channel_member(&state.db, channel_id, user_id).await {
+        Ok(Some(_)) => {
+            channels.insert(channel_id, Instant::now());
+            true
+        }
+        Ok(None) => {
+            channels.remove(&channel_id);
+            false
+        }
+        // Checked again with the next event
+        Err(err) => {
+            error!(%channel_id, "failed to check the membership ({err})");
+            false
+        }
+    }
+}
+
+/// Not persisted, only fanned out to the subscribers
+async fn announce_typing(state: &ServerState, user_id: Uuid, channel_id: Uuid) {
+    // A bit less than the interval, so the repeated events of a client pass
+    let throttle = TYPING_INTERVAL_SECS - 1;
+
+    match typing::announce_typing(&state.redis, channel_id, user_id, throttle).await {
+        Ok(true) => publish(&state.redis, channel_id, ChannelEvent::Typing { user_id }).await,
+        Ok(false) => (),
+        Err(err) => error!(%channel_id, "failed to announce the typing ({err})"),
+    }
+}
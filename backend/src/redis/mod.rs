@@ -1,7 +1,41 @@
+use redis::{ErrorKind, FromRedisValue, RedisError, RedisResult, RedisWrite, ToRedisArgs, Value};
+use serde::{de::DeserializeOwned, Serialize};
+
 pub mod oauth;
+pub mod presence;
+pub mod pubsub;
 pub mod session;
+pub mod two_factor;
+pub mod typing;
+pub mod webauthn;
 
 const SESSION_STORAGE: u32 = 0;
 const OAUTH_STATE_STORAGE: u32 = 1;
+const TWO_FACTOR_STORAGE: u32 = 2;
+const WEBAUTHN_STATE_STORAGE: u32 = 3;
+const TYPING_STORAGE: u32 = 4;
+const PRESENCE_STORAGE: u32 = 5;
 
 const SELECT: &str = "SELECT";
+
+/// Stores the value as JSON
+struct Json<T>(T);
+
+impl<T: Serialize> ToRedisArgs for Json<T> {
+    fn write_redis_args<W>(&self, out: &mut W)
+    where
+        W: ?Sized + RedisWrite,
+    {
+        out.write_arg(&serde_json::to_vec(&self.0).expect("JSON serialization"));
+    }
+}
+
+impl<T: DeserializeOwned> FromRedisValue for Json<T> {
+    fn from_redis_value(value: &Value) -> RedisResult<Self> {
+        let bytes: Vec<u8> = FromRedisValue::from_redis_value(value)?;
+
+        serde_json::from_slice(&bytes).map(Self).map_err(|err| {
+            RedisError::from((ErrorKind::TypeError, "invalid JSON value", err.to_string()))
+        })
+    }
+}
@@ -1,12 +1,22 @@
-use oauth2::{CsrfToken, PkceCodeVerifier};
+use oauth2::CsrfToken;
 use redis::{AsyncCommands, Client, RedisError};
+use serde::{Deserialize, Serialize};
 
-use super::{OAUTH_STATE_STORAGE, SELECT};
+use super::{Json, OAUTH_STATE_STORAGE, SELECT};
+
+/// Everything needed to finish the authorization code flow, keyed by the CSRF token
+#[derive(Deserialize, Serialize)]
+pub struct OAuthState {
+    pub provider: String,
+    pub pkce_verifier: String,
+    pub nonce: String,
+    pub return_to: Option<String>,
+}
 
 pub async fn insert_state(
     redis: &Client,
     crsf_token: CsrfToken,
-    pkce_verifier: PkceCodeVerifier,
+    state: OAuthState,
     seconds: u64,
 ) -> Result<(), RedisError> {
     let mut connection = redis.get_async_connection().await?;
@@ -17,17 +27,16 @@ pub async fn insert_state(
         .await?;
 
     connection
-        .set_ex(
-            crsf_token.secret().to_owned(),
-            pkce_verifier.secret().to_owned(),
-            seconds,
-        )
+        .set_ex(crsf_token.secret().to_owned(), Json(state), seconds)
         .await?;
 
     Ok(())
 }
 
-pub async fn take_state(redis: &Client, crsf_token: CsrfToken) -> Result<String, RedisError> {
+pub async fn take_state(
+    redis: &Client,
+    crsf_token: CsrfToken,
+) -> Result<Option<OAuthState>, RedisError> {
     let mut connection = redis.get_async_connection().await?;
 
     redis::cmd(SELECT)
@@ -35,5 +44,8 @@ pub async fn take_state(redis: &Client, crsf_token: CsrfToken) -> Result<String,
         .query_async(&mut connection)
         .await?;
 
-    connection.get_del(crsf_token.secret().to_owned()).await
+    let state: Option<Json<OAuthState>> =
+        connection.get_del(crsf_token.secret().to_owned()).await?;
+
+    Ok(state.map(|Json(state)| state))
 }
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use redis::{AsyncCommands, Client, RedisError};
+use uuid::Uuid;
+
+use super::{PRESENCE_STORAGE, SELECT};
+
+pub const ONLINE: &str = "online";
+pub const AWAY: &str = "away";
+
+/// The open connections of the user, each with its status and the time of its last heartbeat,
+/// so an idle tab doesn't hide an active one
+fn connections_key(user_id: Uuid) -> String {
+    format!("{user_id}:connections")
+}
+
+async fn connection(redis: &Client) -> Result<redis::aio::Connection, RedisError> {
+    let mut connection = redis.get_async_connection().await?;
+
+    redis::cmd(SELECT)
+        .arg(PRESENCE_STORAGE)
+        .query_async(&mut connection)
+        .await?;
+
+    Ok(connection)
+}
+
+/// The key expires without the heartbeats, so a crashed instance doesn't leave the users online
+pub async fn heartbeat(
+    redis: &Client,
+    user_id: Uuid,
+    connection_id: Uuid,
+    status: &str,
+    seconds: u64,
+) -> Result<(), RedisError> {
+    let mut connection = connection(redis).await?;
+
+    redis::pipe()
+        .hset(
+            connections_key(user_id),
+            connection_id.to_string(),
+            format!("{status} {}", Utc::now().timestamp()),
+        )
+        .expire(connections_key(user_id), seconds as i64)
+        .query_async(&mut connection)
+        .await
+}
+
+/// The user is offline when the last connection is closed, the empty hash is removed by Redis
+pub async fn disconnect(
+    redis: &Client,
+    user_id: Uuid,
+    connection_id: Uuid,
+) -> Result<(), RedisError> {
+    let mut connection = connection(redis).await?;
+
+    connection
+        .hdel(connections_key(user_id), connection_id.to_string())
+        .await
+}
+
+/// The statuses in the order of the users, `None` for the offline ones
+pub async fn statuses(
+    redis: &Client,
+    user_ids: &[Uuid],
+    stale_seconds: u64,
+    seconds: u64,
+) -> Result<Vec<Option<&'static str>>, RedisError> {
+    if user_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut connection = connection(redis).await?;
+
+    let mut pipe = redis::pipe();
+    for user_id in user_ids {
+        pipe.hgetall(connections_key(*user_id));
+    }
+    let connections: Vec<HashMap<String, String>> = pipe.query_async(&mut connection).await?;
+
+    let now = Utc::now().timestamp();
+    Ok(connections
+        .iter()
+        .map(|connections| status(connections, now, stale_seconds, seconds))
+        .collect())
+}
+
+/// Online if any connection is, an online connection without a recent heartbeat is away.
+/// The connections of a crashed instance are skipped after the expiry.
+fn status(
+    connections: &HashMap<String, String>,
+    now: i64,
+    stale_seconds: u64,
+    seconds: u64,
+) -> Option<&'static str> {
+    let mut status = None;
+
+    for value in connections.values() {
+        let Some((connection_status, heartbeat_at)) = value.split_once(' ') else {
+            continue;
+        };
+        let Ok(heartbeat_at) = heartbeat_at.parse::<i64>() else {
+            continue;
+        };
+
+        let age = now.saturating_sub(heartbeat_at);
+        if age > seconds as i64 {
+            continue;
+        }
+
+        if connection_status == ONLINE && age <= stale_seconds as i64 {
+            return Some(ONLINE);
+        }
+
+        status = Some(AWAY);
+    }
+
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status() {
+        let now = 1_000;
+        let connections = |values: &[&str]| -> HashMap<String, String> {
+            values
+                .iter()
+                .enumerate()
+                .map(|(idx, value)| (idx.to_string(), value.to_string()))
+                .collect()
+        };
+
+        assert_eq!(status(&connections(&[]), now, 60, 90), None);
+        assert_eq!(
+            status(&connections(&["online 990"]), now, 60, 90),
+            Some(ONLINE)
+        );
+
+        // The idle background tab doesn't hide the active one
+        assert_eq!(
+            status(&connections(&["away 995", "online 990"]), now, 60, 90),
+            Some(ONLINE)
+        );
+        assert_eq!(
+            status(&connections(&["away 995", "online 930"]), now, 60, 90),
+            Some(AWAY)
+        );
+        assert_eq!(status(&connections(&["online 900"]), now, 60, 90), None);
+    }
+}
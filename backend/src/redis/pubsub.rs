@@ -0,0 +1,30 @@
+use common::realtime::ServerEvent;
+use futures_util::{Stream, StreamExt};
+use redis::{AsyncCommands, Client, RedisError};
+
+use super::Json;
+
+// Pub/sub channels don't depend on the selected database
+const CHANNEL_EVENTS: &str = "channel_events";
+
+pub async fn publish_channel_event(redis: &Client, event: &ServerEvent) -> Result<(), RedisError> {
+    let mut connection = redis.get_async_connection().await?;
+
+    connection.publish(CHANNEL_EVENTS, Json(event)).await?;
+    Ok(())
+}
+
+/// Malformed events are skipped, the stream ends when the connection is lost
+pub async fn subscribe_channel_events(
+    redis: &Client,
+) -> Result<impl Stream<Item = ServerEvent>, RedisError> {
+    let mut pubsub = redis.get_async_connection().await?.into_pubsub();
+    pubsub.subscribe(CHANNEL_EVENTS).await?;
+
+    Ok(pubsub.into_on_message().filter_map(|message| async move {
+        message
+            .get_payload::<Json<ServerEvent>>()
+            .ok()
+            .map(|Json(event)| event)
+    }))
+}
@@ -0,0 +1,69 @@
+use redis::{AsyncCommands, Client, RedisError};
+
+use super::{SELECT, TWO_FACTOR_STORAGE};
+
+fn attempts_key(token: &str) -> String {
+    format!("{token}:attempts")
+}
+
+pub async fn insert_pending_token(
+    redis: &Client,
+    token: String,
+    user_id: String,
+    seconds: u64,
+) -> Result<(), RedisError> {
+    let mut connection = redis.get_async_connection().await?;
+
+    redis::cmd(SELECT)
+        .arg(TWO_FACTOR_STORAGE)
+        .query_async(&mut connection)
+        .await?;
+
+    connection.set_ex(token, user_id, seconds).await?;
+    Ok(())
+}
+
+pub async fn get_pending_token(redis: &Client, token: &str) -> Result<Option<String>, RedisError> {
+    let mut connection = redis.get_async_connection().await?;
+
+    redis::cmd(SELECT)
+        .arg(TWO_FACTOR_STORAGE)
+        .query_async(&mut connection)
+        .await?;
+
+    connection.get(token).await
+}
+
+pub async fn remove_pending_token(redis: &Client, token: &str) -> Result<(), RedisError> {
+    let mut connection = redis.get_async_connection().await?;
+
+    redis::cmd(SELECT)
+        .arg(TWO_FACTOR_STORAGE)
+        .query_async(&mut connection)
+        .await?;
+
+    connection
+        .del(&[token.to_owned(), attempts_key(token)])
+        .await?;
+    Ok(())
+}
+
+/// Returns the number of the failed attempts for the pending token
+pub async fn increment_failed_attempts(
+    redis: &Client,
+    token: &str,
+    seconds: u64,
+) -> Result<u64, RedisError> {
+    let mut connection = redis.get_async_connection().await?;
+
+    redis::cmd(SELECT)
+        .arg(TWO_FACTOR_STORAGE)
+        .query_async(&mut connection)
+        .await?;
+
+    let key = attempts_key(token);
+    let attempts = connection.incr(&key, 1).await?;
+    connection.expire(&key, seconds as i64).await?;
+
+    Ok(attempts)
+}
@@ -0,0 +1,31 @@
+use redis::{Client, RedisError};
+use uuid::Uuid;
+
+use super::{SELECT, TYPING_STORAGE};
+
+/// Returns `false` if the typing was already announced within the last `seconds`,
+/// so a chatty client doesn't flood the channel subscribers
+pub async fn announce_typing(
+    redis: &Client,
+    channel_id: Uuid,
+    user_id: Uuid,
+    seconds: u64,
+) -> Result<bool, RedisError> {
+    let mut connection = redis.get_async_connection().await?;
+
+    redis::cmd(SELECT)
+        .arg(TYPING_STORAGE)
+        .query_async(&mut connection)
+        .await?;
+
+    let result: Option<String> = redis::cmd("SET")
+        .arg(format!("{channel_id}:{user_id}"))
+        .arg(1)
+        .arg("EX")
+        .arg(seconds)
+        .arg("NX")
+        .query_async(&mut connection)
+        .await?;
+
+    Ok(result.is_some())
+}
@@ -0,0 +1,39 @@
+use redis::{AsyncCommands, Client, RedisError};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{Json, SELECT, WEBAUTHN_STATE_STORAGE};
+
+pub async fn insert_state<T: Serialize>(
+    redis: &Client,
+    challenge_id: String,
+    state: T,
+    seconds: u64,
+) -> Result<(), RedisError> {
+    let mut connection = redis.get_async_connection().await?;
+
+    redis::cmd(SELECT)
+        .arg(WEBAUTHN_STATE_STORAGE)
+        .query_async(&mut connection)
+        .await?;
+
+    connection
+        .set_ex(challenge_id, Json(state), seconds)
+        .await?;
+    Ok(())
+}
+
+/// The challenge can be answered only once
+pub async fn take_state<T: DeserializeOwned>(
+    redis: &Client,
+    challenge_id: &str,
+) -> Result<Option<T>, RedisError> {
+    let mut connection = redis.get_async_connection().await?;
+
+    redis::cmd(SELECT)
+        .arg(WEBAUTHN_STATE_STORAGE)
+        .query_async(&mut connection)
+        .await?;
+
+    let state: Option<Json<T>> = connection.get_del(challenge_id).await?;
+    Ok(state.map(|Json(state)| state))
+}
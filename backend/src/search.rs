@@ -0,0 +1,200 @@
+use api_error_derive::ApiError;
+use axum::{
+    extract::{Query as QueryParams, State},
+    routing::get,
+    Json, Router,
+};
+use chrono::NaiveDateTime;
+use common::{
+    message::SearchResult, DEFAULT_SEARCH_LIMIT, MAX_SEARCH_LIMIT, MAX_SEARCH_OFFSET,
+    MAX_SEARCH_QUERY_SIZE,
+};
+use sea_orm::DbErr;
+use serde::Deserialize;
+use service::query::{MessageSearch, Query};
+use thiserror::Error;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{channel::message::messages_view, session::SessionContext, state::ServerState};
+
+pub fn routes() -> Router<ServerState> {
+    Router::new().route("/", get(search_route))
+}
+
+#[derive(Deserialize, Validate)]
+pub struct SearchRequest {
+    /// The web search syntax: the quoted phrases, `or` and the excluded `-words`
+    #[validate(length(min = 1, max = "MAX_SEARCH_QUERY_SIZE"))]
+    pub query: String,
+
+    pub sender_id: Option<Uuid>,
+    pub channel_id: Option<Uuid>,
+
+    /// Inclusive
+    pub from: Option<NaiveDateTime>,
+
+    /// Exclusive
+    pub to: Option<NaiveDateTime>,
+
+    #[validate(range(max = "MAX_SEARCH_OFFSET"))]
+    pub offset: Option<u64>,
+
+    #[validate(range(min = 1, max = "MAX_SEARCH_LIMIT"))]
+    pub limit: Option<u64>,
+}
+
+#[derive(ApiError, Debug, Error)]
+pub enum SearchError {
+    #[error("validation error")]
+    #[status_code(BAD_REQUEST)]
+    ValidationError(#[from] validator::ValidationErrors),
+
+    #[error("the date range ends before it starts")]
+    #[status_code(BAD_REQUEST)]
+    InvalidDateRange,
+
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+}
+
+/// The filters of the request with the defaults
+fn message_search(request: SearchRequest) -> Result<MessageSearch, SearchError> {
+    request.validate()?;
+
+    if let (Some(from), Some(to)) = (request.from, request.to) {
+        if from >= to {
+            return Err(SearchError::InvalidDateRange);
+        }
+    }
+
+    Ok(MessageSearch {
+        query: request.query,
+        sender_id: request.sender_id,
+        channel_id: request.channel_id,
+        from: request.from,
+        to: request.to,
+        offset: request.offset.unwrap_or_default(),
+        limit: request.limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+    })
+}
+
+/// The messages of the channels of the user matching the query, the best ranked first
+pub async fn search(
+    state: ServerState,
+    user_id: Uuid,
+    request: SearchRequest,
+) -> Result<Vec<SearchResult>, SearchError> {
+    let hits = Query::search_messages(&state.db, user_id, message_search(request)?).await?;
+
+    let (models, hits): (Vec<_>, Vec<_>) = hits
+        .into_iter()
+        .map(|hit| (hit.message, (hit.rank, hit.snippet_html)))
+        .unzip();
+    let messages = messages_view(&state.db, user_id, models).await?;
+
+    Ok(messages
+        .into_iter()
+        .zip(hits)
+        .map(|(message, (rank, snippet_html))| SearchResult {
+            message,
+            rank,
+            snippet_html,
+        })
+        .collect())
+}
+
+pub async fn search_route(
+    State(state): State<ServerState>,
+    session: SessionContext,
+    QueryParams(request): QueryParams<SearchRequest>,
+) -> Result<Json<Vec<SearchResult>>, SearchError> {
+    search(state, session.user_id, request).await.map(Json)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn request(query: &str) -> SearchRequest {
+        SearchRequest {
+            query: query.to_owned(),
+            sender_id: None,
+            channel_id: None,
+            from: None,
+            to: None,
+            offset: None,
+            limit: None,
+        }
+    }
+
+    #[test]
+    fn test_message_search() {
+        let search = message_search(request("a")).unwrap();
+        assert_eq!(search.offset, 0);
+        assert_eq!(search.limit, DEFAULT_SEARCH_LIMIT);
+
+        let day = |day| {
+            NaiveDate::from_ymd_opt(2024, 1, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        };
+
+        let search = message_search(SearchRequest {
+            sender_id: Some(Uuid::from_u128(1)),
+            channel_id: Some(Uuid::from_u128(2)),
+            from: Some(day(1)),
+            to: Some(day(2)),
+            offset: Some(10),
+            limit: Some(5),
+            ..request("a")
+        })
+        .unwrap();
+        assert_eq!(search.sender_id, Some(Uuid::from_u128(1)));
+        assert_eq!(search.channel_id, Some(Uuid::from_u128(2)));
+        assert_eq!((search.from, search.to), (Some(day(1)), Some(day(2))));
+        assert_eq!((search.offset, search.limit), (10, 5));
+    }
+
+    #[test]
+    fn test_message_search_invalid() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+
+        // The range end is exclusive, an empty range matches nothing
+        let result = message_search(SearchRequest {
+            from: Some(day),
+            to: Some(day),
+            ..request("a")
+        });
+        assert!(matches!(result, Err(SearchError::InvalidDateRange)));
+
+        assert!(matches!(
+            message_search(request("")),
+            Err(SearchError::ValidationError(_))
+        ));
+        assert!(matches!(
+            message_search(request(&"a".repeat(MAX_SEARCH_QUERY_SIZE + 1))),
+            Err(SearchError::ValidationError(_))
+        ));
+        assert!(matches!(
+            message_search(SearchRequest {
+                limit: Some(MAX_SEARCH_LIMIT + 1),
+                ..request("a")
+            }),
+            Err(SearchError::ValidationError(_))
+        ));
+        assert!(matches!(
+            message_search(SearchRequest {
+                offset: Some(MAX_SEARCH_OFFSET + 1),
+                ..request("a")
+            }),
+            Err(SearchError::ValidationError(_))
+        ));
+    }
+}
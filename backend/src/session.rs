@@ -84,10 +84,11 @@ impl FromRequestParts<ServerState> for SessionContext {
         parts: &mut Parts,
         _state: &ServerState,
     ) -> Result<Self, SessionContextError> {
+        // Resolved by `mw_session_context_resolver`
         parts
             .extensions
-            .get::<SessionContext>()
-            .ok_or(SessionContextError::AuthFailNoSessionToken)
-            .cloned()
+            .get::<Result<SessionContext, SessionContextError>>()
+            .ok_or(SessionContextError::AuthFailNoSessionToken)?
+            .clone()
     }
 }
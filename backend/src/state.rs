@@ -1,8 +1,9 @@
+use std::sync::Arc;
+
 use anyhow::Context;
 use axum::extract::FromRef;
 use leptos::LeptosOptions;
 use migration::{Migrator, MigratorTrait};
-use oauth2::basic::BasicClient;
 use rand_chacha::{
     rand_core::{OsRng, RngCore, SeedableRng},
     ChaCha8Rng,
@@ -10,16 +11,39 @@ use rand_chacha::{
 use redis::Client as RedisClient;
 use reqwest::Client as ReqwestClient;
 use sea_orm::{Database, DatabaseConnection};
+use tokio::sync::Semaphore;
+use webauthn_rs::Webauthn;
 
-use crate::{auth::oauth, environment::Environment};
+use crate::{
+    auth::{
+        oauth::provider::OAuthProviders,
+        passkey::{self, PasskeyDecoy},
+        totp::TotpCipher,
+    },
+    blob::{local::LocalBlobStore, BlobStore},
+    environment::Environment,
+    link_preview,
+    realtime::Realtime,
+};
 
 #[derive(Clone, FromRef)]
 pub struct ServerState {
     pub random: ChaCha8Rng,
     pub reqwest: ReqwestClient,
-    pub oauth: BasicClient,
+
+    /// Only for the links from the messages, see `link_preview::fetch::client`
+    pub link_preview: ReqwestClient,
+
+    /// Bounds the background link preview fetches, see `link_preview::spawn_unfurl`
+    pub unfurl_permits: Arc<Semaphore>,
+    pub oauth: OAuthProviders,
+    pub totp_cipher: TotpCipher,
+    pub webauthn: Arc<Webauthn>,
+    pub passkey_decoy: PasskeyDecoy,
+    pub realtime: Realtime,
     pub redis: RedisClient,
     pub db: DatabaseConnection,
+    pub blobs: Arc<dyn BlobStore>,
     pub leptos_options: LeptosOptions,
 }
 
@@ -33,8 +57,26 @@ impl ServerState {
             .brotli(true)
             .build()
             .context("Failed to initialize reqwest::Client")?;
+        let link_preview = link_preview::fetch::client(false)
+            .context("Failed to initialize the link preview client")?;
+        let unfurl_permits = Arc::new(Semaphore::new(link_preview::MAX_CONCURRENT_UNFURLS));
 
-        let oauth = oauth::google::create_basic_client(environment);
+        let oauth = OAuthProviders::new(
+            &reqwest,
+            &environment.redirect_url,
+            &environment.oauth_providers,
+        )
+        .await?;
+
+        let totp_cipher = TotpCipher::new(&environment.totp_encryption_key)?;
+        let webauthn = Arc::new(passkey::create_webauthn(
+            &environment.redirect_url,
+            environment.webauthn_rp_id.as_deref(),
+        )?);
+        let passkey_decoy = PasskeyDecoy::new(
+            &environment.redirect_url,
+            environment.webauthn_rp_id.as_deref(),
+        )?;
 
         let redis = RedisClient::open(format!(
             "redis://:{}@{}",
@@ -42,6 +84,8 @@ impl ServerState {
         ))
         .context("Redis connection failed")?;
 
+        let realtime = Realtime::new(redis.clone());
+
         let db = Database::connect(format!(
             "postgres://postgres:{}@{}/simple_messenger",
             environment.postgres_password, environment.postgres_host,
@@ -51,12 +95,21 @@ impl ServerState {
 
         Migrator::up(&db, None).await?;
 
+        let blobs = Arc::new(LocalBlobStore::new(&environment.blob_storage_path));
+
         Ok(Self {
             random,
             reqwest,
+            link_preview,
+            unfurl_permits,
             oauth,
+            totp_cipher,
+            webauthn,
+            passkey_decoy,
+            realtime,
             redis,
             db,
+            blobs,
             leptos_options,
         })
     }
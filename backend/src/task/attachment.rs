@@ -0,0 +1,77 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use entity::attachment;
+use sea_orm::{DatabaseConnection, DbErr};
+use service::{mutation::Mutation, query::Query};
+use tokio::time;
+use tracing::{error, info};
+
+use crate::blob::{attachment_key, thumbnail_key, BlobStore};
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600); // 1 hour
+const CLEANUP_BATCH_SIZE: u64 = 100;
+
+/// The uploads not sent with a message are abandoned after that
+const PENDING_UPLOAD_EXPIRY: Duration = Duration::from_secs(86400); // 1 day
+
+/// Deletes the abandoned uploads, the files of the deleted messages go with the purge
+pub async fn run(db: DatabaseConnection, blobs: Arc<dyn BlobStore>) {
+    let mut interval = time::interval(CLEANUP_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match cleanup(&db, blobs.as_ref()).await {
+            Ok(0) => (),
+            Ok(deleted) => info!(deleted, "deleted the stale attachments"),
+            Err(err) => error!("failed to delete the stale attachments ({err})"),
+        }
+    }
+}
+
+async fn cleanup(db: &DatabaseConnection, blobs: &dyn BlobStore) -> Result<u64, DbErr> {
+    let uploaded_before = Utc::now().naive_utc()
+        - chrono::Duration::from_std(PENDING_UPLOAD_EXPIRY).expect("expiry is out of range");
+
+    let mut total = 0;
+    loop {
+        let attachments =
+            Query::find_abandoned_uploads(db, uploaded_before, CLEANUP_BATCH_SIZE).await?;
+
+        let (deleted, failed) = delete(db, blobs, &attachments).await?;
+        total += deleted;
+
+        if failed || (attachments.len() as u64) < CLEANUP_BATCH_SIZE {
+            return Ok(total);
+        }
+    }
+}
+
+/// Deletes the files, then the rows. The rows are kept if the files can't be deleted,
+/// so the next run retries, it's reported by the returned flag.
+pub(super) async fn delete(
+    db: &DatabaseConnection,
+    blobs: &dyn BlobStore,
+    attachments: &[attachment::Model],
+) -> Result<(u64, bool), DbErr> {
+    let mut deleted_ids = Vec::with_capacity(attachments.len());
+    for attachment in attachments {
+        let result = match blobs.delete(&attachment_key(attachment.id)).await {
+            Ok(()) => blobs.delete(&thumbnail_key(attachment.id)).await,
+            Err(err) => Err(err),
+        };
+
+        match result {
+            Ok(()) => deleted_ids.push(attachment.id),
+            Err(err) => {
+                error!(attachment_id = %attachment.id, "failed to delete the blob ({err})")
+            }
+        }
+    }
+
+    let failed = deleted_ids.len() < attachments.len();
+    let deleted = Mutation::delete_attachments(db, deleted_ids).await?;
+
+    Ok((deleted, failed))
+}
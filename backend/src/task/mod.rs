@@ -0,0 +1,24 @@
+use std::time::Duration;
+
+use crate::{environment::Environment, state::ServerState};
+
+pub mod attachment;
+pub mod purge;
+pub mod retention;
+pub mod scheduled;
+
+const SECONDS_IN_DAY: u64 = 86400;
+
+/// Spawns the periodic maintenance tasks, they are safe to run on every instance
+pub fn spawn(state: &ServerState, environment: &Environment) {
+    let purge_after = Duration::from_secs(environment.message_purge_after_days * SECONDS_IN_DAY);
+
+    tokio::spawn(purge::run(
+        state.db.clone(),
+        state.blobs.clone(),
+        purge_after,
+    ));
+    tokio::spawn(attachment::run(state.db.clone(), state.blobs.clone()));
+    tokio::spawn(scheduled::run(state.clone()));
+    tokio::spawn(retention::run(state.clone()));
+}
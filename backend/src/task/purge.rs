@@ -0,0 +1,79 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use sea_orm::{prelude::DateTime, DatabaseConnection, DbErr};
+use service::{mutation::Mutation, query::Query};
+use tokio::time;
+use tracing::{error, info};
+
+use crate::{blob::BlobStore, link_preview};
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(3600); // 1 hour
+const PURGE_BATCH_SIZE: u64 = 500;
+const ATTACHMENT_BATCH_SIZE: u64 = 100;
+
+/// Erases the content of the messages deleted more than `purge_after` ago with their files,
+/// mentions and link previews
+pub async fn run(db: DatabaseConnection, blobs: Arc<dyn BlobStore>, purge_after: Duration) {
+    let mut interval = time::interval(PURGE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match purge(&db, blobs.as_ref(), purge_after).await {
+            Ok(0) => (),
+            Ok(purged) => info!(purged, "purged the deleted messages"),
+            Err(err) => error!("failed to purge the deleted messages ({err})"),
+        }
+    }
+}
+
+async fn purge(
+    db: &DatabaseConnection,
+    blobs: &dyn BlobStore,
+    purge_after: Duration,
+) -> Result<u64, DbErr> {
+    let deleted_before = Utc::now().naive_utc()
+        - chrono::Duration::from_std(purge_after).expect("purge period is out of range");
+
+    // The messages with the files left are kept, so the next run retries
+    delete_attachments(db, blobs, deleted_before).await?;
+
+    // Short transactions, so the message table isn't locked for long
+    let mut total = 0;
+    loop {
+        let contents =
+            Mutation::purge_deleted_messages(db, deleted_before, PURGE_BATCH_SIZE).await?;
+        total += contents.len() as u64;
+
+        let mut urls: Vec<String> = contents
+            .iter()
+            .flat_map(|content| link_preview::extract_urls(content))
+            .collect();
+        urls.sort_unstable();
+        urls.dedup();
+
+        Mutation::delete_unused_link_previews(db, urls).await?;
+
+        if (contents.len() as u64) < PURGE_BATCH_SIZE {
+            return Ok(total);
+        }
+    }
+}
+
+async fn delete_attachments(
+    db: &DatabaseConnection,
+    blobs: &dyn BlobStore,
+    deleted_before: DateTime,
+) -> Result<(), DbErr> {
+    loop {
+        let attachments =
+            Query::find_deleted_attachments(db, deleted_before, ATTACHMENT_BATCH_SIZE).await?;
+
+        let (_, failed) = super::attachment::delete(db, blobs, &attachments).await?;
+
+        if failed || (attachments.len() as u64) < ATTACHMENT_BATCH_SIZE {
+            return Ok(());
+        }
+    }
+}
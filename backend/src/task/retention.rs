@@ -0,0 +1,81 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::Utc;
+use common::realtime::ChannelEvent;
+use sea_orm::{prelude::DateTime, DbErr};
+use service::{mutation::Mutation, query::Query};
+use tokio::time;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{realtime, state::ServerState};
+
+/// Often enough for the disappearing messages
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+const CLEANUP_BATCH_SIZE: u64 = 500;
+const ATTACHMENT_BATCH_SIZE: u64 = 100;
+
+/// Deletes the messages past the retention period of their channel or their TTL,
+/// the channels under a legal hold are skipped
+pub async fn run(state: ServerState) {
+    let mut interval = time::interval(CLEANUP_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match cleanup(&state).await {
+            Ok(0) => (),
+            Ok(deleted) => info!(deleted, "deleted the expired messages"),
+            Err(err) => error!("failed to delete the expired messages ({err})"),
+        }
+    }
+}
+
+async fn cleanup(state: &ServerState) -> Result<u64, DbErr> {
+    let now = Utc::now().naive_utc();
+
+    // The messages with the files left are kept, so the next run retries
+    delete_attachments(state, now).await?;
+
+    // Short transactions, so the message table isn't locked for long
+    let mut total = 0;
+    loop {
+        let expired = Mutation::delete_expired_messages(&state.db, now, CLEANUP_BATCH_SIZE).await?;
+        total += expired.len() as u64;
+
+        let mut by_channel = HashMap::<Uuid, Vec<Uuid>>::new();
+        for message in &expired {
+            by_channel
+                .entry(message.channel_id)
+                .or_default()
+                .push(message.id);
+        }
+
+        for (channel_id, message_ids) in by_channel {
+            realtime::publish(
+                &state.redis,
+                channel_id,
+                ChannelEvent::MessagesExpired { message_ids },
+            )
+            .await;
+        }
+
+        if (expired.len() as u64) < CLEANUP_BATCH_SIZE {
+            return Ok(total);
+        }
+    }
+}
+
+async fn delete_attachments(state: &ServerState, now: DateTime) -> Result<(), DbErr> {
+    loop {
+        let attachments =
+            Query::find_expired_attachments(&state.db, now, ATTACHMENT_BATCH_SIZE).await?;
+
+        let (_, failed) =
+            super::attachment::delete(&state.db, state.blobs.as_ref(), &attachments).await?;
+
+        if failed || (attachments.len() as u64) < ATTACHMENT_BATCH_SIZE {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use entity::scheduled_message;
+use sea_orm::DbErr;
+use service::{
+    mutation::{CreateMessageData, CreateMessageError, Mutation},
+    query::Query,
+};
+use tokio::time;
+use tracing::{error, info, warn};
+
+use crate::{channel::message::publish_created, link_preview, state::ServerState};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const DELIVERY_BATCH_SIZE: u64 = 50;
+
+/// Another instance claims the message again after that if this one didn't deliver it
+const CLAIM_LEASE: Duration = Duration::from_secs(60);
+
+/// Sends the due scheduled messages. They are in the database, so the ones due during
+/// a restart are sent right after it.
+pub async fn run(state: ServerState) {
+    let mut interval = time::interval(POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match deliver_due(&state).await {
+            Ok(0) => (),
+            Ok(delivered) => info!(delivered, "delivered the scheduled messages"),
+            Err(err) => error!("failed to deliver the scheduled messages ({err})"),
+        }
+    }
+}
+
+async fn deliver_due(state: &ServerState) -> Result<u64, DbErr> {
+    let mut total = 0;
+    loop {
+        let claimed_until = Utc::now().naive_utc()
+            + chrono::Duration::from_std(CLAIM_LEASE).expect("lease is out of range");
+        let scheduled =
+            Mutation::claim_due_scheduled_messages(&state.db, claimed_until, DELIVERY_BATCH_SIZE)
+                .await?;
+
+        for scheduled in &scheduled {
+            // The unsent messages stay claimed, so they are retried after the lease
+            if let Err(err) = deliver(state, scheduled.clone()).await {
+                error!(scheduled_id = %scheduled.id, "failed to deliver the scheduled message ({err})");
+                continue;
+            }
+
+            total += 1;
+        }
+
+        if (scheduled.len() as u64) < DELIVERY_BATCH_SIZE {
+            return Ok(total);
+        }
+    }
+}
+
+/// The message gets the id of the scheduled one, which is deleted in the same transaction,
+/// so a retry after the send finds nothing to send
+async fn deliver(state: &ServerState, scheduled: scheduled_message::Model) -> Result<(), DbErr> {
+    // The sender could leave the channel since
+    if Query::find_channel_member(&state.db, scheduled.channel_id, scheduled.sender_id)
+        .await?
+        .is_none()
+    {
+        warn!(scheduled_id = %scheduled.id, "the sender isn't a member, dropping the message");
+        return Mutation::delete_scheduled_message(&state.db, scheduled.id).await;
+    }
+
+    let result = Mutation::create_message(
+        &state.db,
+        CreateMessageData {
+            id: Some(scheduled.id),
+            nonce: None,
+            sender_id: scheduled.sender_id,
+            channel_id: scheduled.channel_id,
+            urls: link_preview::extract_urls(&scheduled.content),
+            content: scheduled.content,
+            parent_id: scheduled.parent_id,
+            attachment_ids: Vec::new(),
+        },
+    )
+    .await;
+
+    match result {
+        // Published after the commit, so the clients can fetch the message
+        Ok(message) => {
+            publish_created(state, message).await?;
+        }
+        Err(CreateMessageError::Db(err)) => return Err(err),
+        Err(CreateMessageError::ScheduledNotFound) => (),
+        // The thread root was deleted since, the message can't be sent anymore
+        Err(err) => {
+            warn!(scheduled_id = %scheduled.id, "dropping the scheduled message ({err})");
+            Mutation::delete_scheduled_message(&state.db, scheduled.id).await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use backend::channel::attachment::{
+    download_attachment, upload_attachment, DownloadAttachmentError, Upload, UploadAttachmentError,
+};
+use chrono::NaiveDateTime;
+use entity::{attachment, channel_member, sea_orm_active_enums::ChannelRole};
+use sea_orm::{DatabaseBackend, MockDatabase};
+use uuid::Uuid;
+
+use crate::prepare::*;
+
+mod prepare;
+
+const OTHER_USER_ID: Uuid = Uuid::from_u128(2);
+const ATTACHMENT_ID: Uuid = Uuid::from_u128(4);
+
+fn pending_attachment(uploader_id: Uuid) -> attachment::Model {
+    attachment::Model {
+        id: ATTACHMENT_ID,
+        channel_id: CHANNEL_ID,
+        uploader_id,
+        message_id: None,
+        name: "a.txt".to_owned(),
+        content_type: "text/plain".to_owned(),
+        size: 1,
+        has_thumbnail: false,
+        created_at: NaiveDateTime::default(),
+    }
+}
+
+#[tokio::test]
+async fn rejects_upload_by_non_member() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([Vec::<channel_member::Model>::new()])
+        .into_connection();
+
+    let upload = Upload {
+        name: "a.txt".to_owned(),
+        content_type: "text/plain".to_owned(),
+        data: b"a".to_vec(),
+    };
+
+    let result = upload_attachment(mock_state(db), USER_ID, CHANNEL_ID, upload).await;
+    assert!(matches!(result, Err(UploadAttachmentError::NotMember)));
+}
+
+#[tokio::test]
+async fn rejects_download_by_non_member() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([Vec::<channel_member::Model>::new()])
+        .into_connection();
+
+    let result =
+        download_attachment(mock_state(db), USER_ID, CHANNEL_ID, ATTACHMENT_ID, false).await;
+    assert!(matches!(result, Err(DownloadAttachmentError::NotMember)));
+}
+
+#[tokio::test]
+async fn hides_pending_upload_of_another_user() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[member(ChannelRole::Member)]])
+        .append_query_results([[pending_attachment(OTHER_USER_ID)]])
+        .into_connection();
+
+    let result =
+        download_attachment(mock_state(db), USER_ID, CHANNEL_ID, ATTACHMENT_ID, false).await;
+    assert!(matches!(
+        result,
+        Err(DownloadAttachmentError::AttachmentNotFound)
+    ));
+}
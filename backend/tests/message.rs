@@ -0,0 +1,93 @@
+use backend::channel::message::{
+    delete_message, send_message, DeleteError, SendMessageError, SendMessagePayload,
+};
+use chrono::NaiveDateTime;
+use entity::{
+    attachment, message,
+    sea_orm_active_enums::{ChannelRole, MessageKind},
+};
+use sea_orm::{DatabaseBackend, MockDatabase};
+use uuid::Uuid;
+
+use crate::prepare::*;
+
+mod prepare;
+
+const NONCE: Uuid = Uuid::from_u128(5);
+
+fn sent_message(channel_id: Uuid) -> message::Model {
+    message::Model {
+        id: Uuid::from_u128(4),
+        created_at: NaiveDateTime::default(),
+        sender_id: USER_ID,
+        channel_id,
+        content: "sent".to_owned(),
+        content_html: Some("<p>sent</p>".to_owned()),
+        content_text: Some("sent".to_owned()),
+        edited_at: None,
+        deleted_at: None,
+        deleted_by: None,
+        parent_id: None,
+        reply_count: 0,
+        last_reply_at: None,
+        expires_at: None,
+        nonce: Some(NONCE),
+        seq: 1,
+        kind: MessageKind::User,
+        purged_at: None,
+    }
+}
+
+fn retry() -> SendMessagePayload {
+    SendMessagePayload {
+        content: "retried".to_owned(),
+        parent_id: None,
+        attachment_ids: Vec::new(),
+        nonce: Some(NONCE),
+    }
+}
+
+#[tokio::test]
+async fn returns_message_of_repeated_nonce() {
+    // Nothing is queued for a second insert, the retry must not send again
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[member(ChannelRole::Member)]])
+        .append_query_results([[sent_message(CHANNEL_ID)]])
+        .append_query_results([Vec::<attachment::Model>::new()])
+        .append_query_results([Vec::<attachment::Model>::new()])
+        .into_connection();
+
+    let message = send_message(mock_state(db), USER_ID, CHANNEL_ID, retry())
+        .await
+        .unwrap();
+    assert_eq!(message.id, Uuid::from_u128(4));
+    assert_eq!(message.content, "sent");
+}
+
+#[tokio::test]
+async fn rejects_nonce_of_another_channel() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[member(ChannelRole::Member)]])
+        .append_query_results([[sent_message(Uuid::from_u128(6))]])
+        .into_connection();
+
+    let result = send_message(mock_state(db), USER_ID, CHANNEL_ID, retry()).await;
+    assert!(matches!(result, Err(SendMessageError::NonceReused)));
+}
+
+#[tokio::test]
+async fn rejects_deleting_system_message() {
+    let system = message::Model {
+        kind: MessageKind::System,
+        ..sent_message(CHANNEL_ID)
+    };
+
+    // Not even a moderator can remove it from the history
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[member(ChannelRole::Moderator)]])
+        .append_query_results([[system]])
+        .into_connection();
+
+    let result = delete_message(mock_state(db), USER_ID, CHANNEL_ID, Uuid::from_u128(4)).await;
+    assert!(matches!(result, Err(DeleteError::MessageNotFound)));
+}
@@ -0,0 +1,240 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::{routing::get, Json, Router};
+use backend::{
+    auth::oauth::{
+        id_token::IdTokenError,
+        provider::{ClaimsSource, OAuthProviders},
+    },
+    environment::OAuthProviderEnvironment,
+};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+const REDIRECT_URL: &str = "http://localhost:3000";
+
+const KEY_ID: &str = "test";
+const KEY_PEM: &[u8] = include_bytes!("fixtures/id_token_key.pem");
+const KEY_MODULUS: &str = "nF7MNkmM_sLyFerGGvPyDaFG6Za8iCBElcHhd1qh5oiumgMT_GzwxZLdBCzTDnnyRdpjiYKpNFXIEdUS5NNvlAQsX-132E9FBTaEpVru74psoq2SRAsPa9RxrmDacFJuBaesCFfwkXPc5LSx2htLj8085d-ByikNAQN3qB47n5Ez46cDNSzuPB-AkUl99h0QBctSg9sKtgXMZDzao2igto8Awyr4VQdm8Q-ef75sETYGQ1P2PgDjbcvd-Eg7aTUH5vipRNh6yW946-UTlIa9BAhwqocOGQ02_C4-IP4_Ei4q3YYTbNeVEyUqdhXZD6S8b18qCx7fTGVcwCAtR-GTuw";
+
+/// Serves the discovery document on a random local port and returns the issuer URL
+async fn spawn_mock_issuer(advertised_issuer: Option<&str>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let issuer = format!("http://{}", listener.local_addr().unwrap());
+
+    let metadata = json!({
+        "issuer": advertised_issuer.unwrap_or(&issuer),
+        "authorization_endpoint": format!("{issuer}/authorize"),
+        "token_endpoint": format!("{issuer}/token"),
+        "userinfo_endpoint": format!("{issuer}/userinfo"),
+        "jwks_uri": format!("{issuer}/jwks"),
+    });
+
+    let jwks = json!({
+        "keys": [{
+            "kty": "RSA",
+            "kid": KEY_ID,
+            "use": "sig",
+            "alg": "RS256",
+            "n": KEY_MODULUS,
+            "e": "AQAB",
+        }],
+    });
+
+    let app = Router::new()
+        .route(
+            "/.well-known/openid-configuration",
+            get(move || async move { Json(metadata) }),
+        )
+        .route("/jwks", get(move || async move { Json(jwks) }));
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    issuer
+}
+
+fn provider_environment(issuer: Option<String>) -> OAuthProviderEnvironment {
+    OAuthProviderEnvironment {
+        name: "mock".to_owned(),
+        issuer,
+        auth_url: None,
+        token_url: None,
+        userinfo_url: None,
+        revocation_url: None,
+        client_id: "client".to_owned(),
+        client_secret: "secret".to_owned(),
+        scopes: vec!["openid".to_owned(), "email".to_owned()],
+        email_claim: "email".to_owned(),
+        email_verified_claim: "email_verified".to_owned(),
+        email_verified_by_default: false,
+    }
+}
+
+fn sign_id_token(claims: Value) -> String {
+    sign_id_token_with(Algorithm::RS256, claims)
+}
+
+fn sign_id_token_with(algorithm: Algorithm, claims: Value) -> String {
+    let mut header = Header::new(algorithm);
+    header.kid = Some(KEY_ID.to_owned());
+
+    jsonwebtoken::encode(
+        &header,
+        &claims,
+        &EncodingKey::from_rsa_pem(KEY_PEM).unwrap(),
+    )
+    .unwrap()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[tokio::test]
+async fn discovers_endpoints() {
+    let issuer = spawn_mock_issuer(None).await;
+
+    let mut environment = provider_environment(Some(format!("{issuer}/")));
+    environment.token_url = Some("http://localhost:4000/token".to_owned());
+
+    let providers = OAuthProviders::new(&reqwest::Client::new(), REDIRECT_URL, &[environment])
+        .await
+        .unwrap();
+    let provider = providers.get("mock").unwrap();
+
+    assert_eq!(
+        provider.client.auth_url().as_str(),
+        format!("{issuer}/authorize")
+    );
+    assert_eq!(
+        provider.client.token_url().unwrap().as_str(),
+        "http://localhost:4000/token"
+    );
+    assert_eq!(
+        provider.client.redirect_url().unwrap().as_str(),
+        "http://localhost:3000/api/auth/oauth/mock/authorized"
+    );
+    assert!(matches!(provider.claims_source, ClaimsSource::IdToken(_)));
+    assert!(provider.client.revocation_url().is_none());
+}
+
+#[tokio::test]
+async fn verifies_id_token() {
+    let issuer = spawn_mock_issuer(None).await;
+    let reqwest = reqwest::Client::new();
+
+    let providers = OAuthProviders::new(
+        &reqwest,
+        REDIRECT_URL,
+        &[provider_environment(Some(issuer.clone()))],
+    )
+    .await
+    .unwrap();
+    let provider = providers.get("mock").unwrap();
+
+    let ClaimsSource::IdToken(verifier) = &provider.claims_source else {
+        panic!("the mock issuer supports OpenID Connect");
+    };
+
+    let claims = json!({
+        "iss": issuer,
+        "aud": "client",
+        "exp": now() + 60,
+        "nonce": "nonce",
+        "email": "a@a.com",
+        "email_verified": true,
+    });
+
+    let verified = verifier
+        .verify(&reqwest, &sign_id_token(claims.clone()), "nonce")
+        .await
+        .unwrap();
+    assert_eq!(provider.email(&verified), Some("a@a.com"));
+    assert!(provider.is_email_verified(&verified));
+
+    // Replayed token from another login attempt
+    assert!(verifier
+        .verify(&reqwest, &sign_id_token(claims.clone()), "other")
+        .await
+        .is_err());
+
+    let mut wrong_audience = claims.clone();
+    wrong_audience["aud"] = json!("another-client");
+    assert!(verifier
+        .verify(&reqwest, &sign_id_token(wrong_audience), "nonce")
+        .await
+        .is_err());
+
+    let mut expired = claims.clone();
+    expired["exp"] = json!(now() - 3600);
+    assert!(verifier
+        .verify(&reqwest, &sign_id_token(expired), "nonce")
+        .await
+        .is_err());
+
+    // The key is published for RS256 only
+    assert!(matches!(
+        verifier
+            .verify(
+                &reqwest,
+                &sign_id_token_with(Algorithm::PS256, claims.clone()),
+                "nonce"
+            )
+            .await,
+        Err(IdTokenError::KeyMismatch(Algorithm::PS256))
+    ));
+
+    let mut without_claim = claims.clone();
+    without_claim
+        .as_object_mut()
+        .unwrap()
+        .remove("email_verified");
+    let without_claim = verifier
+        .verify(&reqwest, &sign_id_token(without_claim), "nonce")
+        .await
+        .unwrap();
+    assert!(!provider.is_email_verified(&without_claim));
+
+    let mut unverified = claims;
+    unverified["email_verified"] = json!(false);
+    let unverified = verifier
+        .verify(&reqwest, &sign_id_token(unverified), "nonce")
+        .await
+        .unwrap();
+    assert!(!provider.is_email_verified(&unverified));
+}
+
+#[tokio::test]
+async fn rejects_issuer_mismatch() {
+    let issuer = spawn_mock_issuer(Some("https://attacker.example.com")).await;
+
+    let result = OAuthProviders::new(
+        &reqwest::Client::new(),
+        REDIRECT_URL,
+        &[provider_environment(Some(issuer))],
+    )
+    .await;
+
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn works_without_discovery() {
+    let mut environment = provider_environment(None);
+    environment.auth_url = Some("https://github.com/login/oauth/authorize".to_owned());
+    environment.token_url = Some("https://github.com/login/oauth/access_token".to_owned());
+    environment.userinfo_url = Some("https://api.github.com/user".to_owned());
+
+    let providers = OAuthProviders::new(&reqwest::Client::new(), REDIRECT_URL, &[environment])
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        providers.get("mock").unwrap().claims_source,
+        ClaimsSource::Userinfo(_)
+    ));
+    assert!(providers.get("google").is_none());
+}
@@ -0,0 +1,70 @@
+use backend::auth::passkey::create_webauthn;
+use uuid::Uuid;
+use webauthn_authenticator_rs::{softpasskey::SoftPasskey, WebauthnAuthenticator};
+use webauthn_rs::prelude::{Passkey, Url};
+
+const REDIRECT_URL: &str = "https://localhost:3000";
+
+#[test]
+fn registers_and_authenticates() {
+    let webauthn = create_webauthn(REDIRECT_URL, None).unwrap();
+    let origin = Url::parse(REDIRECT_URL).unwrap();
+    let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new());
+
+    let (options, registration) = webauthn
+        .start_passkey_registration(Uuid::new_v4(), "a@a.com", "a", None)
+        .unwrap();
+    let credential = authenticator
+        .do_registration(origin.clone(), options)
+        .unwrap();
+    let passkey = webauthn
+        .finish_passkey_registration(&credential, &registration)
+        .unwrap();
+
+    // The passkey is stored as JSON in the database
+    let passkey: Passkey = serde_json::from_value(serde_json::to_value(&passkey).unwrap()).unwrap();
+
+    let (options, authentication) = webauthn
+        .start_passkey_authentication(&[passkey.clone()])
+        .unwrap();
+    let credential = authenticator.do_authentication(origin, options).unwrap();
+    let result = webauthn
+        .finish_passkey_authentication(&credential, &authentication)
+        .unwrap();
+
+    assert_eq!(result.cred_id(), passkey.cred_id());
+}
+
+#[test]
+fn rejects_replayed_assertion() {
+    let webauthn = create_webauthn(REDIRECT_URL, None).unwrap();
+    let origin = Url::parse(REDIRECT_URL).unwrap();
+    let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new());
+
+    let (options, registration) = webauthn
+        .start_passkey_registration(Uuid::new_v4(), "a@a.com", "a", None)
+        .unwrap();
+    let credential = authenticator
+        .do_registration(origin.clone(), options)
+        .unwrap();
+    let passkey = webauthn
+        .finish_passkey_registration(&credential, &registration)
+        .unwrap();
+
+    let (options, _) = webauthn
+        .start_passkey_authentication(&[passkey.clone()])
+        .unwrap();
+    let credential = authenticator.do_authentication(origin, options).unwrap();
+
+    // Signed for another challenge
+    let (_, authentication) = webauthn.start_passkey_authentication(&[passkey]).unwrap();
+    assert!(webauthn
+        .finish_passkey_authentication(&credential, &authentication)
+        .is_err());
+}
+
+#[test]
+fn rejects_foreign_rp_id() {
+    assert!(create_webauthn(REDIRECT_URL, Some("example.com")).is_err());
+    assert!(create_webauthn("https://chat.example.com", Some("example.com")).is_ok());
+}
@@ -0,0 +1,35 @@
+use backend::channel::pin::{pin_message, unpin_message, PinError};
+use entity::{channel_member, sea_orm_active_enums::ChannelRole};
+use sea_orm::{DatabaseBackend, MockDatabase};
+use uuid::Uuid;
+
+use crate::prepare::*;
+
+mod prepare;
+
+const MESSAGE_ID: Uuid = Uuid::from_u128(4);
+
+#[tokio::test]
+async fn rejects_pin_by_member() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[member(ChannelRole::Member)]])
+        .append_query_results([[member(ChannelRole::Member)]])
+        .into_connection();
+    let state = mock_state(db);
+
+    let result = pin_message(state.clone(), USER_ID, CHANNEL_ID, MESSAGE_ID).await;
+    assert!(matches!(result, Err(PinError::NotModerator)));
+
+    let result = unpin_message(state, USER_ID, CHANNEL_ID, MESSAGE_ID).await;
+    assert!(matches!(result, Err(PinError::NotModerator)));
+}
+
+#[tokio::test]
+async fn rejects_pin_by_non_member() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([Vec::<channel_member::Model>::new()])
+        .into_connection();
+
+    let result = pin_message(mock_state(db), USER_ID, CHANNEL_ID, MESSAGE_ID).await;
+    assert!(matches!(result, Err(PinError::NotMember)));
+}
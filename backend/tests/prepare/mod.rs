@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use backend::{
+    auth::{
+        oauth::provider::OAuthProviders,
+        passkey::{create_webauthn, PasskeyDecoy},
+        totp::TotpCipher,
+    },
+    blob::local::LocalBlobStore,
+    link_preview,
+    realtime::Realtime,
+    state::ServerState,
+};
+use chrono::NaiveDateTime;
+use entity::{channel_member, sea_orm_active_enums::ChannelRole};
+use leptos::LeptosOptions;
+use rand_chacha::{rand_core::SeedableRng, ChaCha8Rng};
+use sea_orm::DatabaseConnection;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+const REDIRECT_URL: &str = "https://localhost:3000";
+
+pub const USER_ID: Uuid = Uuid::from_u128(1);
+pub const CHANNEL_ID: Uuid = Uuid::from_u128(3);
+
+/// Only the database is used by the handlers under the test
+pub fn mock_state(db: DatabaseConnection) -> ServerState {
+    // Nothing listens there, the realtime subscription just keeps retrying
+    let redis = redis::Client::open("redis://127.0.0.1:1").unwrap();
+
+    ServerState {
+        random: ChaCha8Rng::seed_from_u64(0),
+        reqwest: reqwest::Client::new(),
+        link_preview: link_preview::fetch::client(false).unwrap(),
+        unfurl_permits: Arc::new(Semaphore::new(link_preview::MAX_CONCURRENT_UNFURLS)),
+        oauth: OAuthProviders::default(),
+        totp_cipher: TotpCipher::new(&"11".repeat(32)).unwrap(),
+        webauthn: Arc::new(create_webauthn(REDIRECT_URL, None).unwrap()),
+        passkey_decoy: PasskeyDecoy::new(REDIRECT_URL, None).unwrap(),
+        realtime: Realtime::new(redis.clone()),
+        redis,
+        db,
+        blobs: Arc::new(LocalBlobStore::new(std::env::temp_dir())),
+        leptos_options: LeptosOptions::builder().output_name("test").build(),
+    }
+}
+
+/// The user of the tests in the channel of the tests
+pub fn member(role: ChannelRole) -> channel_member::Model {
+    channel_member::Model {
+        channel_id: CHANNEL_ID,
+        user_id: USER_ID,
+        role,
+        joined_at: NaiveDateTime::default(),
+    }
+}
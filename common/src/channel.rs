@@ -0,0 +1,111 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::message::PinnedMessage;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Channel {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: NaiveDateTime,
+
+    /// Joined only with an invite
+    pub is_private: bool,
+
+    pub topic: Option<String>,
+    pub description: Option<String>,
+
+    /// An emoji
+    pub icon: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChannelDetails {
+    pub channel: Channel,
+
+    /// The newest pin first
+    pub pins: Vec<PinnedMessage>,
+
+    pub retention: ChannelRetention,
+
+    /// The number of the newest message, the catch-up continues from it
+    pub last_seq: i64,
+}
+
+/// How long the messages of the channel are kept
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    Forever,
+
+    /// The messages older than that are deleted
+    Days {
+        days: u32,
+    },
+
+    /// The new messages disappear after that, the sent ones keep their expiry
+    MessageTtl {
+        secs: u32,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChannelRetention {
+    pub policy: RetentionPolicy,
+
+    /// Suspends the deletion, including the purge of the deleted messages
+    pub legal_hold: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct InviteUse {
+    pub user_id: Uuid,
+    pub used_at: NaiveDateTime,
+}
+
+/// The link to a private channel, unusable after the expiry, the last use or the revocation
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChannelInvite {
+    pub id: Uuid,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub max_uses: Option<u32>,
+    pub use_count: u32,
+    pub revoked_at: Option<NaiveDateTime>,
+
+    /// The oldest use first
+    pub uses: Vec<InviteUse>,
+}
+
+/// Only the hash of the token is stored, so it is shown once
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CreatedInvite {
+    pub invite: ChannelInvite,
+    pub token: String,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelRole {
+    Owner,
+    Admin,
+    Moderator,
+    Member,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChannelMember {
+    pub user_id: Uuid,
+    pub name: String,
+    pub role: ChannelRole,
+}
+
+/// A channel of the sidebar, the counts are capped at `MAX_UNREAD_COUNT`
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ChannelSummary {
+    pub channel: Channel,
+    pub unread_count: u32,
+    pub mention_count: u32,
+}
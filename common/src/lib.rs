@@ -1,3 +1,178 @@
+pub mod channel;
+pub mod message;
+pub mod presence;
+pub mod realtime;
+
 pub const MAX_USER_EMAIL_SIZE: usize = 320; // RFC 5321, RFC 5322
 pub const MAX_USER_NAME_SIZE: usize = 20;
 pub const MAX_USER_PASSWORD_SIZE: usize = 100; // Not using in the database
+
+pub const MAX_PASSKEY_NAME_SIZE: usize = 64;
+
+pub const MAX_CHANNEL_NAME_SIZE: usize = 32;
+pub const MAX_CHANNEL_TOPIC_SIZE: usize = 250; // In characters
+pub const MAX_CHANNEL_DESCRIPTION_SIZE: usize = 1000; // In characters
+pub const MAX_MESSAGE_CONTENT_SIZE: usize = 4000; // In characters
+pub const MAX_CHANNEL_PINS: u64 = 50;
+
+/// The composer saves the draft after the typing pauses for that long
+pub const DRAFT_SAVE_DELAY_MS: u64 = 1000;
+
+/// Per user in all the channels
+pub const MAX_SCHEDULED_MESSAGES: u64 = 100;
+pub const MAX_SCHEDULE_AHEAD_DAYS: i64 = 365;
+
+/// The limits of the channel retention policy
+pub const MAX_RETENTION_DAYS: u32 = 3650;
+pub const MIN_MESSAGE_TTL_SECS: u32 = 60;
+pub const MAX_MESSAGE_TTL_SECS: u32 = 30 * 86400; // 30 days
+
+/// The limits of the channel invite links
+pub const MAX_INVITE_USES: u32 = 1000;
+pub const MAX_INVITE_EXPIRY_DAYS: u32 = 30;
+
+pub const DEFAULT_MESSAGE_HISTORY_LIMIT: u64 = 50;
+pub const MAX_MESSAGE_HISTORY_LIMIT: u64 = 100;
+
+/// The typing client repeats the event with the interval, the indicator is hidden
+/// after the expiry without the events
+pub const TYPING_INTERVAL_SECS: u64 = 3;
+pub const TYPING_EXPIRY_SECS: u64 = 6;
+
+/// The connected client sends the heartbeats with the interval, the user is away after
+/// a missed one (a background page is throttled) and offline after the expiry without them
+pub const PRESENCE_HEARTBEAT_SECS: u64 = 30;
+pub const PRESENCE_STALE_SECS: u64 = 60;
+pub const PRESENCE_EXPIRY_SECS: u64 = 90;
+
+/// The client reports the user away after that without any input
+pub const PRESENCE_IDLE_SECS: u64 = 300;
+pub const MAX_PRESENCE_BATCH_SIZE: usize = 100;
+
+/// The badges show "99+" above, so the counting stops there
+pub const MAX_UNREAD_COUNT: u64 = 100;
+
+pub const MAX_ATTACHMENT_SIZE: usize = 10 * 1024 * 1024; // 10 MiB
+pub const MAX_ATTACHMENT_NAME_SIZE: usize = 255;
+pub const MAX_MESSAGE_ATTACHMENTS: usize = 10;
+
+/// The images get the thumbnails, the other files are always downloaded, never shown inline
+pub const ATTACHMENT_IMAGE_TYPES: &[&str] = &["image/gif", "image/jpeg", "image/png", "image/webp"];
+pub const ATTACHMENT_FILE_TYPES: &[&str] = &["application/pdf", "application/zip", "text/plain"];
+
+pub const MAX_SEARCH_QUERY_SIZE: usize = 200;
+pub const DEFAULT_SEARCH_LIMIT: u64 = 20;
+pub const MAX_SEARCH_LIMIT: u64 = 50;
+
+/// The ranking sorts all the matches, so the deep pages aren't served
+pub const MAX_SEARCH_OFFSET: u64 = 500;
+
+/// Only the first links of a message are unfurled
+pub const MAX_LINK_PREVIEWS: usize = 3;
+pub const MAX_LINK_PREVIEW_URL_SIZE: usize = 2048;
+
+pub const MAX_REACTION_EMOJI_SIZE: usize = 32; // In bytes, an emoji can be a long ZWJ sequence
+
+pub const DEFAULT_RETURN_TO: &str = "/";
+pub const MAX_RETURN_TO_SIZE: usize = 2048;
+
+/// Checks that the post-login redirection stays on the same origin
+/// (only absolute paths, without the scheme-relative "//" and "/\\" forms)
+pub fn is_safe_return_to(path: &str) -> bool {
+    path.len() <= MAX_RETURN_TO_SIZE
+        && path.starts_with('/')
+        && !path.starts_with("//")
+        && !path.chars().any(|ch| ch == '\\' || ch.is_control())
+}
+
+/// Reactions are single emojis, not arbitrary text
+/// (no whitespace and at least one non-ASCII character, so the keycaps like "1️⃣" pass)
+pub fn is_valid_reaction_emoji(emoji: &str) -> bool {
+    !emoji.is_empty()
+        && emoji.len() <= MAX_REACTION_EMOJI_SIZE
+        && !emoji.is_ascii()
+        && !emoji
+            .chars()
+            .any(|ch| ch.is_whitespace() || ch.is_control() || ch.is_alphabetic())
+}
+
+fn is_mention_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.')
+}
+
+/// The distinct names mentioned as "@name" in the order of appearance,
+/// the "@" inside a word (like in an email) isn't a mention
+pub fn parse_mentions(content: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut prev = None;
+
+    for (idx, ch) in content.char_indices() {
+        if ch == '@' && !prev.is_some_and(is_mention_char) {
+            let rest = &content[idx + 1..];
+            let end = rest
+                .find(|ch: char| !is_mention_char(ch))
+                .unwrap_or(rest.len());
+
+            // The punctuation after the name
+            let name = rest[..end].trim_end_matches(['.', '-']);
+            if !name.is_empty()
+                && name.chars().count() <= MAX_USER_NAME_SIZE
+                && !names.contains(&name)
+            {
+                names.push(name);
+            }
+        }
+
+        prev = Some(ch);
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_safe_return_to() {
+        assert!(is_safe_return_to("/"));
+        assert!(is_safe_return_to("/channels/general?message=1#last"));
+
+        assert!(!is_safe_return_to(""));
+        assert!(!is_safe_return_to("channels"));
+        assert!(!is_safe_return_to("https://example.com"));
+        assert!(!is_safe_return_to("//example.com"));
+        assert!(!is_safe_return_to("/\\example.com"));
+        assert!(!is_safe_return_to("/\texample.com"));
+        assert!(!is_safe_return_to(&"/".repeat(MAX_RETURN_TO_SIZE + 1)));
+    }
+
+    #[test]
+    fn test_is_valid_reaction_emoji() {
+        assert!(is_valid_reaction_emoji("👍"));
+        assert!(is_valid_reaction_emoji("❤️"));
+        assert!(is_valid_reaction_emoji("1️⃣"));
+        assert!(is_valid_reaction_emoji("👩‍👩‍👧‍👦"));
+
+        assert!(!is_valid_reaction_emoji(""));
+        assert!(!is_valid_reaction_emoji("+1"));
+        assert!(!is_valid_reaction_emoji("👍 👍"));
+        assert!(!is_valid_reaction_emoji("привет"));
+        assert!(!is_valid_reaction_emoji(
+            &"👍".repeat(MAX_REACTION_EMOJI_SIZE)
+        ));
+    }
+
+    #[test]
+    fn test_parse_mentions() {
+        assert_eq!(parse_mentions("@alice hi"), vec!["alice"]);
+        assert_eq!(parse_mentions("hi @bob."), vec!["bob"]);
+        assert_eq!(parse_mentions("@a, @b and @a"), vec!["a", "b"]);
+        assert_eq!(parse_mentions("(@john_doe)"), vec!["john_doe"]);
+        assert_eq!(parse_mentions("@Иван"), vec!["Иван"]);
+
+        assert!(parse_mentions("a@b.com").is_empty());
+        assert!(parse_mentions("@ @@").is_empty());
+        assert!(parse_mentions(&format!("@{}", "a".repeat(MAX_USER_NAME_SIZE + 1))).is_empty());
+    }
+}
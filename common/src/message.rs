@@ -0,0 +1,183 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Message {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+
+    /// The number in the channel, the replies included, without gaps on send
+    pub seq: i64,
+    pub sender_id: Uuid,
+
+    #[serde(default)]
+    pub kind: MessageKind,
+
+    /// Empty for the deleted messages
+    pub content: String,
+
+    /// The sanitized rendering of the Markdown content
+    #[serde(default)]
+    pub content_html: String,
+    pub created_at: NaiveDateTime,
+    pub edited_at: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+
+    /// The root message of the thread, replies to the replies are not supported
+    pub parent_id: Option<Uuid>,
+    pub reply_count: u32,
+    pub last_reply_at: Option<NaiveDateTime>,
+
+    /// In the order of the first reaction
+    #[serde(default)]
+    pub reactions: Vec<Reaction>,
+
+    /// In the order of the upload
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+
+    /// In the order of the links in the content
+    #[serde(default)]
+    pub link_previews: Vec<LinkPreview>,
+}
+
+/// The system messages are posted by the server on the changes of the channel, the sender
+/// is the member who made them
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+    #[default]
+    User,
+    System,
+}
+
+impl Message {
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    pub fn add_reaction(&mut self, emoji: &str, own: bool) {
+        match self
+            .reactions
+            .iter_mut()
+            .find(|reaction| reaction.emoji == emoji)
+        {
+            Some(reaction) => {
+                reaction.count += 1;
+                reaction.reacted |= own;
+            }
+            None => self.reactions.push(Reaction {
+                emoji: emoji.to_owned(),
+                count: 1,
+                reacted: own,
+            }),
+        }
+    }
+
+    pub fn remove_reaction(&mut self, emoji: &str, own: bool) {
+        let Some(idx) = self
+            .reactions
+            .iter()
+            .position(|reaction| reaction.emoji == emoji)
+        else {
+            return;
+        };
+
+        let reaction = &mut self.reactions[idx];
+        reaction.count = reaction.count.saturating_sub(1);
+        if own {
+            reaction.reacted = false;
+        }
+
+        if reaction.count == 0 {
+            self.reactions.remove(idx);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Reaction {
+    pub emoji: String,
+    pub count: u32,
+
+    /// The current user is among the reacted
+    pub reacted: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub name: String,
+    pub content_type: String,
+
+    /// In bytes
+    pub size: u64,
+    pub has_thumbnail: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct LinkPreview {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub site_name: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PinnedMessage {
+    pub message: Message,
+
+    /// None if the user was deleted
+    pub pinned_by: Option<Uuid>,
+    pub pinned_at: NaiveDateTime,
+}
+
+/// The unsent composer text of the user in the channel
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Draft {
+    pub content: String,
+    pub updated_at: NaiveDateTime,
+}
+
+/// A message to be sent at `send_at`, visible only to the sender until then
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScheduledMessage {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub content: String,
+
+    /// UTC
+    pub send_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+/// A previous content of the edited message
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MessageRevision {
+    pub content: String,
+
+    /// When this content was replaced
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SearchResult {
+    pub message: Message,
+
+    /// Higher is better, only comparable within the same search
+    pub rank: f32,
+
+    /// The matched fragments, escaped with the matched words in the `mark` tags
+    pub snippet_html: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Thread {
+    pub root: Message,
+
+    /// The oldest replies first
+    pub replies: Vec<Message>,
+}
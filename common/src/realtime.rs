@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    channel::{Channel, ChannelRetention},
+    message::{LinkPreview, Message, PinnedMessage},
+};
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChannelEvent {
+    MessageCreated(Message),
+    MessageEdited(Message),
+
+    /// The tombstone of the deleted message
+    MessageDeleted(Message),
+
+    ReactionAdded {
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: String,
+    },
+    ReactionRemoved {
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: String,
+    },
+
+    MessagePinned(PinnedMessage),
+    MessageUnpinned {
+        message_id: Uuid,
+    },
+
+    RetentionChanged(ChannelRetention),
+
+    /// The topic, the description or the icon changed
+    ChannelUpdated(Channel),
+
+    /// Deleted by the retention policy of the channel, with their replies
+    MessagesExpired {
+        message_ids: Vec<Uuid>,
+    },
+
+    /// Fetched after the message was sent or edited, replaces the previews
+    LinkPreviews {
+        message_id: Uuid,
+        previews: Vec<LinkPreview>,
+    },
+
+    /// Ephemeral, the member is typing for `TYPING_EXPIRY_SECS` unless repeated
+    Typing {
+        user_id: Uuid,
+    },
+}
+
+/// Sent by the server to the websocket clients subscribed to the channel
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ServerEvent {
+    pub channel_id: Uuid,
+    pub event: ChannelEvent,
+}
+
+/// Sent by the websocket clients, `Typing` every `TYPING_INTERVAL_SECS` while typing in a
+/// subscribed channel and `Heartbeat` every `PRESENCE_HEARTBEAT_SECS`, `away` after
+/// `PRESENCE_IDLE_SECS` without input
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientEvent {
+    Subscribe { channel_id: Uuid },
+    Unsubscribe { channel_id: Uuid },
+    Typing { channel_id: Uuid },
+    Heartbeat { away: bool },
+}
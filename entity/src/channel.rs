@@ -10,12 +10,61 @@ pub struct Model {
     pub created_at: DateTime,
     #[sea_orm(unique)]
     pub name: String,
+    pub is_private: bool,
+    pub retention_days: Option<i32>,
+    pub message_ttl_secs: Option<i32>,
+    pub legal_hold: bool,
+    pub last_seq: i64,
+    pub topic: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub description: Option<String>,
+    pub icon: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::attachment::Entity")]
+    Attachment,
+    #[sea_orm(has_many = "super::channel_invite::Entity")]
+    ChannelInvite,
+    #[sea_orm(has_many = "super::channel_member::Entity")]
+    ChannelMember,
+    #[sea_orm(has_many = "super::channel_read_state::Entity")]
+    ChannelReadState,
     #[sea_orm(has_many = "super::message::Entity")]
     Message,
+    #[sea_orm(has_many = "super::message_draft::Entity")]
+    MessageDraft,
+    #[sea_orm(has_many = "super::message_mention::Entity")]
+    MessageMention,
+    #[sea_orm(has_many = "super::pinned_message::Entity")]
+    PinnedMessage,
+    #[sea_orm(has_many = "super::scheduled_message::Entity")]
+    ScheduledMessage,
+}
+
+impl Related<super::attachment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Attachment.def()
+    }
+}
+
+impl Related<super::channel_invite::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChannelInvite.def()
+    }
+}
+
+impl Related<super::channel_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChannelMember.def()
+    }
+}
+
+impl Related<super::channel_read_state::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChannelReadState.def()
+    }
 }
 
 impl Related<super::message::Entity> for Entity {
@@ -24,4 +73,28 @@ impl Related<super::message::Entity> for Entity {
     }
 }
 
+impl Related<super::message_draft::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessageDraft.def()
+    }
+}
+
+impl Related<super::message_mention::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessageMention.def()
+    }
+}
+
+impl Related<super::pinned_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PinnedMessage.def()
+    }
+}
+
+impl Related<super::scheduled_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ScheduledMessage.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
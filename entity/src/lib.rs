@@ -2,6 +2,22 @@
 
 pub mod prelude;
 
+pub mod attachment;
 pub mod channel;
+pub mod channel_invite;
+pub mod channel_invite_use;
+pub mod channel_member;
+pub mod channel_read_state;
+pub mod link_preview;
 pub mod message;
+pub mod message_draft;
+pub mod message_link;
+pub mod message_mention;
+pub mod message_reaction;
+pub mod message_revision;
+pub mod passkey;
+pub mod pinned_message;
+pub mod recovery_code;
+pub mod scheduled_message;
+pub mod sea_orm_active_enums;
 pub mod user;
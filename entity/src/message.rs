@@ -2,6 +2,8 @@
 
 use sea_orm::entity::prelude::*;
 
+use super::sea_orm_active_enums::MessageKind;
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "message")]
 pub struct Model {
@@ -10,11 +12,29 @@ pub struct Model {
     pub created_at: DateTime,
     pub sender_id: Uuid,
     pub channel_id: Uuid,
+    #[sea_orm(column_type = "Text")]
     pub content: String,
+    pub edited_at: Option<DateTime>,
+    pub deleted_at: Option<DateTime>,
+    pub deleted_by: Option<Uuid>,
+    pub parent_id: Option<Uuid>,
+    pub reply_count: i32,
+    pub last_reply_at: Option<DateTime>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub content_html: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub content_text: Option<String>,
+    pub expires_at: Option<DateTime>,
+    pub nonce: Option<Uuid>,
+    pub seq: i64,
+    pub kind: MessageKind,
+    pub purged_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::attachment::Entity")]
+    Attachment,
     #[sea_orm(
         belongs_to = "super::channel::Entity",
         from = "Column::ChannelId",
@@ -23,6 +43,26 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Channel,
+    #[sea_orm(has_many = "super::message_link::Entity")]
+    MessageLink,
+    #[sea_orm(has_many = "super::message_mention::Entity")]
+    MessageMention,
+    #[sea_orm(has_many = "super::message_reaction::Entity")]
+    MessageReaction,
+    #[sea_orm(has_many = "super::message_revision::Entity")]
+    MessageRevision,
+    #[sea_orm(has_many = "super::pinned_message::Entity")]
+    PinnedMessage,
+    #[sea_orm(has_many = "super::scheduled_message::Entity")]
+    ScheduledMessage,
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::ParentId",
+        to = "Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    SelfRef,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::SenderId",
@@ -33,12 +73,54 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::attachment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Attachment.def()
+    }
+}
+
 impl Related<super::channel::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Channel.def()
     }
 }
 
+impl Related<super::message_link::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessageLink.def()
+    }
+}
+
+impl Related<super::message_mention::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessageMention.def()
+    }
+}
+
+impl Related<super::message_reaction::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessageReaction.def()
+    }
+}
+
+impl Related<super::message_revision::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessageRevision.def()
+    }
+}
+
+impl Related<super::pinned_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PinnedMessage.def()
+    }
+}
+
+impl Related<super::scheduled_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ScheduledMessage.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
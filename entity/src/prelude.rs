@@ -1,5 +1,20 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.6
 
+pub use super::attachment::Entity as Attachment;
 pub use super::channel::Entity as Channel;
+pub use super::channel_invite::Entity as ChannelInvite;
+pub use super::channel_invite_use::Entity as ChannelInviteUse;
+pub use super::channel_member::Entity as ChannelMember;
+pub use super::channel_read_state::Entity as ChannelReadState;
+pub use super::link_preview::Entity as LinkPreview;
 pub use super::message::Entity as Message;
+pub use super::message_draft::Entity as MessageDraft;
+pub use super::message_link::Entity as MessageLink;
+pub use super::message_mention::Entity as MessageMention;
+pub use super::message_reaction::Entity as MessageReaction;
+pub use super::message_revision::Entity as MessageRevision;
+pub use super::passkey::Entity as Passkey;
+pub use super::pinned_message::Entity as PinnedMessage;
+pub use super::recovery_code::Entity as RecoveryCode;
+pub use super::scheduled_message::Entity as ScheduledMessage;
 pub use super::user::Entity as User;
@@ -12,12 +12,69 @@ pub struct Model {
     pub password: String,
     pub name: String,
     pub avatar: Option<String>,
+    pub totp_secret: Option<Vec<u8>>,
+    pub totp_enabled: bool,
+    pub totp_last_step: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::attachment::Entity")]
+    Attachment,
+    #[sea_orm(has_many = "super::channel_invite::Entity")]
+    ChannelInvite,
+    #[sea_orm(has_many = "super::channel_invite_use::Entity")]
+    ChannelInviteUse,
+    #[sea_orm(has_many = "super::channel_member::Entity")]
+    ChannelMember,
+    #[sea_orm(has_many = "super::channel_read_state::Entity")]
+    ChannelReadState,
     #[sea_orm(has_many = "super::message::Entity")]
     Message,
+    #[sea_orm(has_many = "super::message_draft::Entity")]
+    MessageDraft,
+    #[sea_orm(has_many = "super::message_mention::Entity")]
+    MessageMention,
+    #[sea_orm(has_many = "super::message_reaction::Entity")]
+    MessageReaction,
+    #[sea_orm(has_many = "super::passkey::Entity")]
+    Passkey,
+    #[sea_orm(has_many = "super::pinned_message::Entity")]
+    PinnedMessage,
+    #[sea_orm(has_many = "super::recovery_code::Entity")]
+    RecoveryCode,
+    #[sea_orm(has_many = "super::scheduled_message::Entity")]
+    ScheduledMessage,
+}
+
+impl Related<super::attachment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Attachment.def()
+    }
+}
+
+impl Related<super::channel_invite::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChannelInvite.def()
+    }
+}
+
+impl Related<super::channel_invite_use::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChannelInviteUse.def()
+    }
+}
+
+impl Related<super::channel_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChannelMember.def()
+    }
+}
+
+impl Related<super::channel_read_state::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ChannelReadState.def()
+    }
 }
 
 impl Related<super::message::Entity> for Entity {
@@ -26,4 +83,46 @@ impl Related<super::message::Entity> for Entity {
     }
 }
 
+impl Related<super::message_draft::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessageDraft.def()
+    }
+}
+
+impl Related<super::message_mention::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessageMention.def()
+    }
+}
+
+impl Related<super::message_reaction::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MessageReaction.def()
+    }
+}
+
+impl Related<super::passkey::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Passkey.def()
+    }
+}
+
+impl Related<super::pinned_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PinnedMessage.def()
+    }
+}
+
+impl Related<super::recovery_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecoveryCode.def()
+    }
+}
+
+impl Related<super::scheduled_message::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ScheduledMessage.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
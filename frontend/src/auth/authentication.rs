@@ -1,7 +1,12 @@
 use leptos::*;
-use leptos_router::{use_query, IntoParam, Params};
+use leptos_router::{use_query, ActionForm, IntoParam, Params};
 use tracing::error;
 
+use super::{
+    passkey::PasskeyLogin,
+    return_to::{use_return_to, with_return_to},
+};
+
 #[derive(Params, PartialEq)]
 struct AuthenticationParams {
     error: String,
@@ -9,8 +14,24 @@ struct AuthenticationParams {
 
 #[component]
 pub fn Authentication() -> impl IntoView {
+    let authenticate_action = create_server_action::<Authenticate>();
+    let authenticate_result = authenticate_action.value();
+
+    let return_to = use_return_to();
+    let (email, set_email) = create_signal(String::new());
+
     let params = use_query::<AuthenticationParams>();
     let error_msg = move || {
+        if let Some(Err(err)) = authenticate_result() {
+            return match err {
+                ServerFnError::ServerError(val) => Some(val),
+                other => {
+                    error!(description = ?other);
+                    None
+                }
+            };
+        }
+
         params.with(|params| match params {
             Ok(val) => Some(val.error.clone()),
             Err(err) => match err {
@@ -37,18 +58,26 @@ pub fn Authentication() -> impl IntoView {
                 </div>
             </Show>
 
-            <form>
+            <ActionForm action=authenticate_action>
+                <input type="hidden" name="return_to" value=return_to />
+
                 <div class="mb-5 space-y-2 text-sm">
                     <label class="block">
                         "Email"
                         <br/>
-                        <input type="text" id="email" class="px-2 w-full h-7 border border-gray-400 rounded-sm" />
+                        <input
+                            type="text"
+                            name="email"
+                            autocomplete="email webauthn"
+                            on:input=move |ev| set_email(event_target_value(&ev))
+                            class="px-2 w-full h-7 border border-gray-400 rounded-sm"
+                        />
                     </label>
 
                     <label class="block">
                         "Password"
                         <br/>
-                        <input type="password" id="password" class="px-2 w-full h-7 border border-gray-400 rounded-sm" />
+                        <input type="password" name="password" autocomplete="current-password" class="px-2 w-full h-7 border border-gray-400 rounded-sm" />
                     </label>
                 </div>
 
@@ -57,14 +86,18 @@ pub fn Authentication() -> impl IntoView {
                     value="Log in"
                     class="py-1 w-full h-9 text-slate-50 font-semibold bg-blue-400 border border-gray-400 rounded-sm"
                 />
-            </form>
+            </ActionForm>
+
+            <div class="mt-2">
+                <PasskeyLogin email=email />
+            </div>
 
             <div class="inline-flex items-center justify-center w-full">
                 <hr class="w-full h-px my-8 bg-gray-200 border-0" />
                 <span class="absolute px-3 font-medium text-gray-900 -translate-x-1/2 bg-white left-1/2">"or"</span>
             </div>
 
-            <a href="/api/auth/google">
+            <a href=move || with_return_to("/api/auth/oauth/google", return_to()) rel="external">
                 <img class="mx-auto w-10 h-10 p-1 hover:bg-slate-100 rounded" src="/assets/google_logo.svg" />
             </a>
 
@@ -74,14 +107,52 @@ pub fn Authentication() -> impl IntoView {
             </div>
 
             <p class="text-center text-sm text-blue-500 hover:text-blue-300">
-                <a href="/registration">"Create a new account"</a>
+                <a href=move || with_return_to("/registration", return_to())>"Create a new account"</a>
             </p>
         </div>
     }
 }
 
 #[server]
-async fn authenticate(email: String, password: String) -> Result<(), ServerFnError> {
+async fn authenticate(
+    email: String,
+    password: String,
+    return_to: Option<String>,
+) -> Result<(), ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::{
+        auth::authenticate::{self, AuthenticateOutcome, AuthorizatePayload},
+        state::ServerState,
+        INTERNAL_SERVER_ERROR_STR,
+    };
+    use common::{is_safe_return_to, DEFAULT_RETURN_TO};
+    use leptos_axum::extract;
+    use tower_cookies::Cookies;
+
+    let (Some(state), Ok(cookies)) = (
+        use_context::<ServerState>(),
+        extract(|cookies: Cookies| async move { cookies }).await,
+    ) else {
+        error!(description = "Failed to extract");
+        return Err(ServerFnError::ServerError(INTERNAL_SERVER_ERROR_STR.into()));
+    };
+
+    let payload = AuthorizatePayload { email, password };
+    let outcome = authenticate::authenticate(state, cookies, payload)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))?;
+
+    let return_to = return_to.filter(|path| is_safe_return_to(path));
+
+    match outcome {
+        AuthenticateOutcome::Authenticated => {
+            leptos_axum::redirect(return_to.as_deref().unwrap_or(DEFAULT_RETURN_TO))
+        }
+        AuthenticateOutcome::TwoFactorRequired => {
+            leptos_axum::redirect(&with_return_to("/two_factor", return_to))
+        }
+    }
+
     Ok(())
 }
 
@@ -1,3 +1,6 @@
 pub mod authentication;
+pub mod passkey;
 pub mod registration;
 pub mod registration_details;
+mod return_to;
+pub mod two_factor;
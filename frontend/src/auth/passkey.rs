@@ -0,0 +1,278 @@
+use common::{DEFAULT_RETURN_TO, MAX_PASSKEY_NAME_SIZE};
+use gloo_net::http::{Request, Response};
+use leptos::*;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use webauthn_rs_proto::{
+    CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+    RequestChallengeResponse,
+};
+
+use super::return_to::use_return_to;
+
+const CANCELLED_MSG: &str = "The passkey request was cancelled or is not supported";
+
+#[derive(Deserialize)]
+struct ChallengeResponse<T> {
+    challenge_id: String,
+    options: T,
+}
+
+#[derive(Serialize)]
+struct RegisterFinishPayload {
+    challenge_id: String,
+    name: String,
+    credential: RegisterPublicKeyCredential,
+}
+
+#[derive(Serialize)]
+struct AuthenticateStartPayload {
+    email: String,
+}
+
+#[derive(Serialize)]
+struct AuthenticateFinishPayload {
+    challenge_id: String,
+    credential: PublicKeyCredential,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorResponseData,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponseData {
+    kind: String,
+}
+
+/// The passkey ceremonies need the browser WebAuthn API, so they call the backend directly
+async fn post<T: Serialize>(url: &str, body: &T) -> Result<Response, String> {
+    let response = Request::post(url)
+        .json(body)
+        .map_err(|err| err.to_string())?
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if response.ok() {
+        return Ok(response);
+    }
+
+    match response.json::<ErrorResponse>().await {
+        Ok(val) => Err(val.error.kind),
+        Err(_) => Err(response.status_text()),
+    }
+}
+
+async fn post_json<T: Serialize, R: DeserializeOwned>(url: &str, body: &T) -> Result<R, String> {
+    post(url, body)
+        .await?
+        .json()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn register_passkey(name: String) -> Result<(), String> {
+    let challenge: ChallengeResponse<CreationChallengeResponse> =
+        post_json("/api/auth/passkey/register/start", &()).await?;
+
+    let credential = browser::create_credential(challenge.options).await?;
+
+    post(
+        "/api/auth/passkey/register/finish",
+        &RegisterFinishPayload {
+            challenge_id: challenge.challenge_id,
+            name,
+            credential,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn authenticate_with_passkey(email: String) -> Result<(), String> {
+    let challenge: ChallengeResponse<RequestChallengeResponse> = post_json(
+        "/api/auth/passkey/authenticate/start",
+        &AuthenticateStartPayload { email },
+    )
+    .await?;
+
+    let credential = browser::get_credential(challenge.options).await?;
+
+    post(
+        "/api/auth/passkey/authenticate/finish",
+        &AuthenticateFinishPayload {
+            challenge_id: challenge.challenge_id,
+            credential,
+        },
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(feature = "hydrate")]
+mod browser {
+    use wasm_bindgen_futures::JsFuture;
+    use webauthn_rs_proto::{
+        CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+        RequestChallengeResponse,
+    };
+
+    use super::CANCELLED_MSG;
+
+    pub async fn create_credential(
+        options: CreationChallengeResponse,
+    ) -> Result<RegisterPublicKeyCredential, String> {
+        let promise = leptos::window()
+            .navigator()
+            .credentials()
+            .create_with_options(&options.into())
+            .map_err(|_| CANCELLED_MSG.to_owned())?;
+
+        let credential = JsFuture::from(promise)
+            .await
+            .map_err(|_| CANCELLED_MSG.to_owned())?;
+
+        Ok(web_sys::PublicKeyCredential::from(credential).into())
+    }
+
+    pub async fn get_credential(
+        options: RequestChallengeResponse,
+    ) -> Result<PublicKeyCredential, String> {
+        let promise = leptos::window()
+            .navigator()
+            .credentials()
+            .get_with_options(&options.into())
+            .map_err(|_| CANCELLED_MSG.to_owned())?;
+
+        let credential = JsFuture::from(promise)
+            .await
+            .map_err(|_| CANCELLED_MSG.to_owned())?;
+
+        Ok(web_sys::PublicKeyCredential::from(credential).into())
+    }
+}
+
+// Event handlers never run on the server
+#[cfg(not(feature = "hydrate"))]
+mod browser {
+    use webauthn_rs_proto::{
+        CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+        RequestChallengeResponse,
+    };
+
+    use super::CANCELLED_MSG;
+
+    pub async fn create_credential(
+        _options: CreationChallengeResponse,
+    ) -> Result<RegisterPublicKeyCredential, String> {
+        Err(CANCELLED_MSG.to_owned())
+    }
+
+    pub async fn get_credential(
+        _options: RequestChallengeResponse,
+    ) -> Result<PublicKeyCredential, String> {
+        Err(CANCELLED_MSG.to_owned())
+    }
+}
+
+#[component]
+pub fn PasskeyLogin(#[prop(into)] email: Signal<String>) -> impl IntoView {
+    let return_to = use_return_to();
+
+    let authenticate_action = create_action(move |email: &String| {
+        let email = email.clone();
+
+        async move {
+            authenticate_with_passkey(email).await?;
+
+            // Full reload, the session cookie is set now
+            let path = return_to.get_untracked();
+            window()
+                .location()
+                .set_href(path.as_deref().unwrap_or(DEFAULT_RETURN_TO))
+                .map_err(|_| CANCELLED_MSG.to_owned())
+        }
+    });
+
+    let error_msg = move || match authenticate_action.value()() {
+        Some(Err(err)) => Some(view! {
+            <p class="p-1 mb-2 bg-red-400 rounded text-sm text-white break-words">{err}</p>
+        }),
+        _ => None,
+    };
+
+    view! {
+        {error_msg}
+        <button
+            type="button"
+            disabled=move || email.with(String::is_empty) || authenticate_action.pending()()
+            on:click=move |_| authenticate_action.dispatch(email.get_untracked())
+            class="
+                py-1 w-full h-9 font-semibold border border-gray-400 rounded-sm
+                hover:bg-slate-100 disabled:text-gray-400
+            "
+        >
+            "Log in with a passkey"
+        </button>
+    }
+}
+
+#[component]
+pub fn Passkeys() -> impl IntoView {
+    let (name, set_name) = create_signal(String::new());
+
+    let register_action = create_action(|name: &String| register_passkey(name.clone()));
+    let register_result = register_action.value();
+
+    let result_msg = move || match register_result() {
+        Some(Ok(())) => Some(view! {
+            <p class="p-1 mb-5 bg-green-500 rounded-md text-sm text-white">"The passkey was added"</p>
+        }),
+        Some(Err(err)) => Some(view! {
+            <p class="p-1 mb-5 bg-red-400 rounded-md text-sm text-white break-words">
+                "Error(s):"<br/>
+                {err}
+            </p>
+        }),
+        None => None,
+    };
+
+    view! {
+        <div class="
+            absolute top-2/5 left-1/2 -translate-x-1/2 -translate-y-1/2 p-10
+            max-w-xs w-full border rounded-xl shadow-md
+        ">
+            <p class="mb-5 text-xl text-center">"Passkeys"</p>
+            {result_msg}
+            <div class="mb-5 space-y-4 text-sm">
+                <label class="block">
+                    <p class="mb-1">"Name of the new passkey"</p>
+                    <input
+                        type="text"
+                        required=true
+                        maxlength=MAX_PASSKEY_NAME_SIZE
+                        placeholder="My laptop"
+                        prop:value=name
+                        on:input=move |ev| set_name(event_target_value(&ev))
+                        class="px-2 py-1 w-full border border-gray-400 rounded-md"
+                    />
+                </label>
+            </div>
+
+            <button
+                type="button"
+                disabled=move || name.with(|name| name.trim().is_empty()) || register_action.pending()()
+                on:click=move |_| register_action.dispatch(name.get_untracked().trim().to_owned())
+                class="
+                    py-1 w-full h-9 rounded-md hover:cursor-pointer text-white
+                    bg-blue-500 hover:bg-blue-600 disabled:bg-blue-300
+                "
+            >
+                "Add a passkey"
+            </button>
+        </div>
+    }
+}
@@ -4,11 +4,13 @@ use leptos_router::{ActionForm, A};
 use tracing::error;
 use validator::Validate;
 
+use super::return_to::{use_return_to, with_return_to};
 use crate::validation;
 
 #[component]
 pub fn Registration() -> impl IntoView {
     let next_step_action = create_server_action::<GoToRegistrationDetailsStep>();
+    let return_to = use_return_to();
 
     let (password, set_password) = create_signal("".to_owned());
     let (confirm, set_confirm) = create_signal("".to_owned());
@@ -50,6 +52,8 @@ pub fn Registration() -> impl IntoView {
                 <p class="mb-5 text-xl text-center">"Create a new account"</p>
                 {error_msg}
                 <ActionForm action=next_step_action>
+                    <input type="hidden" name="return_to" value=return_to />
+
                     <div class="mb-5 space-y-4 text-sm">
                         <label class="block">
                             <p class="mb-1">"Email"</p>
@@ -131,13 +135,13 @@ pub fn Registration() -> impl IntoView {
                     <span class="absolute px-3 font-medium text-gray-900 -translate-x-1/2 bg-white left-1/2">"or"</span>
                 </div>
 
-                <a href="/api/auth/oauth/google" rel="external">
+                <a href=move || with_return_to("/api/auth/oauth/google", return_to()) rel="external">
                     <img class="mx-auto w-10 h-10 p-1 hover:bg-slate-100 rounded" src="/assets/google_logo.svg" />
                 </a>
             </div>
 
             <div class="px-10 py-5 border rounded-xl shadow-md text-center text-sm">
-                "Already registered? "<A href="/authentication" class="text-blue-500 hover:text-blue-300">"Log in."</A>
+                "Already registered? "<A href=move || with_return_to("/authentication", return_to()) class="text-blue-500 hover:text-blue-300">"Log in."</A>
             </div>
         </div>
     }
@@ -193,11 +197,14 @@ async fn go_to_registration_details_step(
     email: String,
     password: String,
     confirm: String,
+    return_to: Option<String>,
 ) -> Result<(), ServerFnError> {
     use backend::cookies::{
         self, REGISTRATION_EMAIL_TOKEN, REGISTRATION_PASSWORD_TOKEN, REGISTRATION_TYPE_TOKEN,
+        RETURN_TO_TOKEN,
     };
     use backend::INTERNAL_SERVER_ERROR_STR;
+    use common::is_safe_return_to;
     use leptos_axum::extract;
     use service::RegistrationType;
     use tower_cookies::Cookies;
@@ -217,6 +224,10 @@ async fn go_to_registration_details_step(
             REGISTRATION_PASSWORD_TOKEN,
             password,
         ));
+
+        if let Some(return_to) = return_to.filter(|path| is_safe_return_to(path)) {
+            cookies.add(cookies::create_secure_cookie(RETURN_TO_TOKEN, return_to));
+        }
     })
     .await
     {
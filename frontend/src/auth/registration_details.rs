@@ -92,16 +92,31 @@ fn validate_name(name: String) -> Result<(), String> {
 
 #[server]
 async fn register(name: String) -> Result<(), ServerFnError> {
-    use backend::INTERNAL_SERVER_ERROR_STR;
+    use backend::{cookies::RETURN_TO_TOKEN, INTERNAL_SERVER_ERROR_STR};
+    use common::{is_safe_return_to, DEFAULT_RETURN_TO};
     use leptos_axum::extract;
-    use tower_cookies::Cookies;
+    use tower_cookies::{Cookie, Cookies};
     use tracing::error;
 
-    if let Err(_) = extract(|cookies: Cookies| async move {}).await {
+    let Ok(return_to) = extract(|cookies: Cookies| async move {
+        let return_to = cookies
+            .get(RETURN_TO_TOKEN)
+            .map(|cookie| cookie.value().to_owned());
+        cookies.remove(Cookie::build(RETURN_TO_TOKEN).path("/").build());
+
+        return_to
+    })
+    .await
+    else {
         error!(description = "Failed to extract");
         return Err(ServerFnError::ServerError(INTERNAL_SERVER_ERROR_STR.into()));
-    }
+    };
 
-    leptos_axum::redirect("/");
+    leptos_axum::redirect(
+        return_to
+            .as_deref()
+            .filter(|path| is_safe_return_to(path))
+            .unwrap_or(DEFAULT_RETURN_TO),
+    );
     Ok(())
 }
@@ -0,0 +1,31 @@
+use common::is_safe_return_to;
+use leptos::*;
+use leptos_router::{use_query, IntoParam, Params};
+
+#[derive(Params, PartialEq)]
+struct ReturnToParams {
+    return_to: String,
+}
+
+/// The validated `return_to` query parameter of the current page
+pub fn use_return_to() -> Signal<Option<String>> {
+    let params = use_query::<ReturnToParams>();
+
+    Signal::derive(move || {
+        params.with(|params| {
+            params
+                .as_ref()
+                .ok()
+                .map(|params| params.return_to.clone())
+                .filter(|path| is_safe_return_to(path))
+        })
+    })
+}
+
+/// Passes the `return_to` query parameter through to the next step
+pub fn with_return_to(path: &str, return_to: Option<String>) -> String {
+    match return_to {
+        Some(return_to) => format!("{path}?return_to={}", urlencoding::encode(&return_to)),
+        None => path.to_owned(),
+    }
+}
@@ -0,0 +1,100 @@
+use leptos::*;
+use leptos_router::ActionForm;
+use tracing::error;
+
+use super::return_to::use_return_to;
+
+#[component]
+pub fn TwoFactor() -> impl IntoView {
+    let verify_action = create_server_action::<VerifyTwoFactor>();
+    let verify_result = verify_action.value();
+
+    let return_to = use_return_to();
+
+    let error_msg = move || {
+        let Some(Err(err)) = verify_result() else {
+            return None;
+        };
+
+        let msg = match err {
+            ServerFnError::ServerError(val) => val,
+            other => {
+                error!(description = ?other);
+                return None;
+            }
+        };
+
+        Some(view! {
+            <p class="p-1 mb-5 bg-red-400 border-red-500 rounded-md text-sm text-white">
+                "Error(s):"<br/>
+                {msg}
+            </p>
+        })
+    };
+
+    view! {
+        <div class="
+            absolute top-2/5 left-1/2 -translate-x-1/2 -translate-y-1/2 p-10
+            max-w-xs w-full border rounded-xl shadow-md
+        ">
+            <p class="mb-5 text-xl text-center">"Two-factor authentication"</p>
+            {error_msg}
+            <ActionForm action=verify_action>
+                <input type="hidden" name="return_to" value=return_to />
+
+                <div class="mb-5 space-y-4 text-sm">
+                    <label class="block">
+                        <p class="mb-1">"Code from your authenticator app or a recovery code"</p>
+                        <input
+                            type="text"
+                            name="code"
+                            required=true
+                            maxlength=32
+                            autocomplete="one-time-code"
+                            inputmode="numeric"
+                            class="px-2 py-1 w-full border border-gray-400 rounded-md"
+                        />
+                    </label>
+                </div>
+
+                <input
+                    type="submit"
+                    value="Verify"
+                    class="
+                        py-1 w-full h-9 rounded-md hover:cursor-pointer text-white
+                        bg-blue-500 hover:bg-blue-600
+                    "
+                />
+            </ActionForm>
+        </div>
+    }
+}
+
+#[server]
+async fn verify_two_factor(code: String, return_to: Option<String>) -> Result<(), ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::{auth::totp, state::ServerState, INTERNAL_SERVER_ERROR_STR};
+    use common::{is_safe_return_to, DEFAULT_RETURN_TO};
+    use leptos_axum::extract;
+    use tower_cookies::Cookies;
+
+    let (Some(state), Ok(cookies)) = (
+        use_context::<ServerState>(),
+        extract(|cookies: Cookies| async move { cookies }).await,
+    ) else {
+        error!(description = "Failed to extract");
+        return Err(ServerFnError::ServerError(INTERNAL_SERVER_ERROR_STR.into()));
+    };
+
+    totp::verify(state, cookies, &code)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))?;
+
+    leptos_axum::redirect(
+        return_to
+            .as_deref()
+            .filter(|path| is_safe_return_to(path))
+            .unwrap_or(DEFAULT_RETURN_TO),
+    );
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use common::message::Attachment;
+use leptos::*;
+use uuid::Uuid;
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+
+pub fn attachment_url(channel_id: Uuid, attachment_id: Uuid) -> String {
+    format!("/api/channels/{channel_id}/attachments/{attachment_id}")
+}
+
+pub fn format_size(size: u64) -> String {
+    match size {
+        size if size >= MIB => format!("{:.1} MiB", size as f64 / MIB as f64),
+        size if size >= KIB => format!("{:.1} KiB", size as f64 / KIB as f64),
+        size => format!("{size} B"),
+    }
+}
+
+/// The images are shown as the thumbnails, the other files as the download links
+#[component]
+pub fn Attachments(channel_id: Uuid, attachments: Vec<Attachment>) -> impl IntoView {
+    let items = attachments
+        .into_iter()
+        .map(|attachment| {
+            let url = attachment_url(channel_id, attachment.id);
+
+            match attachment.has_thumbnail {
+                true => view! {
+                    <a href=url.clone() target="_blank" rel="noopener noreferrer">
+                        <img
+                            src=format!("{url}/thumbnail")
+                            alt=attachment.name
+                            loading="lazy"
+                            class="max-h-40 rounded-md border border-gray-200"
+                        />
+                    </a>
+                },
+                false => view! {
+                    <a
+                        href=url
+                        download=attachment.name.clone()
+                        class="px-2 py-1 border border-gray-300 rounded-md text-sm hover:bg-slate-100"
+                    >
+                        {attachment.name}
+                        <span class="ml-2 text-xs text-gray-400">{format_size(attachment.size)}</span>
+                    </a>
+                },
+            }
+        })
+        .collect_view();
+
+    view! { <div class="flex flex-wrap items-end gap-2 mt-1">{items}</div> }
+}
+
+/// The multipart upload isn't supported by the server functions, so it calls the backend directly
+#[cfg(feature = "hydrate")]
+pub async fn upload(channel_id: Uuid, file: web_sys::File) -> Result<Attachment, String> {
+    use gloo_net::http::Request;
+    use serde::Deserialize;
+    use web_sys::FormData;
+
+    #[derive(Deserialize)]
+    struct ErrorResponse {
+        error: ErrorResponseData,
+    }
+
+    #[derive(Deserialize)]
+    struct ErrorResponseData {
+        kind: String,
+    }
+
+    let form = FormData::new().map_err(|err| format!("{err:?}"))?;
+    form.append_with_blob_and_filename("file", &file, &file.name())
+        .map_err(|err| format!("{err:?}"))?;
+
+    let response = Request::post(&format!("/api/channels/{channel_id}/attachments"))
+        .body(form)
+        .map_err(|err| err.to_string())?
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    if !response.ok() {
+        return match response.json::<ErrorResponse>().await {
+            Ok(val) => Err(val.error.kind),
+            Err(_) => Err(response.status_text()),
+        };
+    }
+
+    response.json().await.map_err(|err| err.to_string())
+}
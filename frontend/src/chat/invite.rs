@@ -0,0 +1,71 @@
+use leptos::*;
+use leptos_router::{use_params_map, ActionForm};
+use tracing::error;
+
+/// The page of an invite link, joins the channel and opens it
+#[component]
+pub fn JoinInvite() -> impl IntoView {
+    let params = use_params_map();
+    let token = move || params.with(|params| params.get("token").cloned().unwrap_or_default());
+
+    let join_action = create_server_action::<JoinByInvite>();
+    let join_result = join_action.value();
+
+    let error_msg = move || {
+        let Some(Err(err)) = join_result() else {
+            return None;
+        };
+
+        let msg = match err {
+            ServerFnError::ServerError(val) => val,
+            other => {
+                error!(description = ?other);
+                return None;
+            }
+        };
+
+        Some(view! {
+            <p class="p-1 mb-5 bg-red-400 border-red-500 rounded-md text-sm text-white">
+                "Error(s):"<br/>
+                {msg}
+            </p>
+        })
+    };
+
+    view! {
+        <div class="
+            absolute top-2/5 left-1/2 -translate-x-1/2 -translate-y-1/2 p-10
+            max-w-xs w-full border rounded-xl shadow-md
+        ">
+            <p class="mb-5 text-xl text-center">"You were invited to a channel"</p>
+            {error_msg}
+            <ActionForm action=join_action>
+                <input type="hidden" name="token" value=token />
+
+                <input
+                    type="submit"
+                    value="Join"
+                    class="
+                        py-1 w-full h-9 rounded-md hover:cursor-pointer text-white
+                        bg-blue-500 hover:bg-blue-600
+                    "
+                />
+            </ActionForm>
+        </div>
+    }
+}
+
+#[server]
+async fn join_by_invite(token: String) -> Result<(), ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::invite;
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    let channel = invite::join_by_invite(state, user_id, token)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))?;
+
+    leptos_axum::redirect(&format!("/channels/{}", channel.id));
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use common::message::LinkPreview;
+use leptos::*;
+
+/// The cards under the message, the images are loaded from the linked site
+#[component]
+pub fn LinkPreviews(previews: Vec<LinkPreview>) -> impl IntoView {
+    let cards = previews
+        .into_iter()
+        .map(|preview| {
+            // The plain http images would be blocked as the mixed content anyway
+            let image = preview
+                .image_url
+                .filter(|url| url.starts_with("https://"))
+                .map(|url| {
+                    view! {
+                        <img
+                            src=url
+                            alt=""
+                            loading="lazy"
+                            referrerpolicy="no-referrer"
+                            class="w-20 h-20 object-cover rounded-md"
+                        />
+                    }
+                });
+
+            view! {
+                <a
+                    href=preview.url
+                    target="_blank"
+                    rel="noopener noreferrer nofollow"
+                    class="flex gap-3 max-w-md p-2 border-l-4 border-gray-300 rounded-md bg-white hover:bg-slate-100"
+                >
+                    <div class="flex flex-col min-w-0">
+                        {preview.site_name.map(|site_name| view! {
+                            <span class="text-xs text-gray-400">{site_name}</span>
+                        })}
+                        {preview.title.map(|title| view! {
+                            <span class="text-sm font-semibold text-blue-500 truncate">{title}</span>
+                        })}
+                        {preview.description.map(|description| view! {
+                            <span class="text-sm text-gray-600 line-clamp-3">{description}</span>
+                        })}
+                    </div>
+                    {image}
+                </a>
+            }
+        })
+        .collect_view();
+
+    view! { <div class="flex flex-col gap-1 mt-1">{cards}</div> }
+}
@@ -0,0 +1,1780 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use common::{
+    channel::{Channel, ChannelMember, ChannelRetention, ChannelRole, RetentionPolicy},
+    message::{Attachment, Message, MessageKind, PinnedMessage, Thread},
+    parse_mentions,
+    presence::Presence,
+    realtime::{ChannelEvent, ClientEvent},
+    DEFAULT_MESSAGE_HISTORY_LIMIT, DRAFT_SAVE_DELAY_MS, MAX_CHANNEL_DESCRIPTION_SIZE,
+    MAX_CHANNEL_TOPIC_SIZE, MAX_MESSAGE_ATTACHMENTS, MAX_MESSAGE_CONTENT_SIZE,
+    PRESENCE_HEARTBEAT_SECS, TYPING_EXPIRY_SECS, TYPING_INTERVAL_SECS,
+};
+use futures_channel::mpsc::UnboundedSender;
+use leptos::*;
+use leptos_router::use_params_map;
+use serde::{Deserialize, Serialize};
+use tracing::error;
+use uuid::Uuid;
+
+use self::{
+    attachment::{format_size, Attachments},
+    link_preview::LinkPreviews,
+    sidebar::Sidebar,
+};
+
+mod attachment;
+pub mod invite;
+mod link_preview;
+mod realtime;
+mod sidebar;
+
+const DELETED_MSG: &str = "This message was deleted";
+const QUICK_REACTIONS: [&str; 4] = ["👍", "❤️", "😂", "🎉"];
+
+/// The rendered Markdown has no classes, so it's styled by the container
+const MESSAGE_CONTENT_CLASS: &str = "
+    break-words
+    [&_a]:text-blue-500 [&_a]:underline
+    [&_blockquote]:pl-2 [&_blockquote]:border-l-4 [&_blockquote]:border-gray-300
+    [&_blockquote]:text-gray-600
+    [&_code]:px-1 [&_code]:rounded [&_code]:bg-slate-100 [&_code]:font-mono [&_code]:text-sm
+    [&_pre]:my-1 [&_pre]:p-2 [&_pre]:rounded-md [&_pre]:bg-slate-100 [&_pre]:overflow-x-auto
+    [&_pre_code]:p-0
+";
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ChatDetails {
+    pub channel: Channel,
+    pub user_id: Uuid,
+    pub members: Vec<ChannelMember>,
+
+    /// The oldest messages first
+    pub messages: Vec<Message>,
+
+    /// The newest pin first
+    pub pins: Vec<PinnedMessage>,
+
+    pub retention: ChannelRetention,
+
+    /// Loaded before the messages, so nothing sent meanwhile is missed by the catch-up
+    pub last_seq: i64,
+
+    /// The unsent text of the channel composer
+    pub draft: Option<String>,
+}
+
+/// The live events and the responses can arrive out of order
+fn insert_by_seq(messages: &mut Vec<Message>, message: Message) {
+    let idx = messages.partition_point(|other| other.seq < message.seq);
+    messages.insert(idx, message);
+}
+
+/// A message until the server confirms it, the retries reuse the nonce so it's sent once
+#[derive(Clone, Debug, PartialEq)]
+struct OutboxMessage {
+    nonce: Uuid,
+    parent_id: Option<Uuid>,
+    content: String,
+    attachment_ids: Vec<Uuid>,
+
+    /// Of the last attempt, cleared by the retry
+    error: Option<String>,
+}
+
+/// The state of the opened channel shared by the chat components
+#[derive(Clone, Copy)]
+struct ChatContext {
+    channel_id: Uuid,
+    user_id: Uuid,
+    members: StoredValue<Vec<ChannelMember>>,
+
+    /// The topic, the description and the icon follow the events
+    channel: RwSignal<Channel>,
+    messages: RwSignal<Vec<Message>>,
+    thread: RwSignal<Option<Thread>>,
+    pins: RwSignal<Vec<PinnedMessage>>,
+    retention: RwSignal<ChannelRetention>,
+
+    /// The sent messages arrive from the response and the websocket both
+    seen: StoredValue<HashSet<Uuid>>,
+
+    outbox: RwSignal<Vec<OutboxMessage>>,
+
+    /// The newest message or reply, the read marker follows it
+    latest: RwSignal<Option<Uuid>>,
+
+    /// The number of the newest message or reply received
+    last_seq: StoredValue<i64>,
+
+    /// The websocket queue, set once connected
+    outgoing: StoredValue<Option<UnboundedSender<ClientEvent>>>,
+
+    /// The other typing members until the expiry
+    typing: RwSignal<Vec<(Uuid, DateTime<Utc>)>>,
+    typing_sent_at: StoredValue<Option<DateTime<Utc>>>,
+
+    presence: RwSignal<HashMap<Uuid, Presence>>,
+}
+
+impl ChatContext {
+    fn new(details: ChatDetails) -> Self {
+        let seen = details.messages.iter().map(|message| message.id).collect();
+        let latest = details.messages.last().map(|message| message.id);
+
+        Self {
+            channel_id: details.channel.id,
+            user_id: details.user_id,
+            members: store_value(details.members),
+            channel: create_rw_signal(details.channel),
+            messages: create_rw_signal(details.messages),
+            thread: create_rw_signal(None),
+            pins: create_rw_signal(details.pins),
+            retention: create_rw_signal(details.retention),
+            seen: store_value(seen),
+            outbox: create_rw_signal(Vec::new()),
+            latest: create_rw_signal(latest),
+            last_seq: store_value(details.last_seq),
+            outgoing: store_value(None),
+            typing: create_rw_signal(Vec::new()),
+            typing_sent_at: store_value(None),
+            presence: create_rw_signal(HashMap::new()),
+        }
+    }
+
+    fn refresh_presence(&self) {
+        let chat = *self;
+        let user_ids = self
+            .members
+            .with_value(|members| members.iter().map(|member| member.user_id).collect());
+
+        spawn_local(async move {
+            match load_presence(user_ids).await {
+                Ok(presence) => chat.presence.set(presence),
+                Err(err) => error!(description = ?err),
+            }
+        });
+    }
+
+    /// Throttled to one event per `TYPING_INTERVAL_SECS`
+    fn notify_typing(&self) {
+        let now = Utc::now();
+        let interval = chrono::Duration::seconds(TYPING_INTERVAL_SECS as i64);
+        if self
+            .typing_sent_at
+            .get_value()
+            .is_some_and(|sent_at| now - sent_at < interval)
+        {
+            return;
+        }
+
+        self.typing_sent_at.set_value(Some(now));
+        self.outgoing.with_value(|outgoing| {
+            if let Some(outgoing) = outgoing {
+                let _ = outgoing.unbounded_send(ClientEvent::Typing {
+                    channel_id: self.channel_id,
+                });
+            }
+        });
+    }
+
+    fn set_typing(&self, user_id: Uuid) {
+        if user_id == self.user_id {
+            return;
+        }
+
+        let expires_at = Utc::now() + chrono::Duration::seconds(TYPING_EXPIRY_SECS as i64);
+        self.typing.update(|typing| {
+            typing.retain(|(typing_id, _)| *typing_id != user_id);
+            typing.push((user_id, expires_at));
+        });
+
+        // Unless the typing was repeated
+        let chat = *self;
+        set_timeout(
+            move || {
+                chat.typing
+                    .update(|typing| typing.retain(|entry| *entry != (user_id, expires_at)))
+            },
+            Duration::from_secs(TYPING_EXPIRY_SECS),
+        );
+    }
+
+    fn sender_name(&self, sender_id: Uuid) -> String {
+        self.members.with_value(|members| {
+            members
+                .iter()
+                .find(|member| member.user_id == sender_id)
+                .map(|member| member.name.clone())
+                .unwrap_or_else(|| "Unknown".to_owned())
+        })
+    }
+
+    fn is_moderator(&self) -> bool {
+        self.members.with_value(|members| {
+            members
+                .iter()
+                .find(|member| member.user_id == self.user_id)
+                .is_some_and(|member| {
+                    matches!(
+                        member.role,
+                        ChannelRole::Owner | ChannelRole::Admin | ChannelRole::Moderator
+                    )
+                })
+        })
+    }
+
+    fn is_admin(&self) -> bool {
+        self.members.with_value(|members| {
+            members
+                .iter()
+                .find(|member| member.user_id == self.user_id)
+                .is_some_and(|member| {
+                    matches!(member.role, ChannelRole::Owner | ChannelRole::Admin)
+                })
+        })
+    }
+
+    /// The pin list follows the events
+    fn toggle_pin(&self, message_id: Uuid, pinned: bool) {
+        let channel_id = self.channel_id;
+
+        spawn_local(async move {
+            let result = match pinned {
+                true => unpin_message(channel_id, message_id).await,
+                false => pin_message(channel_id, message_id).await,
+            };
+
+            if let Err(err) = result {
+                error!(description = ?err);
+            }
+        });
+    }
+
+    fn mentions_me(&self, message: &Message) -> bool {
+        self.members.with_value(|members| {
+            members
+                .iter()
+                .find(|member| member.user_id == self.user_id)
+                .is_some_and(|member| parse_mentions(&message.content).contains(&&*member.name))
+        })
+    }
+
+    fn apply_event(&self, event: ChannelEvent) {
+        match event {
+            ChannelEvent::MessageCreated(message) => self.insert(message),
+            ChannelEvent::MessageEdited(message) => {
+                self.replace_pin(&message);
+                self.replace(message)
+            }
+            ChannelEvent::MessageDeleted(message) => {
+                // The server doesn't count the deleted replies
+                if let Some(parent_id) = message.parent_id {
+                    self.update_message(parent_id, |root| {
+                        root.reply_count = root.reply_count.saturating_sub(1);
+                    });
+                }
+
+                self.replace_pin(&message);
+                self.replace(message)
+            }
+            ChannelEvent::MessagePinned(pin) => self.pins.update(|pins| {
+                pins.retain(|other| other.message.id != pin.message.id);
+                pins.insert(0, pin);
+            }),
+            ChannelEvent::MessageUnpinned { message_id } => self
+                .pins
+                .update(|pins| pins.retain(|pin| pin.message.id != message_id)),
+            ChannelEvent::ReactionAdded {
+                message_id,
+                user_id,
+                emoji,
+            } => self.react(message_id, user_id, &emoji, true),
+            ChannelEvent::ReactionRemoved {
+                message_id,
+                user_id,
+                emoji,
+            } => self.react(message_id, user_id, &emoji, false),
+            ChannelEvent::LinkPreviews {
+                message_id,
+                previews,
+            } => self.update_message(message_id, |message| {
+                if !message.is_deleted() {
+                    message.link_previews = previews.clone();
+                }
+            }),
+            ChannelEvent::RetentionChanged(retention) => self.retention.set(retention),
+            ChannelEvent::ChannelUpdated(channel) => self.channel.set(channel),
+            ChannelEvent::MessagesExpired { message_ids } => self.remove(&message_ids),
+            ChannelEvent::Typing { user_id } => self.set_typing(user_id),
+        }
+    }
+
+    /// Fetches the messages sent while the websocket was disconnected
+    fn catch_up(&self) {
+        let chat = *self;
+
+        spawn_local(async move {
+            loop {
+                let after_seq = chat.last_seq.get_value();
+                let messages = match catch_up(chat.channel_id, after_seq).await {
+                    Ok(val) => val,
+                    Err(err) => {
+                        error!(description = ?err);
+                        return;
+                    }
+                };
+
+                let done = (messages.len() as u64) < DEFAULT_MESSAGE_HISTORY_LIMIT;
+                for message in messages {
+                    // The seen messages don't move the number otherwise
+                    chat.last_seq
+                        .update_value(|last_seq| *last_seq = (*last_seq).max(message.seq));
+                    chat.insert(message);
+                }
+
+                if done {
+                    return;
+                }
+            }
+        });
+    }
+
+    fn send(&self, message: OutboxMessage) {
+        let nonce = message.nonce;
+        self.outbox.update(|outbox| outbox.push(message));
+        self.deliver(nonce);
+    }
+
+    /// The server returns the message of the earlier attempt with the same nonce
+    fn deliver(&self, nonce: Uuid) {
+        let chat = *self;
+        let Some(message) = self.outbox.with_untracked(|outbox| {
+            outbox
+                .iter()
+                .find(|message| message.nonce == nonce)
+                .cloned()
+        }) else {
+            return;
+        };
+        self.set_outbox_error(nonce, None);
+
+        spawn_local(async move {
+            let result = send_message(
+                chat.channel_id,
+                message.content,
+                message.parent_id,
+                message.attachment_ids,
+                Some(nonce),
+            )
+            .await;
+
+            match result {
+                Ok(message) => {
+                    chat.discard(nonce);
+                    chat.insert(message);
+                }
+                Err(ServerFnError::ServerError(msg)) => chat.set_outbox_error(nonce, Some(msg)),
+                Err(err) => chat.set_outbox_error(nonce, Some(err.to_string())),
+            }
+        });
+    }
+
+    fn discard(&self, nonce: Uuid) {
+        self.outbox
+            .update(|outbox| outbox.retain(|message| message.nonce != nonce));
+    }
+
+    fn set_outbox_error(&self, nonce: Uuid, error: Option<String>) {
+        self.outbox.update(|outbox| {
+            if let Some(message) = outbox.iter_mut().find(|message| message.nonce == nonce) {
+                message.error = error;
+            }
+        });
+    }
+
+    /// The expired thread root closes the thread
+    fn remove(&self, message_ids: &[Uuid]) {
+        self.messages
+            .update(|messages| messages.retain(|message| !message_ids.contains(&message.id)));
+        self.pins
+            .update(|pins| pins.retain(|pin| !message_ids.contains(&pin.message.id)));
+
+        self.thread.update(|thread| {
+            if thread
+                .as_ref()
+                .is_some_and(|thread| message_ids.contains(&thread.root.id))
+            {
+                *thread = None;
+            } else if let Some(thread) = thread {
+                thread
+                    .replies
+                    .retain(|reply| !message_ids.contains(&reply.id));
+            }
+        });
+    }
+
+    /// The own reactions are applied right after the request, so their events are skipped
+    fn react(&self, message_id: Uuid, user_id: Uuid, emoji: &str, added: bool) {
+        let own = user_id == self.user_id;
+
+        self.update_message(message_id, |message| {
+            let reacted = message
+                .reactions
+                .iter()
+                .any(|reaction| reaction.emoji == emoji && reaction.reacted);
+
+            match added {
+                true if !(own && reacted) => message.add_reaction(emoji, own),
+                false if !own || reacted => message.remove_reaction(emoji, own),
+                _ => (),
+            }
+        });
+    }
+
+    fn update_message(&self, message_id: Uuid, f: impl Fn(&mut Message)) {
+        self.messages.update(|messages| {
+            if let Some(message) = messages.iter_mut().find(|message| message.id == message_id) {
+                f(message);
+            }
+        });
+
+        self.thread.update(|thread| {
+            let Some(thread) = thread else {
+                return;
+            };
+
+            if thread.root.id == message_id {
+                f(&mut thread.root);
+            }
+            if let Some(reply) = thread
+                .replies
+                .iter_mut()
+                .find(|reply| reply.id == message_id)
+            {
+                f(reply);
+            }
+        });
+    }
+
+    fn insert(&self, message: Message) {
+        let inserted = self.seen.try_update_value(|seen| seen.insert(message.id));
+        if inserted != Some(true) {
+            return;
+        }
+
+        self.latest.set(Some(message.id));
+        self.last_seq
+            .update_value(|last_seq| *last_seq = (*last_seq).max(message.seq));
+
+        // The message is sent, the sender doesn't type anymore
+        self.typing.update(|typing| {
+            typing.retain(|(typing_id, _)| *typing_id != message.sender_id);
+        });
+
+        let Some(parent_id) = message.parent_id else {
+            self.messages
+                .update(|messages| insert_by_seq(messages, message));
+            return;
+        };
+
+        let count_reply = |root: &mut Message| {
+            root.reply_count += 1;
+            root.last_reply_at = Some(message.created_at);
+        };
+
+        self.messages.update(|messages| {
+            if let Some(root) = messages.iter_mut().find(|root| root.id == parent_id) {
+                count_reply(root);
+            }
+        });
+
+        self.thread.update(|thread| {
+            if let Some(thread) = thread.as_mut().filter(|thread| thread.root.id == parent_id) {
+                count_reply(&mut thread.root);
+                insert_by_seq(&mut thread.replies, message.clone());
+            }
+        });
+    }
+
+    fn replace(&self, message: Message) {
+        self.update_message(message.id, |old| {
+            // The events don't carry the reactions as they are seen by the current user,
+            // the attachments don't change after the send,
+            // the previews of the new links come later in their own event
+            let (reactions, attachments, mut link_previews) = match message.is_deleted() {
+                true => (Vec::new(), Vec::new(), Vec::new()),
+                false => (
+                    std::mem::take(&mut old.reactions),
+                    std::mem::take(&mut old.attachments),
+                    std::mem::take(&mut old.link_previews),
+                ),
+            };
+            link_previews.retain(|preview| message.content.contains(&preview.url));
+
+            *old = Message {
+                reactions,
+                attachments,
+                link_previews,
+                ..message.clone()
+            };
+        });
+    }
+
+    /// The deleted messages are unpinned by the server
+    fn replace_pin(&self, message: &Message) {
+        self.pins.update(|pins| match message.is_deleted() {
+            true => pins.retain(|pin| pin.message.id != message.id),
+            false => {
+                if let Some(pin) = pins.iter_mut().find(|pin| pin.message.id == message.id) {
+                    pin.message.content = message.content.clone();
+                    pin.message.content_html = message.content_html.clone();
+                    pin.message.edited_at = message.edited_at;
+                }
+            }
+        });
+    }
+
+    fn toggle_reaction(&self, message_id: Uuid, emoji: String, reacted: bool) {
+        let chat = *self;
+
+        spawn_local(async move {
+            let result = match reacted {
+                true => remove_reaction(chat.channel_id, message_id, emoji.clone()).await,
+                false => add_reaction(chat.channel_id, message_id, emoji.clone()).await,
+            };
+
+            match result {
+                Ok(()) => chat.react(message_id, chat.user_id, &emoji, !reacted),
+                Err(err) => error!(description = ?err),
+            }
+        });
+    }
+
+    fn open_thread(&self, root_id: Uuid) {
+        let chat = *self;
+
+        spawn_local(async move {
+            let thread = match load_thread(chat.channel_id, root_id).await {
+                Ok(val) => val,
+                Err(err) => {
+                    error!(description = ?err);
+                    return;
+                }
+            };
+
+            chat.seen.update_value(|seen| {
+                seen.extend(thread.replies.iter().map(|reply| reply.id));
+            });
+
+            // The loaded root has the fresh reply count
+            let root = thread.root.clone();
+            chat.update_message(root.id, |old| *old = root.clone());
+            chat.thread.set(Some(thread));
+        });
+    }
+}
+
+/// The partial name after the "@" being typed at the end of the content
+fn typed_mention(content: &str) -> Option<&str> {
+    let start = content.rfind('@')?;
+    let prefix = &content[start + 1..];
+
+    let starts_word = content[..start]
+        .chars()
+        .next_back()
+        .map_or(true, char::is_whitespace);
+
+    (starts_word && !prefix.contains(char::is_whitespace)).then_some(prefix)
+}
+
+#[component]
+pub fn Chat() -> impl IntoView {
+    let params = use_params_map();
+    let channel_id = move || {
+        params.with(|params| {
+            params
+                .get("channel_id")
+                .and_then(|channel_id| Uuid::parse_str(channel_id).ok())
+        })
+    };
+
+    let details = create_resource(channel_id, |channel_id| async move {
+        match channel_id {
+            Some(channel_id) => load_chat(channel_id).await,
+            None => Err(ServerFnError::ServerError("Channel not found".to_owned())),
+        }
+    });
+
+    let chat_view = move || {
+        details().map(|details| match details {
+            Ok(details) => view! { <ChatView details /> }.into_view(),
+            Err(err) => {
+                let msg = match err {
+                    ServerFnError::ServerError(val) => val,
+                    other => {
+                        error!(description = ?other);
+                        "Failed to load the channel".to_owned()
+                    }
+                };
+
+                view! {
+                    <p class="p-1 m-5 bg-red-400 rounded-md text-sm text-white">{msg}</p>
+                }
+                .into_view()
+            }
+        })
+    };
+
+    view! {
+        <main class="pt-5 pb-10 h-full font-inter">
+            <div class="mx-auto max-w-screen-lg h-full bg-white border border-gray-200 rounded-md">
+                <Suspense fallback=|| view! {
+                    <div class="mx-auto w-full max-w-2xl h-full flex flex-col justify-end gap-5">
+                        <ChatStub />
+                    </div>
+                }>
+                    {chat_view}
+                </Suspense>
+            </div>
+        </main>
+    }
+}
+
+#[component]
+fn ChatView(details: ChatDetails) -> impl IntoView {
+    let draft = details.draft.clone();
+    let chat = ChatContext::new(details);
+    provide_context(chat);
+
+    chat.outgoing.set_value(realtime::connect(chat));
+
+    // The heartbeats of the others refresh their presence with the same interval
+    create_effect(move |_| {
+        chat.refresh_presence();
+
+        let interval = set_interval_with_handle(
+            move || chat.refresh_presence(),
+            Duration::from_secs(PRESENCE_HEARTBEAT_SECS),
+        );
+        on_cleanup(move || {
+            if let Ok(interval) = interval {
+                interval.clear();
+            }
+        });
+    });
+
+    // The chat is open, so everything in it is read
+    let mark_read_action = create_action(move |message_id: &Uuid| {
+        let message_id = *message_id;
+        async move {
+            if let Err(err) = mark_read(chat.channel_id, message_id).await {
+                error!(description = ?err);
+            }
+        }
+    });
+    create_effect(move |_| {
+        if let Some(message_id) = chat.latest.get() {
+            mark_read_action.dispatch(message_id);
+        }
+    });
+
+    let typing_msg = move || {
+        let names: Vec<String> = chat.typing.with(|typing| {
+            typing
+                .iter()
+                .map(|(user_id, _)| chat.sender_name(*user_id))
+                .collect()
+        });
+
+        match names.as_slice() {
+            [] => None,
+            [name] => Some(format!("{name} is typing…")),
+            names => Some(format!("{} are typing…", names.join(", "))),
+        }
+    };
+
+    let thread_panel = move || {
+        chat.thread
+            .with(Option::is_some)
+            .then(|| view! { <ThreadPanel /> })
+    };
+
+    view! {
+        <div class="h-full flex">
+            <Sidebar current=chat.channel_id refresh=mark_read_action.version() />
+            <div class="flex-auto h-full flex flex-col">
+                <div class="px-5 py-3 border-b border-gray-200 flex items-center gap-3">
+                    <ChannelHeader />
+                    <Pins />
+                    <RetentionNote />
+                </div>
+                <div class="flex-auto h-0 px-5 py-3 flex flex-col justify-end gap-3 overflow-y-auto">
+                    <For
+                        each=chat.messages
+                        // Any change renders the message again
+                        key=|message| message.clone()
+                        children=|message| view! { <MessageItem message in_thread=false /> }
+                    />
+                    <Outbox parent_id=None />
+                </div>
+                <p class="px-5 h-4 text-xs text-gray-400">{typing_msg}</p>
+                <Composer parent_id=None draft=draft.unwrap_or_default() />
+            </div>
+            {thread_panel}
+        </div>
+    }
+}
+
+/// The name with the icon and the topic, the description is in the tooltip
+#[component]
+fn ChannelHeader() -> impl IntoView {
+    let chat = expect_context::<ChatContext>();
+    let (editing, set_editing) = create_signal(false);
+
+    let edit_button = chat.is_admin().then(|| {
+        view! {
+            <button
+                type="button"
+                on:click=move |_| set_editing.update(|editing| *editing = !*editing)
+                class="text-xs text-gray-400 hover:text-gray-600"
+            >
+                "Edit"
+            </button>
+        }
+    });
+
+    let form = move || {
+        editing().then(|| view! { <ChannelProfileForm on_done=move || set_editing(false) /> })
+    };
+
+    view! {
+        <div class="relative flex items-baseline gap-2">
+            {move || chat.channel.with(|channel| {
+                let icon = channel.icon.clone().map(|icon| view! { <span>{icon}</span> });
+                let topic = channel.topic.clone().map(|topic| {
+                    view! {
+                        <p class="text-xs text-gray-500" title=channel.description.clone()>
+                            {topic}
+                        </p>
+                    }
+                });
+
+                view! {
+                    {icon}
+                    <p class="font-semibold">"#"{channel.name.clone()}</p>
+                    {topic}
+                }
+            })}
+            {edit_button}
+            {form}
+        </div>
+    }
+}
+
+/// Saves the whole profile, the blank fields are cleared
+#[component]
+fn ChannelProfileForm<F>(on_done: F) -> impl IntoView
+where
+    F: Fn() + Copy + 'static,
+{
+    let chat = expect_context::<ChatContext>();
+    let (topic, set_topic) = create_signal(
+        chat.channel
+            .with_untracked(|channel| channel.topic.clone().unwrap_or_default()),
+    );
+    let (description, set_description) = create_signal(
+        chat.channel
+            .with_untracked(|channel| channel.description.clone().unwrap_or_default()),
+    );
+    let (icon, set_icon) = create_signal(
+        chat.channel
+            .with_untracked(|channel| channel.icon.clone().unwrap_or_default()),
+    );
+
+    let save_action = create_action(move |_: &()| {
+        let (topic, description, icon) = (topic(), description(), icon());
+        async move {
+            let channel = set_channel_profile(chat.channel_id, topic, description, icon).await?;
+            chat.channel.set(channel);
+            on_done();
+            Ok::<_, ServerFnError>(())
+        }
+    });
+
+    let error_msg = move || match save_action.value().get() {
+        Some(Err(ServerFnError::ServerError(msg))) => {
+            Some(view! { <p class="text-xs text-red-500">{msg}</p> })
+        }
+        Some(Err(err)) => {
+            error!(description = ?err);
+            None
+        }
+        _ => None,
+    };
+
+    view! {
+        <form
+            on:submit=move |ev| {
+                ev.prevent_default();
+                save_action.dispatch(());
+            }
+            class="absolute top-8 left-0 z-10 w-96 p-3 space-y-2 bg-white border border-gray-200 rounded-md shadow-md text-sm"
+        >
+            <input
+                type="text"
+                placeholder="Icon (an emoji)"
+                prop:value=icon
+                on:input=move |ev| set_icon(event_target_value(&ev))
+                class="px-2 py-1 w-full border border-gray-400 rounded-md"
+            />
+            <input
+                type="text"
+                placeholder="Topic"
+                maxlength=MAX_CHANNEL_TOPIC_SIZE
+                prop:value=topic
+                on:input=move |ev| set_topic(event_target_value(&ev))
+                class="px-2 py-1 w-full border border-gray-400 rounded-md"
+            />
+            <textarea
+                placeholder="Description"
+                maxlength=MAX_CHANNEL_DESCRIPTION_SIZE
+                prop:value=description
+                on:input=move |ev| set_description(event_target_value(&ev))
+                class="px-2 py-1 w-full h-24 border border-gray-400 rounded-md resize-none"
+            />
+            {error_msg}
+            <input
+                type="submit"
+                value="Save"
+                class="px-3 py-1 rounded-md hover:cursor-pointer text-white bg-blue-500 hover:bg-blue-600"
+            />
+        </form>
+    }
+}
+
+#[component]
+fn RetentionNote() -> impl IntoView {
+    let chat = expect_context::<ChatContext>();
+
+    move || {
+        let retention = chat.retention.get();
+        let note = match retention.policy {
+            _ if retention.legal_hold => "Legal hold, nothing is deleted".to_owned(),
+            RetentionPolicy::Forever => return None,
+            RetentionPolicy::Days { days } => format!("Messages are kept for {days} days"),
+            RetentionPolicy::MessageTtl { secs } => {
+                format!("Messages disappear after {}", format_ttl(secs))
+            }
+        };
+
+        Some(view! { <p class="text-xs text-gray-400">{note}</p> })
+    }
+}
+
+fn format_ttl(secs: u32) -> String {
+    match secs {
+        secs if secs % 86400 == 0 => format!("{} days", secs / 86400),
+        secs if secs % 3600 == 0 => format!("{} hours", secs / 3600),
+        secs => format!("{} minutes", secs / 60),
+    }
+}
+
+/// The pin count, expands to the list of the pinned messages
+#[component]
+fn Pins() -> impl IntoView {
+    let chat = expect_context::<ChatContext>();
+    let (expanded, set_expanded) = create_signal(false);
+
+    let list = move || {
+        expanded().then(|| {
+            let items = chat.pins.with(|pins| {
+                pins.iter()
+                    .map(|pin| {
+                        let sender_name = chat.sender_name(pin.message.sender_id);
+
+                        view! {
+                            <div class="px-3 py-2 border-b border-gray-100 last:border-0">
+                                <span class="text-xs font-semibold">{sender_name}</span>
+                                <div
+                                    class=MESSAGE_CONTENT_CLASS
+                                    inner_html=pin.message.content_html.clone()
+                                />
+                            </div>
+                        }
+                    })
+                    .collect_view()
+            });
+
+            view! {
+                <div class="absolute top-8 left-0 z-10 w-96 max-h-96 overflow-y-auto bg-white border border-gray-200 rounded-md shadow-md text-sm">
+                    {items}
+                </div>
+            }
+        })
+    };
+
+    move || {
+        let count = chat.pins.with(Vec::len);
+
+        (count > 0).then(|| {
+            view! {
+                <div class="relative">
+                    <button
+                        type="button"
+                        on:click=move |_| set_expanded.update(|expanded| *expanded = !*expanded)
+                        class="px-2 border border-gray-300 rounded-full text-xs hover:bg-slate-100"
+                    >
+                        "📌 "{count}
+                    </button>
+                    {list}
+                </div>
+            }
+        })
+    }
+}
+
+#[component]
+fn MessageItem(message: Message, in_thread: bool) -> impl IntoView {
+    let chat = expect_context::<ChatContext>();
+
+    let sender_name = chat.sender_name(message.sender_id);
+    let created_at = message.created_at.format("%d.%m.%Y %H:%M").to_string();
+
+    // The changes of the channel, the content is plain text
+    if message.kind == MessageKind::System {
+        return view! {
+            <p class="text-center text-xs text-gray-400 italic">
+                {sender_name}" "{message.content}" · "{created_at}
+            </p>
+        }
+        .into_view();
+    }
+
+    let is_own = message.sender_id == chat.user_id;
+    let mentions_me = !is_own && chat.mentions_me(&message);
+
+    let sender_id = message.sender_id;
+    let presence_class = move || {
+        let presence = chat
+            .presence
+            .with(|presence| presence.get(&sender_id).copied().unwrap_or_default());
+
+        match presence {
+            Presence::Online => "inline-block w-2 h-2 rounded-full bg-green-500",
+            Presence::Away => "inline-block w-2 h-2 rounded-full bg-yellow-400",
+            Presence::Offline => "inline-block w-2 h-2 rounded-full bg-gray-300",
+        }
+    };
+
+    let content = match message.is_deleted() {
+        true => view! { <p class="text-gray-400 italic">{DELETED_MSG}</p> }.into_view(),
+        // Sanitized by the server, so the SSR and the hydrated output are the same
+        false => view! {
+            <div class=MESSAGE_CONTENT_CLASS inner_html=message.content_html.clone() />
+        }
+        .into_view(),
+    };
+
+    let edited = message
+        .edited_at
+        .filter(|_| !message.is_deleted())
+        .map(|_| view! { <span class="text-xs text-gray-400">"(edited)"</span> });
+
+    // Only the roots have threads
+    let replies = (!in_thread && message.parent_id.is_none()).then(|| {
+        let root_id = message.id;
+        let label = match message.reply_count {
+            0 => "Reply".to_owned(),
+            1 => "1 reply".to_owned(),
+            count => format!("{count} replies"),
+        };
+
+        view! {
+            <button
+                type="button"
+                on:click=move |_| chat.open_thread(root_id)
+                class="text-xs text-blue-500 hover:underline"
+            >
+                {label}
+            </button>
+        }
+    });
+
+    let pin = (!in_thread && !message.is_deleted() && chat.is_moderator()).then(|| {
+        let message_id = message.id;
+        let pinned = move || {
+            chat.pins
+                .with(|pins| pins.iter().any(|pin| pin.message.id == message_id))
+        };
+
+        view! {
+            <button
+                type="button"
+                on:click=move |_| chat.toggle_pin(message_id, pinned())
+                class="ml-auto text-xs text-gray-400 hover:text-gray-600"
+            >
+                {move || if pinned() { "Unpin" } else { "Pin" }}
+            </button>
+        }
+    });
+
+    view! {
+        <div
+            class="px-4 py-2 border border-gray-200 rounded-xl"
+            class=("bg-slate-50", is_own)
+            class=("bg-yellow-50", mentions_me)
+        >
+            <div class="flex items-baseline gap-2">
+                <span class=presence_class />
+                <span class="text-sm font-semibold">{sender_name}</span>
+                <span class="text-xs text-gray-400">{created_at}</span>
+                {edited}
+                {pin}
+            </div>
+            {content}
+            {(!message.attachments.is_empty()).then(|| view! {
+                <Attachments channel_id=message.channel_id attachments=message.attachments.clone() />
+            })}
+            {(!message.link_previews.is_empty()).then(|| view! {
+                <LinkPreviews previews=message.link_previews.clone() />
+            })}
+            {(!message.is_deleted()).then(|| view! { <Reactions message=message.clone() /> })}
+            {replies}
+        </div>
+    }
+    .into_view()
+}
+
+#[component]
+fn Reactions(message: Message) -> impl IntoView {
+    let chat = expect_context::<ChatContext>();
+    let message_id = message.id;
+
+    let reactions = message
+        .reactions
+        .iter()
+        .map(|reaction| {
+            let emoji = reaction.emoji.clone();
+            let reacted = reaction.reacted;
+
+            view! {
+                <button
+                    type="button"
+                    on:click=move |_| chat.toggle_reaction(message_id, emoji.clone(), reacted)
+                    class="px-2 border rounded-full text-xs"
+                    class=("border-blue-400", reacted)
+                    class=("bg-blue-50", reacted)
+                >
+                    {reaction.emoji.clone()}" "{reaction.count}
+                </button>
+            }
+        })
+        .collect_view();
+
+    // The emojis without the reactions yet
+    let quick_reactions = QUICK_REACTIONS
+        .into_iter()
+        .filter(|emoji| {
+            !message
+                .reactions
+                .iter()
+                .any(|reaction| reaction.emoji == *emoji)
+        })
+        .map(|emoji| {
+            view! {
+                <button
+                    type="button"
+                    on:click=move |_| chat.toggle_reaction(message_id, emoji.to_owned(), false)
+                    class="px-1 text-xs opacity-40 hover:opacity-100"
+                >
+                    {emoji}
+                </button>
+            }
+        })
+        .collect_view();
+
+    view! {
+        <div class="mt-1 flex flex-wrap gap-1">
+            {reactions}
+            {quick_reactions}
+        </div>
+    }
+}
+
+/// The channel composer restores and saves the draft, the thread one doesn't
+#[component]
+fn Composer(parent_id: Option<Uuid>, #[prop(optional)] draft: String) -> impl IntoView {
+    let chat = expect_context::<ChatContext>();
+    let (content, set_content) = create_signal(draft);
+
+    let draft_timeout = store_value(None::<TimeoutHandle>);
+    let cancel_draft_save = move || {
+        if let Some(handle) = draft_timeout.get_value() {
+            handle.clear();
+        }
+        draft_timeout.set_value(None);
+    };
+    let schedule_draft_save = move || {
+        if parent_id.is_some() {
+            return;
+        }
+
+        cancel_draft_save();
+        let handle = set_timeout_with_handle(
+            move || {
+                let content = content.get_untracked();
+                spawn_local(async move {
+                    if let Err(err) = save_draft(chat.channel_id, content).await {
+                        error!(description = ?err);
+                    }
+                });
+            },
+            Duration::from_millis(DRAFT_SAVE_DELAY_MS),
+        );
+        draft_timeout.set_value(handle.ok());
+    };
+
+    // The timer would outlive the composer and its content signal
+    on_cleanup(cancel_draft_save);
+
+    // Uploaded, but not sent yet
+    let pending = create_rw_signal(Vec::<Attachment>::new());
+    let uploading = create_rw_signal(0usize);
+    let (upload_error, set_upload_error) = create_signal(None::<String>);
+
+    let can_send = move || {
+        uploading() == 0
+            && (!content.with(|content| content.trim().is_empty())
+                || pending.with(|pending| !pending.is_empty()))
+    };
+
+    let send = move || {
+        if !can_send() {
+            return;
+        }
+
+        let value = content.get_untracked().trim().to_owned();
+        let attachment_ids = pending
+            .get_untracked()
+            .into_iter()
+            .map(|attachment| attachment.id)
+            .collect();
+
+        // The server clears the draft on send
+        cancel_draft_save();
+        set_content(String::new());
+        pending.set(Vec::new());
+        set_upload_error(None);
+        chat.typing_sent_at.set_value(None);
+        chat.send(OutboxMessage {
+            nonce: Uuid::new_v4(),
+            parent_id,
+            content: value,
+            attachment_ids,
+            error: None,
+        });
+    };
+
+    let on_files = move |ev: ev::Event| {
+        #[cfg(feature = "hydrate")]
+        {
+            let input: web_sys::HtmlInputElement = event_target(&ev);
+            let Some(files) = input.files() else {
+                return;
+            };
+
+            let files: Vec<_> = (0..files.length())
+                .filter_map(|idx| files.get(idx))
+                .collect();
+            input.set_value("");
+
+            for file in files {
+                if pending.with_untracked(Vec::len) + uploading.get_untracked()
+                    >= MAX_MESSAGE_ATTACHMENTS
+                {
+                    set_upload_error(Some(format!(
+                        "Not more than {MAX_MESSAGE_ATTACHMENTS} attachments per message"
+                    )));
+                    break;
+                }
+
+                uploading.update(|count| *count += 1);
+                spawn_local(async move {
+                    match attachment::upload(chat.channel_id, file).await {
+                        Ok(attachment) => pending.update(|pending| pending.push(attachment)),
+                        Err(err) => set_upload_error(Some(err)),
+                    }
+
+                    uploading.update(|count| *count -= 1);
+                });
+            }
+        }
+
+        #[cfg(not(feature = "hydrate"))]
+        let _ = ev;
+    };
+
+    let remove_pending = move |attachment_id: Uuid| {
+        pending.update(|pending| pending.retain(|attachment| attachment.id != attachment_id));
+    };
+
+    let pending_list = move || {
+        if pending.with(Vec::is_empty) && uploading() == 0 {
+            return None;
+        }
+
+        let items = pending
+            .get()
+            .into_iter()
+            .map(|attachment| {
+                let attachment_id = attachment.id;
+
+                view! {
+                    <li class="flex items-center gap-1 px-2 py-1 border border-gray-300 rounded-md">
+                        {attachment.name}
+                        <span class="text-xs text-gray-400">{format_size(attachment.size)}</span>
+                        <button
+                            type="button"
+                            on:click=move |_| remove_pending(attachment_id)
+                            class="ml-1 text-gray-400 hover:text-gray-600"
+                        >
+                            "✕"
+                        </button>
+                    </li>
+                }
+            })
+            .collect_view();
+
+        let uploading_msg = move || {
+            (uploading() > 0).then(|| {
+                view! { <li class="px-2 py-1 text-gray-400">"Uploading…"</li> }
+            })
+        };
+
+        Some(view! { <ul class="flex flex-wrap gap-2 mb-2 text-sm">{items}{uploading_msg}</ul> })
+    };
+
+    let error_msg = move || {
+        let msg = upload_error()?;
+
+        Some(view! {
+            <p class="p-1 mb-2 bg-red-400 rounded text-sm text-white break-words">{msg}</p>
+        })
+    };
+
+    let placeholder = match parent_id {
+        Some(_) => "Reply to the thread",
+        None => "Write a message",
+    };
+
+    let suggestions = create_resource(
+        move || content.with(|content| typed_mention(content).map(str::to_owned)),
+        move |prefix| async move {
+            let Some(prefix) = prefix else {
+                return Vec::new();
+            };
+
+            search_members(chat.channel_id, prefix)
+                .await
+                .unwrap_or_default()
+        },
+    );
+
+    let complete_mention = move |name: String| {
+        set_content.update(|content| {
+            if let Some(prefix) = typed_mention(content) {
+                let start = content.len() - prefix.len();
+                content.replace_range(start.., &format!("{name} "));
+            }
+        });
+    };
+
+    let suggestions_list = move || {
+        let members = suggestions.get().unwrap_or_default();
+        let has_mention = content.with(|content| typed_mention(content).is_some());
+        if members.is_empty() || !has_mention {
+            return None;
+        }
+
+        let items = members
+            .into_iter()
+            .map(|member| {
+                let name = member.name.clone();
+
+                view! {
+                    <li>
+                        <button
+                            type="button"
+                            on:click=move |_| complete_mention(name.clone())
+                            class="px-2 py-1 w-full text-left hover:bg-slate-100"
+                        >
+                            "@"{member.name}
+                        </button>
+                    </li>
+                }
+            })
+            .collect_view();
+
+        Some(view! {
+            <ul class="mb-2 border border-gray-200 rounded-md text-sm">{items}</ul>
+        })
+    };
+
+    view! {
+        <div class="px-5 py-3 border-t border-gray-200">
+            {error_msg}
+            {suggestions_list}
+            {pending_list}
+            <div class="flex gap-2">
+                <label class="
+                    flex items-center px-2 border border-gray-400 rounded-md
+                    text-gray-500 hover:bg-slate-100 hover:cursor-pointer
+                ">
+                    "📎"
+                    <input type="file" multiple=true class="hidden" on:change=on_files />
+                </label>
+                <textarea
+                    rows=2
+                    maxlength=MAX_MESSAGE_CONTENT_SIZE
+                    placeholder=placeholder
+                    prop:value=content
+                    on:input=move |ev| {
+                        set_content(event_target_value(&ev));
+                        chat.notify_typing();
+                        schedule_draft_save();
+                    }
+                    on:keydown=move |ev| {
+                        if ev.key() == "Enter" && !ev.shift_key() {
+                            ev.prevent_default();
+                            send();
+                        }
+                    }
+                    class="flex-auto px-2 py-1 border border-gray-400 rounded-md resize-none"
+                />
+                <button
+                    type="button"
+                    disabled=move || !can_send()
+                    on:click=move |_| send()
+                    class="
+                        px-4 rounded-md text-white
+                        bg-blue-500 hover:bg-blue-600 disabled:bg-blue-300
+                    "
+                >
+                    "Send"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+/// The unconfirmed messages of the channel or the thread, after the sent ones
+#[component]
+fn Outbox(parent_id: Option<Uuid>) -> impl IntoView {
+    let chat = expect_context::<ChatContext>();
+
+    let messages = move || {
+        chat.outbox.with(|outbox| {
+            outbox
+                .iter()
+                .filter(|message| message.parent_id == parent_id)
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+    };
+
+    view! {
+        <For
+            each=messages
+            key=|message| message.clone()
+            children=move |message| {
+                let nonce = message.nonce;
+                let status = match message.error {
+                    Some(err) => {
+                        view! {
+                            <p class="text-xs text-red-500">
+                                "Not sent: "{err}" "
+                                <button
+                                    type="button"
+                                    on:click=move |_| chat.deliver(nonce)
+                                    class="underline"
+                                >
+                                    "Retry"
+                                </button>
+                                " "
+                                <button
+                                    type="button"
+                                    on:click=move |_| chat.discard(nonce)
+                                    class="underline"
+                                >
+                                    "Discard"
+                                </button>
+                            </p>
+                        }
+                    }
+                    None => view! { <p class="text-xs text-gray-400">"Sending…"</p> },
+                };
+
+                view! {
+                    <div class="px-4 py-2 border border-dashed border-gray-300 rounded-xl">
+                        <p class="text-gray-500 whitespace-pre-wrap break-words">{message.content}</p>
+                        {status}
+                    </div>
+                }
+            }
+        />
+    }
+}
+
+#[component]
+fn ThreadPanel() -> impl IntoView {
+    let chat = expect_context::<ChatContext>();
+
+    let root = move || {
+        chat.thread
+            .with(|thread| thread.as_ref().map(|thread| thread.root.clone()))
+            .map(|root| view! { <MessageItem message=root in_thread=true /> })
+    };
+
+    let replies = move || {
+        chat.thread
+            .with(|thread| thread.as_ref().map(|thread| thread.replies.clone()))
+            .unwrap_or_default()
+    };
+
+    let root_id = chat
+        .thread
+        .with_untracked(|thread| thread.as_ref().map(|thread| thread.root.id));
+
+    view! {
+        <div class="w-96 h-full flex flex-col border-l border-gray-200">
+            <div class="px-5 py-3 flex justify-between border-b border-gray-200">
+                <p class="font-semibold">"Thread"</p>
+                <button
+                    type="button"
+                    on:click=move |_| chat.thread.set(None)
+                    class="text-sm text-gray-500 hover:text-gray-700"
+                >
+                    "Close"
+                </button>
+            </div>
+            <div class="flex-auto h-0 px-5 py-3 flex flex-col gap-3 overflow-y-auto">
+                {root}
+                <For
+                    each=replies
+                    key=|reply| reply.clone()
+                    children=|reply| view! { <MessageItem message=reply in_thread=true /> }
+                />
+                <Outbox parent_id=root_id />
+            </div>
+            <Composer parent_id=root_id />
+        </div>
+    }
+}
+
+#[component]
+fn ChatStub() -> impl IntoView {
+    view! {
+        <div class="flex items-center animate-pulse">
+            <div class="inline-block ml-4 mr-3 w-10 h-10 bg-slate-300 rounded-full" />
+            <div class="px-5 py-5 w-5/12 bg-white border border-gray-200 rounded-xl space-y-1">
+                <div class="h-3 bg-slate-300 rounded" />
+
+                <div class="h-3" />
+
+                <div class="grid grid-cols-3 gap-4">
+                    <div class="h-3 bg-slate-300 rounded col-span-2"></div>
+                    <div class="h-3 bg-slate-300 rounded col-span-1"></div>
+                </div>
+
+                <div class="h-3 bg-slate-300 rounded" />
+                <div class="h-3 bg-slate-300 rounded" />
+            </div>
+        </div>
+
+        <div class="flex items-center animate-pulse">
+            <div class="inline-block ml-4 mr-3 w-10 h-10 bg-slate-300 rounded-full" />
+            <div class="px-5 py-5 w-1/2 bg-white border border-gray-200 rounded-xl space-y-1">
+                <div class="w-2/3 h-3 bg-slate-300 rounded" />
+
+                <div class="h-3" />
+
+                <div class="h-3 bg-slate-300 rounded" />
+                <div class="h-3 bg-slate-300 rounded" />
+                <div class="h-3 bg-slate-300 rounded" />
+                <div class="w-1/3 h-3 bg-slate-300 rounded" />
+            </div>
+        </div>
+
+        <div class="flex items-center animate-pulse">
+            <div class="inline-block ml-4 mr-3 w-10 h-10 bg-slate-300 rounded-full" />
+            <div class="px-5 py-5 w-5/12 bg-white border border-gray-200 rounded-xl space-y-1">
+                <div class="h-3 bg-slate-300 rounded" />
+                <div class="h-3 bg-slate-300 rounded" />
+                <div class="h-3 bg-slate-300 rounded" />
+                <div class="w-2/3 h-3 bg-slate-300 rounded" />
+            </div>
+        </div>
+    }
+}
+
+#[server]
+async fn load_chat(channel_id: Uuid) -> Result<ChatDetails, ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::{self, message::MessagesRequest};
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    let details = channel::channel(state.clone(), user_id, channel_id)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))?;
+
+    let members = channel::members(state.clone(), user_id, channel_id)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))?;
+
+    let draft = channel::draft::draft(state.clone(), user_id, channel_id)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))?;
+
+    let mut messages = channel::message::messages(
+        state,
+        user_id,
+        channel_id,
+        MessagesRequest {
+            before: None,
+            limit: None,
+        },
+    )
+    .await
+    .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))?;
+
+    // The history is the newest first
+    messages.reverse();
+
+    Ok(ChatDetails {
+        channel: details.channel,
+        user_id,
+        members,
+        messages,
+        pins: details.pins,
+        retention: details.retention,
+        last_seq: details.last_seq,
+        draft: draft.map(|draft| draft.content),
+    })
+}
+
+#[server]
+async fn catch_up(channel_id: Uuid, after_seq: i64) -> Result<Vec<Message>, ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::message::{self, CatchUpRequest};
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    message::catch_up(
+        state,
+        user_id,
+        channel_id,
+        CatchUpRequest {
+            after_seq,
+            limit: None,
+        },
+    )
+    .await
+    .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
+
+#[server]
+async fn save_draft(channel_id: Uuid, content: String) -> Result<(), ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::draft::{self, SaveDraftPayload};
+    use validator::Validate;
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    let payload = SaveDraftPayload { content };
+    payload
+        .validate()
+        .map_err(|err| ServerFnError::ServerError(err.to_string()))?;
+
+    draft::save_draft(state, user_id, channel_id, payload)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
+
+#[server]
+async fn send_message(
+    channel_id: Uuid,
+    content: String,
+    parent_id: Option<Uuid>,
+    attachment_ids: Vec<Uuid>,
+    nonce: Option<Uuid>,
+) -> Result<Message, ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::message::{self, SendMessagePayload};
+    use validator::Validate;
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    let payload = SendMessagePayload {
+        content,
+        parent_id,
+        attachment_ids,
+        nonce,
+    };
+    payload
+        .validate()
+        .map_err(|err| ServerFnError::ServerError(err.to_string()))?;
+
+    message::send_message(state, user_id, channel_id, payload)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
+
+#[server]
+async fn load_thread(channel_id: Uuid, root_id: Uuid) -> Result<Thread, ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::message::{self, ThreadRequest};
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    message::thread(
+        state,
+        user_id,
+        channel_id,
+        root_id,
+        ThreadRequest {
+            after: None,
+            limit: None,
+        },
+    )
+    .await
+    .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
+
+#[server]
+async fn pin_message(channel_id: Uuid, message_id: Uuid) -> Result<(), ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::pin;
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    pin::pin_message(state, user_id, channel_id, message_id)
+        .await
+        .map(|_| ())
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
+
+#[server]
+async fn unpin_message(channel_id: Uuid, message_id: Uuid) -> Result<(), ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::pin;
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    pin::unpin_message(state, user_id, channel_id, message_id)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
+
+#[server]
+async fn add_reaction(
+    channel_id: Uuid,
+    message_id: Uuid,
+    emoji: String,
+) -> Result<(), ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::reaction;
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    reaction::add_reaction(state, user_id, channel_id, message_id, emoji)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
+
+#[server]
+async fn remove_reaction(
+    channel_id: Uuid,
+    message_id: Uuid,
+    emoji: String,
+) -> Result<(), ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::reaction;
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    reaction::remove_reaction(state, user_id, channel_id, message_id, emoji)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
+
+#[server]
+async fn search_members(
+    channel_id: Uuid,
+    prefix: String,
+) -> Result<Vec<ChannelMember>, ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::{self, SearchMembersRequest};
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    channel::search_members(state, user_id, channel_id, SearchMembersRequest { prefix })
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
+
+#[server]
+async fn mark_read(channel_id: Uuid, message_id: Uuid) -> Result<(), ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::read::{self, MarkReadPayload};
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    read::mark_read(state, user_id, channel_id, MarkReadPayload { message_id })
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
+
+#[server]
+async fn load_presence(user_ids: Vec<Uuid>) -> Result<HashMap<Uuid, Presence>, ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::presence::{self, PresencesPayload};
+
+    let (state, _) = crate::session::use_session().await?;
+
+    presence::presences(state, PresencesPayload { user_ids })
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
+
+#[server]
+async fn set_channel_profile(
+    channel_id: Uuid,
+    topic: String,
+    description: String,
+    icon: String,
+) -> Result<Channel, ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::profile::{self, SetProfilePayload};
+    use validator::Validate;
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    let payload = SetProfilePayload {
+        topic: Some(topic),
+        description: Some(description),
+        icon: Some(icon),
+    };
+    payload
+        .validate()
+        .map_err(|err| ServerFnError::ServerError(err.to_string()))?;
+
+    profile::set_profile(state, user_id, channel_id, payload)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
@@ -0,0 +1,163 @@
+use common::realtime::ClientEvent;
+use futures_channel::mpsc::UnboundedSender;
+
+use super::ChatContext;
+
+/// Subscribes to the channel events until the chat is closed, reconnecting after the
+/// connection is lost and catching up with the messages sent meanwhile.
+/// The returned sender queues the client events.
+#[cfg(feature = "hydrate")]
+pub fn connect(chat: ChatContext) -> Option<UnboundedSender<ClientEvent>> {
+    use std::{cell::Cell, rc::Rc, time::Duration};
+
+    use chrono::Utc;
+    use common::{realtime::ServerEvent, PRESENCE_HEARTBEAT_SECS, PRESENCE_IDLE_SECS};
+    use futures_util::{
+        future::{abortable, select, Either},
+        SinkExt, StreamExt,
+    };
+    use gloo_net::websocket::{futures::WebSocket, Message as WsMessage};
+    use leptos::*;
+    use tracing::error;
+
+    const RECONNECT_DELAY: Duration = Duration::from_secs(3);
+
+    let location = window().location();
+    let protocol = match location.protocol().as_deref() {
+        Ok("https:") => "wss",
+        _ => "ws",
+    };
+    let host = location.host().ok()?;
+    let url = format!("{protocol}://{host}/api/realtime");
+
+    let (events, mut queue) = futures_channel::mpsc::unbounded::<ClientEvent>();
+
+    let run = async move {
+        loop {
+            let socket = match WebSocket::open(&url) {
+                Ok(val) => val,
+                Err(err) => {
+                    error!(description = ?err);
+                    sleep(RECONNECT_DELAY).await;
+                    continue;
+                }
+            };
+            let (mut sender, mut receiver) = socket.split();
+
+            let subscribe = ClientEvent::Subscribe {
+                channel_id: chat.channel_id,
+            };
+            let text = serde_json::to_string(&subscribe).expect("ClientEvent serialization");
+            if sender.send(WsMessage::Text(text)).await.is_ok() {
+                // The events are delivered from the subscription on
+                chat.catch_up();
+
+                loop {
+                    match select(queue.next(), receiver.next()).await {
+                        Either::Left((Some(event), _)) => {
+                            let text =
+                                serde_json::to_string(&event).expect("ClientEvent serialization");
+                            if sender.send(WsMessage::Text(text)).await.is_err() {
+                                break;
+                            }
+                        }
+                        // The chat is closed
+                        Either::Left((None, _)) => return,
+                        Either::Right((Some(Ok(WsMessage::Text(text))), _)) => {
+                            match serde_json::from_str::<ServerEvent>(&text) {
+                                Ok(event) if event.channel_id == chat.channel_id => {
+                                    chat.apply_event(event.event)
+                                }
+                                Ok(_) => (),
+                                Err(err) => error!(description = ?err),
+                            }
+                        }
+                        Either::Right((Some(Ok(_)), _)) => (),
+                        Either::Right((Some(Err(_)) | None, _)) => break,
+                    }
+                }
+            }
+
+            sleep(RECONNECT_DELAY).await;
+        }
+    };
+
+    let (task, handle) = abortable(run);
+    spawn_local(async move {
+        // Aborted on cleanup
+        let _ = task.await;
+    });
+
+    // The user is away after `PRESENCE_IDLE_SECS` without any input, an open page doesn't count
+    let last_input = Rc::new(Cell::new(Utc::now()));
+    let away = Rc::new(Cell::new(false));
+
+    let on_input = {
+        let (last_input, away, events) = (last_input.clone(), away.clone(), events.clone());
+
+        move || {
+            last_input.set(Utc::now());
+
+            // Back at once, not on the next heartbeat
+            if away.replace(false) {
+                let _ = events.unbounded_send(ClientEvent::Heartbeat { away: false });
+            }
+        }
+    };
+    let input_listeners = [
+        window_event_listener(ev::keydown, {
+            let on_input = on_input.clone();
+            move |_| on_input()
+        }),
+        window_event_listener(ev::pointerdown, {
+            let on_input = on_input.clone();
+            move |_| on_input()
+        }),
+        window_event_listener(ev::pointermove, {
+            let on_input = on_input.clone();
+            move |_| on_input()
+        }),
+        window_event_listener(ev::wheel, move |_| on_input()),
+    ];
+
+    let heartbeats = events.clone();
+    let heartbeat = set_interval_with_handle(
+        move || {
+            let idle = Utc::now() - last_input.get();
+            away.set(idle >= chrono::Duration::seconds(PRESENCE_IDLE_SECS as i64));
+
+            let _ = heartbeats.unbounded_send(ClientEvent::Heartbeat { away: away.get() });
+        },
+        Duration::from_secs(PRESENCE_HEARTBEAT_SECS),
+    );
+
+    on_cleanup(move || {
+        handle.abort();
+        if let Ok(heartbeat) = heartbeat {
+            heartbeat.clear();
+        }
+        for listener in input_listeners {
+            listener.remove();
+        }
+    });
+
+    Some(events)
+}
+
+#[cfg(feature = "hydrate")]
+async fn sleep(duration: std::time::Duration) {
+    let (done, wait) = futures_channel::oneshot::channel();
+    leptos::set_timeout(
+        move || {
+            let _ = done.send(());
+        },
+        duration,
+    );
+    let _ = wait.await;
+}
+
+// The websocket is opened in the browser only
+#[cfg(not(feature = "hydrate"))]
+pub fn connect(_chat: ChatContext) -> Option<UnboundedSender<ClientEvent>> {
+    None
+}
@@ -0,0 +1,80 @@
+use common::{channel::ChannelSummary, MAX_UNREAD_COUNT};
+use leptos::*;
+use leptos_router::A;
+use uuid::Uuid;
+
+fn badge_count(count: u32) -> String {
+    match u64::from(count) < MAX_UNREAD_COUNT {
+        true => count.to_string(),
+        false => format!("{}+", MAX_UNREAD_COUNT - 1),
+    }
+}
+
+/// The channels of the user with the unread badges, reloaded on every `refresh` change
+#[component]
+pub fn Sidebar(current: Uuid, #[prop(into)] refresh: Signal<usize>) -> impl IntoView {
+    let channels = create_resource(refresh, |_| load_channels());
+
+    let items = move || {
+        channels
+            .get()
+            .and_then(Result::ok)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|summary| view! { <SidebarItem summary current=summary.channel.id == current /> })
+            .collect_view()
+    };
+
+    view! {
+        <nav class="w-56 h-full py-3 border-r border-gray-200 overflow-y-auto">
+            <ul class="space-y-1 text-sm">{items}</ul>
+        </nav>
+    }
+}
+
+#[component]
+fn SidebarItem(summary: ChannelSummary, current: bool) -> impl IntoView {
+    let mentions = (summary.mention_count > 0).then(|| {
+        view! {
+            <span class="px-1.5 rounded-full bg-red-500 text-xs text-white">
+                "@"{badge_count(summary.mention_count)}
+            </span>
+        }
+    });
+
+    // The open channel is being read
+    let unread = (!current && summary.mention_count == 0 && summary.unread_count > 0).then(|| {
+        view! {
+            <span class="px-1.5 rounded-full bg-gray-400 text-xs text-white">
+                {badge_count(summary.unread_count)}
+            </span>
+        }
+    });
+
+    view! {
+        <li>
+            <A
+                href=format!("/channels/{}", summary.channel.id)
+                class="px-4 py-1 flex justify-between items-center hover:bg-slate-100"
+            >
+                <span class=("font-semibold", current || summary.unread_count > 0)>
+                    "#"{summary.channel.name}
+                </span>
+                {mentions}
+                {unread}
+            </A>
+        </li>
+    }
+}
+
+#[server]
+async fn load_channels() -> Result<Vec<ChannelSummary>, ServerFnError> {
+    use api_error_derive::ApiErrorData;
+    use backend::channel::read;
+
+    let (state, user_id) = crate::session::use_session().await?;
+
+    read::channels(state, user_id)
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))
+}
@@ -1,14 +1,19 @@
 use leptos::*;
 use leptos_router::{Route, Routes};
 
-use crate::auth::{
-    authentication::Authentication, registration::Registration,
-    registration_details::RegistrationDetails,
+use crate::{
+    auth::{
+        authentication::Authentication, passkey::Passkeys, registration::Registration,
+        registration_details::RegistrationDetails, two_factor::TwoFactor,
+    },
+    chat::{invite::JoinInvite, Chat},
 };
 
 pub mod auth;
 pub mod chat;
 pub mod error_template;
+#[cfg(feature = "ssr")]
+mod session;
 mod validation;
 
 #[component]
@@ -20,6 +25,10 @@ pub fn Frontend() -> impl IntoView {
                 <Route path="authentication" view=Authentication />
                 <Route path="registration" view=Registration />
                 <Route path="registration_details" view=RegistrationDetails />
+                <Route path="two_factor" view=TwoFactor />
+                <Route path="passkeys" view=Passkeys />
+                <Route path="channels/:channel_id" view=Chat />
+                <Route path="invite/:token" view=JoinInvite />
             </Routes>
         </div>
     }
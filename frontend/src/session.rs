@@ -0,0 +1,20 @@
+use api_error_derive::ApiErrorData;
+use backend::{session::SessionContext, state::ServerState, INTERNAL_SERVER_ERROR_STR};
+use leptos::*;
+use leptos_axum::extract_with_state;
+use tracing::error;
+use uuid::Uuid;
+
+/// The server state and the user of the request, for the server functions behind the login
+pub async fn use_session() -> Result<(ServerState, Uuid), ServerFnError> {
+    let Some(state) = use_context::<ServerState>() else {
+        error!(description = "Failed to extract");
+        return Err(ServerFnError::ServerError(INTERNAL_SERVER_ERROR_STR.into()));
+    };
+
+    let session = extract_with_state(&state, |session: SessionContext| async move { session })
+        .await
+        .map_err(|err| ServerFnError::ServerError(ApiErrorData::from(err).client_description))?;
+
+    Ok((state, session.user_id))
+}
@@ -1,12 +1,62 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20240301_000002_user_totp;
+mod m20240305_000003_passkey;
+mod m20240310_000004_channel_member;
+mod m20240310_000005_message_revision;
+mod m20240312_000006_message_tombstone;
+mod m20240315_000007_message_thread;
+mod m20240317_000008_message_reaction;
+mod m20240319_000009_message_mention;
+mod m20240321_000010_channel_read_state;
+mod m20240323_000011_attachment;
+mod m20240325_000012_message_rendered_content;
+mod m20240327_000013_link_preview;
+mod m20240329_000014_message_search;
+mod m20240331_000015_pinned_message;
+mod m20240402_000016_scheduled_message;
+mod m20240404_000017_message_draft;
+mod m20240406_000018_channel_retention;
+mod m20240408_000019_message_nonce;
+mod m20240410_000020_message_seq;
+mod m20240412_000021_channel_invite;
+mod m20240414_000022_channel_profile;
+mod m20240416_000023_user_totp_step;
+mod m20240418_000024_message_purged_at;
+mod m20240420_000025_message_link;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20240301_000002_user_totp::Migration),
+            Box::new(m20240305_000003_passkey::Migration),
+            Box::new(m20240310_000004_channel_member::Migration),
+            Box::new(m20240310_000005_message_revision::Migration),
+            Box::new(m20240312_000006_message_tombstone::Migration),
+            Box::new(m20240315_000007_message_thread::Migration),
+            Box::new(m20240317_000008_message_reaction::Migration),
+            Box::new(m20240319_000009_message_mention::Migration),
+            Box::new(m20240321_000010_channel_read_state::Migration),
+            Box::new(m20240323_000011_attachment::Migration),
+            Box::new(m20240325_000012_message_rendered_content::Migration),
+            Box::new(m20240327_000013_link_preview::Migration),
+            Box::new(m20240329_000014_message_search::Migration),
+            Box::new(m20240331_000015_pinned_message::Migration),
+            Box::new(m20240402_000016_scheduled_message::Migration),
+            Box::new(m20240404_000017_message_draft::Migration),
+            Box::new(m20240406_000018_channel_retention::Migration),
+            Box::new(m20240408_000019_message_nonce::Migration),
+            Box::new(m20240410_000020_message_seq::Migration),
+            Box::new(m20240412_000021_channel_invite::Migration),
+            Box::new(m20240414_000022_channel_profile::Migration),
+            Box::new(m20240416_000023_user_totp_step::Migration),
+            Box::new(m20240418_000024_message_purged_at::Migration),
+            Box::new(m20240420_000025_message_link::Migration),
+        ]
     }
 }
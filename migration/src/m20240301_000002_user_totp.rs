@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+
+const FK_RECOVERY_CODE_USER: &str = "FK_RecoveryCode_User";
+const IDX_RECOVERY_CODE_USER_ID: &str = "IDX_RecoveryCode_UserId";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+    TotpSecret,
+    TotpEnabled,
+}
+
+#[derive(DeriveIden)]
+enum RecoveryCode {
+    Table,
+    Id,
+    UserId,
+    CodeHash,
+    UsedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(ColumnDef::new(User::TotpSecret).binary())
+                    .add_column(
+                        ColumnDef::new(User::TotpEnabled)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(RecoveryCode::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RecoveryCode::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(SimpleExpr::Custom("gen_random_uuid()".to_owned())),
+                    )
+                    .col(ColumnDef::new(RecoveryCode::UserId).uuid().not_null())
+                    // Hex encoded SHA-256
+                    .col(
+                        ColumnDef::new(RecoveryCode::CodeHash)
+                            .char_len(64)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(RecoveryCode::UsedAt).timestamp())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_RECOVERY_CODE_USER)
+                            .from(RecoveryCode::Table, RecoveryCode::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_RECOVERY_CODE_USER_ID)
+                    .table(RecoveryCode::Table)
+                    .col(RecoveryCode::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RecoveryCode::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::TotpEnabled)
+                    .drop_column(User::TotpSecret)
+                    .to_owned(),
+            )
+            .await
+    }
+}
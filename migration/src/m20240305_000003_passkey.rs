@@ -0,0 +1,91 @@
+use common::MAX_PASSKEY_NAME_SIZE;
+use sea_orm_migration::prelude::*;
+
+const FK_PASSKEY_USER: &str = "FK_Passkey_User";
+const IDX_PASSKEY_USER_ID: &str = "IDX_Passkey_UserId";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Passkey {
+    Table,
+    Id,
+    CreatedAt,
+    UserId,
+    CredentialId,
+    Name,
+    Passkey,
+    LastUsedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Passkey::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Passkey::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(SimpleExpr::Custom("gen_random_uuid()".to_owned())),
+                    )
+                    .col(
+                        ColumnDef::new(Passkey::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(Passkey::UserId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(Passkey::CredentialId)
+                            .binary()
+                            .unique_key()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Passkey::Name)
+                            .string_len(MAX_PASSKEY_NAME_SIZE.try_into().unwrap())
+                            .not_null(),
+                    )
+                    // The serialized credential with the public key and the signature counter
+                    .col(ColumnDef::new(Passkey::Passkey).json_binary().not_null())
+                    .col(ColumnDef::new(Passkey::LastUsedAt).timestamp())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_PASSKEY_USER)
+                            .from(Passkey::Table, Passkey::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_PASSKEY_USER_ID)
+                    .table(Passkey::Table)
+                    .col(Passkey::UserId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Passkey::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,143 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{EnumIter, Iterable},
+    sea_query::extension::postgres::Type,
+};
+
+const FK_CHANNEL_MEMBER_CHANNEL: &str = "FK_ChannelMember_Channel";
+const FK_CHANNEL_MEMBER_USER: &str = "FK_ChannelMember_User";
+const IDX_CHANNEL_MEMBER_USER_ID: &str = "IDX_ChannelMember_UserId";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+    IsPrivate,
+}
+
+#[derive(Iden, EnumIter)]
+enum ChannelRole {
+    Table,
+    Owner,
+    Admin,
+    Moderator,
+    Member,
+}
+
+#[derive(DeriveIden)]
+enum ChannelMember {
+    Table,
+    ChannelId,
+    UserId,
+    Role,
+    JoinedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(ChannelRole::Table)
+                    .values(ChannelRole::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ChannelMember::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ChannelMember::ChannelId).uuid().not_null())
+                    .col(ColumnDef::new(ChannelMember::UserId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(ChannelMember::Role)
+                            .enumeration(ChannelRole::Table, ChannelRole::iter().skip(1))
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelMember::JoinedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(ChannelMember::ChannelId)
+                            .col(ChannelMember::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_CHANNEL_MEMBER_CHANNEL)
+                            .from(ChannelMember::Table, ChannelMember::ChannelId)
+                            .to(Channel::Table, Channel::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_CHANNEL_MEMBER_USER)
+                            .from(ChannelMember::Table, ChannelMember::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The primary key covers the lookups by channel
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_CHANNEL_MEMBER_USER_ID)
+                    .table(ChannelMember::Table)
+                    .col(ChannelMember::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // The private channels aren't open for anyone to join
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Channel::Table)
+                    .add_column(
+                        ColumnDef::new(Channel::IsPrivate)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Channel::Table)
+                    .drop_column(Channel::IsPrivate)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ChannelMember::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(ChannelRole::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,121 @@
+use sea_orm_migration::prelude::*;
+
+const FK_MESSAGE_REVISION_MESSAGE: &str = "FK_MessageRevision_Message";
+const IDX_MESSAGE_REVISION_MESSAGE_ID: &str = "IDX_MessageRevision_MessageId";
+const IDX_MESSAGE_CHANNEL_ID_CREATED_AT: &str = "IDX_Message_ChannelId_CreatedAt";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+    ChannelId,
+    CreatedAt,
+    Content,
+    EditedAt,
+}
+
+#[derive(DeriveIden)]
+enum MessageRevision {
+    Table,
+    Id,
+    MessageId,
+    Content,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .modify_column(ColumnDef::new(Message::Content).text().not_null())
+                    .add_column(ColumnDef::new(Message::EditedAt).timestamp())
+                    .to_owned(),
+            )
+            .await?;
+
+        // The channel history is read page by page from the newest messages
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_MESSAGE_CHANNEL_ID_CREATED_AT)
+                    .table(Message::Table)
+                    .col(Message::ChannelId)
+                    .col(Message::CreatedAt)
+                    .col(Message::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(MessageRevision::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MessageRevision::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(SimpleExpr::Custom("gen_random_uuid()".to_owned())),
+                    )
+                    .col(ColumnDef::new(MessageRevision::MessageId).uuid().not_null())
+                    .col(ColumnDef::new(MessageRevision::Content).text().not_null())
+                    .col(
+                        ColumnDef::new(MessageRevision::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_MESSAGE_REVISION_MESSAGE)
+                            .from(MessageRevision::Table, MessageRevision::MessageId)
+                            .to(Message::Table, Message::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_MESSAGE_REVISION_MESSAGE_ID)
+                    .table(MessageRevision::Table)
+                    .col(MessageRevision::MessageId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MessageRevision::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(IDX_MESSAGE_CHANNEL_ID_CREATED_AT)
+                    .table(Message::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::EditedAt)
+                    .modify_column(ColumnDef::new(Message::Content).string().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
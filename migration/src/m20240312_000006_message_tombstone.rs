@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+
+const FK_MESSAGE_DELETED_BY: &str = "FK_Message_DeletedBy";
+const IDX_MESSAGE_DELETED_AT: &str = "IDX_Message_DeletedAt";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    DeletedAt,
+    DeletedBy,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(ColumnDef::new(Message::DeletedAt).timestamp())
+                    .add_column(ColumnDef::new(Message::DeletedBy).uuid())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name(FK_MESSAGE_DELETED_BY)
+                            .from_tbl(Message::Table)
+                            .from_col(Message::DeletedBy)
+                            .to_tbl(User::Table)
+                            .to_col(User::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Used by the purge of the tombstoned messages
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_MESSAGE_DELETED_AT)
+                    .table(Message::Table)
+                    .col(Message::DeletedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(IDX_MESSAGE_DELETED_AT)
+                    .table(Message::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_foreign_key(Alias::new(FK_MESSAGE_DELETED_BY))
+                    .drop_column(Message::DeletedBy)
+                    .drop_column(Message::DeletedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
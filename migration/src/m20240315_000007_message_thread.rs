@@ -0,0 +1,83 @@
+use sea_orm_migration::prelude::*;
+
+const FK_MESSAGE_PARENT: &str = "FK_Message_Parent";
+const IDX_MESSAGE_PARENT_ID_CREATED_AT: &str = "IDX_Message_ParentId_CreatedAt";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+    CreatedAt,
+    ParentId,
+    ReplyCount,
+    LastReplyAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(ColumnDef::new(Message::ParentId).uuid())
+                    // Denormalized, so the channel history doesn't count the replies
+                    .add_column(
+                        ColumnDef::new(Message::ReplyCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .add_column(ColumnDef::new(Message::LastReplyAt).timestamp())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name(FK_MESSAGE_PARENT)
+                            .from_tbl(Message::Table)
+                            .from_col(Message::ParentId)
+                            .to_tbl(Message::Table)
+                            .to_col(Message::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_MESSAGE_PARENT_ID_CREATED_AT)
+                    .table(Message::Table)
+                    .col(Message::ParentId)
+                    .col(Message::CreatedAt)
+                    .col(Message::Id)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(IDX_MESSAGE_PARENT_ID_CREATED_AT)
+                    .table(Message::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_foreign_key(Alias::new(FK_MESSAGE_PARENT))
+                    .drop_column(Message::LastReplyAt)
+                    .drop_column(Message::ReplyCount)
+                    .drop_column(Message::ParentId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,100 @@
+use common::MAX_REACTION_EMOJI_SIZE;
+use sea_orm_migration::prelude::*;
+
+const FK_MESSAGE_REACTION_MESSAGE: &str = "FK_MessageReaction_Message";
+const FK_MESSAGE_REACTION_USER: &str = "FK_MessageReaction_User";
+const IDX_MESSAGE_REACTION_MESSAGE_ID_USER_ID_EMOJI: &str =
+    "IDX_MessageReaction_MessageId_UserId_Emoji";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum MessageReaction {
+    Table,
+    Id,
+    MessageId,
+    UserId,
+    Emoji,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MessageReaction::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MessageReaction::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(SimpleExpr::Custom("gen_random_uuid()".to_owned())),
+                    )
+                    .col(ColumnDef::new(MessageReaction::MessageId).uuid().not_null())
+                    .col(ColumnDef::new(MessageReaction::UserId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(MessageReaction::Emoji)
+                            .string_len(MAX_REACTION_EMOJI_SIZE.try_into().unwrap())
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MessageReaction::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_MESSAGE_REACTION_MESSAGE)
+                            .from(MessageReaction::Table, MessageReaction::MessageId)
+                            .to(Message::Table, Message::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_MESSAGE_REACTION_USER)
+                            .from(MessageReaction::Table, MessageReaction::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A user reacts with the same emoji once, the index also serves the counts by message
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_MESSAGE_REACTION_MESSAGE_ID_USER_ID_EMOJI)
+                    .table(MessageReaction::Table)
+                    .col(MessageReaction::MessageId)
+                    .col(MessageReaction::UserId)
+                    .col(MessageReaction::Emoji)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MessageReaction::Table).to_owned())
+            .await
+    }
+}
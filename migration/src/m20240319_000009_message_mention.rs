@@ -0,0 +1,103 @@
+use sea_orm_migration::prelude::*;
+
+const FK_MESSAGE_MENTION_MESSAGE: &str = "FK_MessageMention_Message";
+const FK_MESSAGE_MENTION_USER: &str = "FK_MessageMention_User";
+const FK_MESSAGE_MENTION_CHANNEL: &str = "FK_MessageMention_Channel";
+const IDX_MESSAGE_MENTION_USER_ID_CREATED_AT: &str = "IDX_MessageMention_UserId_CreatedAt";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum MessageMention {
+    Table,
+    MessageId,
+    UserId,
+    ChannelId,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The channel and the creation time are copied from the message,
+        // so the feed and the counts don't join the message table
+        manager
+            .create_table(
+                Table::create()
+                    .table(MessageMention::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(MessageMention::MessageId).uuid().not_null())
+                    .col(ColumnDef::new(MessageMention::UserId).uuid().not_null())
+                    .col(ColumnDef::new(MessageMention::ChannelId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(MessageMention::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(MessageMention::MessageId)
+                            .col(MessageMention::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_MESSAGE_MENTION_MESSAGE)
+                            .from(MessageMention::Table, MessageMention::MessageId)
+                            .to(Message::Table, Message::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_MESSAGE_MENTION_USER)
+                            .from(MessageMention::Table, MessageMention::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_MESSAGE_MENTION_CHANNEL)
+                            .from(MessageMention::Table, MessageMention::ChannelId)
+                            .to(Channel::Table, Channel::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_MESSAGE_MENTION_USER_ID_CREATED_AT)
+                    .table(MessageMention::Table)
+                    .col(MessageMention::UserId)
+                    .col(MessageMention::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MessageMention::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,85 @@
+use sea_orm_migration::prelude::*;
+
+const FK_CHANNEL_READ_STATE_CHANNEL: &str = "FK_ChannelReadState_Channel";
+const FK_CHANNEL_READ_STATE_USER: &str = "FK_ChannelReadState_User";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ChannelReadState {
+    Table,
+    ChannelId,
+    UserId,
+    LastReadMessageId,
+    LastReadAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The last read message isn't a foreign key, the marker stays valid after the message
+        // is removed, the creation time of the message is compared with the newer ones
+        manager
+            .create_table(
+                Table::create()
+                    .table(ChannelReadState::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ChannelReadState::ChannelId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ChannelReadState::UserId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(ChannelReadState::LastReadMessageId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ChannelReadState::LastReadAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(ChannelReadState::ChannelId)
+                            .col(ChannelReadState::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_CHANNEL_READ_STATE_CHANNEL)
+                            .from(ChannelReadState::Table, ChannelReadState::ChannelId)
+                            .to(Channel::Table, Channel::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_CHANNEL_READ_STATE_USER)
+                            .from(ChannelReadState::Table, ChannelReadState::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ChannelReadState::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,123 @@
+use common::MAX_ATTACHMENT_NAME_SIZE;
+use sea_orm_migration::prelude::*;
+
+const FK_ATTACHMENT_CHANNEL: &str = "FK_Attachment_Channel";
+const FK_ATTACHMENT_UPLOADER: &str = "FK_Attachment_Uploader";
+const FK_ATTACHMENT_MESSAGE: &str = "FK_Attachment_Message";
+const IDX_ATTACHMENT_MESSAGE_ID: &str = "IDX_Attachment_MessageId";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Attachment {
+    Table,
+    Id,
+    ChannelId,
+    UploaderId,
+    MessageId,
+    Name,
+    ContentType,
+    Size,
+    HasThumbnail,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Attachment::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Attachment::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(SimpleExpr::Custom("gen_random_uuid()".to_owned())),
+                    )
+                    .col(ColumnDef::new(Attachment::ChannelId).uuid().not_null())
+                    .col(ColumnDef::new(Attachment::UploaderId).uuid().not_null())
+                    // Null until the message with the attachment is sent
+                    .col(ColumnDef::new(Attachment::MessageId).uuid())
+                    .col(
+                        ColumnDef::new(Attachment::Name)
+                            .string_len(MAX_ATTACHMENT_NAME_SIZE.try_into().unwrap())
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Attachment::ContentType).string().not_null())
+                    .col(ColumnDef::new(Attachment::Size).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(Attachment::HasThumbnail)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .col(
+                        ColumnDef::new(Attachment::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_ATTACHMENT_CHANNEL)
+                            .from(Attachment::Table, Attachment::ChannelId)
+                            .to(Channel::Table, Channel::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_ATTACHMENT_UPLOADER)
+                            .from(Attachment::Table, Attachment::UploaderId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_ATTACHMENT_MESSAGE)
+                            .from(Attachment::Table, Attachment::MessageId)
+                            .to(Message::Table, Message::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_ATTACHMENT_MESSAGE_ID)
+                    .table(Attachment::Table)
+                    .col(Attachment::MessageId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Attachment::Table).to_owned())
+            .await
+    }
+}
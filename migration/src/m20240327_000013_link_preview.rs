@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum LinkPreview {
+    Table,
+    Url,
+    Title,
+    Description,
+    ImageUrl,
+    SiteName,
+    FetchedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The failed fetches are cached too, without the metadata
+        manager
+            .create_table(
+                Table::create()
+                    .table(LinkPreview::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(LinkPreview::Url)
+                            .text()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(LinkPreview::Title).text())
+                    .col(ColumnDef::new(LinkPreview::Description).text())
+                    .col(ColumnDef::new(LinkPreview::ImageUrl).text())
+                    .col(ColumnDef::new(LinkPreview::SiteName).text())
+                    .col(
+                        ColumnDef::new(LinkPreview::FetchedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LinkPreview::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+const IDX_MESSAGE_SEARCH: &str = "IDX_Message_Search";
+
+/// The rendered plain text has no Markdown syntax, the older messages fall back to the source.
+/// The configuration must be the same as in the search query for the index to be used.
+const SEARCH_EXPRESSION: &str =
+    r#"GENERATED ALWAYS AS (to_tsvector('english', coalesce("content_text", "content"))) STORED"#;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Search,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(
+                        ColumnDef::new(Message::Search)
+                            .custom(Alias::new("tsvector"))
+                            .extra(SEARCH_EXPRESSION),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_MESSAGE_SEARCH)
+                    .table(Message::Table)
+                    .col(Message::Search)
+                    .full_text()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(IDX_MESSAGE_SEARCH)
+                    .table(Message::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::Search)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,104 @@
+use sea_orm_migration::prelude::*;
+
+const FK_PINNED_MESSAGE_CHANNEL: &str = "FK_PinnedMessage_Channel";
+const FK_PINNED_MESSAGE_MESSAGE: &str = "FK_PinnedMessage_Message";
+const FK_PINNED_MESSAGE_PINNED_BY: &str = "FK_PinnedMessage_PinnedBy";
+const IDX_PINNED_MESSAGE_CHANNEL_ID_PINNED_AT: &str = "IDX_PinnedMessage_ChannelId_PinnedAt";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum PinnedMessage {
+    Table,
+    MessageId,
+    ChannelId,
+    PinnedBy,
+    PinnedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A message is pinned once, in its own channel
+        manager
+            .create_table(
+                Table::create()
+                    .table(PinnedMessage::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(PinnedMessage::MessageId)
+                            .uuid()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(PinnedMessage::ChannelId).uuid().not_null())
+                    .col(ColumnDef::new(PinnedMessage::PinnedBy).uuid())
+                    .col(
+                        ColumnDef::new(PinnedMessage::PinnedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_PINNED_MESSAGE_CHANNEL)
+                            .from(PinnedMessage::Table, PinnedMessage::ChannelId)
+                            .to(Channel::Table, Channel::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_PINNED_MESSAGE_MESSAGE)
+                            .from(PinnedMessage::Table, PinnedMessage::MessageId)
+                            .to(Message::Table, Message::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_PINNED_MESSAGE_PINNED_BY)
+                            .from(PinnedMessage::Table, PinnedMessage::PinnedBy)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The pin list of the channel, the newest first
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_PINNED_MESSAGE_CHANNEL_ID_PINNED_AT)
+                    .table(PinnedMessage::Table)
+                    .col(PinnedMessage::ChannelId)
+                    .col(PinnedMessage::PinnedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(PinnedMessage::Table).to_owned())
+            .await
+    }
+}
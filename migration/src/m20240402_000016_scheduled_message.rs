@@ -0,0 +1,133 @@
+use sea_orm_migration::prelude::*;
+
+const FK_SCHEDULED_MESSAGE_CHANNEL: &str = "FK_ScheduledMessage_Channel";
+const FK_SCHEDULED_MESSAGE_SENDER: &str = "FK_ScheduledMessage_Sender";
+const FK_SCHEDULED_MESSAGE_PARENT: &str = "FK_ScheduledMessage_Parent";
+const IDX_SCHEDULED_MESSAGE_SEND_AT: &str = "IDX_ScheduledMessage_SendAt";
+const IDX_SCHEDULED_MESSAGE_SENDER_ID_CHANNEL_ID: &str = "IDX_ScheduledMessage_SenderId_ChannelId";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ScheduledMessage {
+    Table,
+    Id,
+    ChannelId,
+    SenderId,
+    ParentId,
+    Content,
+    SendAt,
+    ClaimedUntil,
+    CreatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ScheduledMessage::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ScheduledMessage::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(SimpleExpr::Custom("gen_random_uuid()".to_owned())),
+                    )
+                    .col(
+                        ColumnDef::new(ScheduledMessage::ChannelId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ScheduledMessage::SenderId).uuid().not_null())
+                    .col(ColumnDef::new(ScheduledMessage::ParentId).uuid())
+                    .col(ColumnDef::new(ScheduledMessage::Content).text().not_null())
+                    .col(
+                        ColumnDef::new(ScheduledMessage::SendAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    // Set while an instance delivers the message
+                    .col(ColumnDef::new(ScheduledMessage::ClaimedUntil).timestamp())
+                    .col(
+                        ColumnDef::new(ScheduledMessage::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_SCHEDULED_MESSAGE_CHANNEL)
+                            .from(ScheduledMessage::Table, ScheduledMessage::ChannelId)
+                            .to(Channel::Table, Channel::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_SCHEDULED_MESSAGE_SENDER)
+                            .from(ScheduledMessage::Table, ScheduledMessage::SenderId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_SCHEDULED_MESSAGE_PARENT)
+                            .from(ScheduledMessage::Table, ScheduledMessage::ParentId)
+                            .to(Message::Table, Message::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The due messages for the scheduler
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_SCHEDULED_MESSAGE_SEND_AT)
+                    .table(ScheduledMessage::Table)
+                    .col(ScheduledMessage::SendAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // The pending messages of the user in the channel
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_SCHEDULED_MESSAGE_SENDER_ID_CHANNEL_ID)
+                    .table(ScheduledMessage::Table)
+                    .col(ScheduledMessage::SenderId)
+                    .col(ScheduledMessage::ChannelId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ScheduledMessage::Table).to_owned())
+            .await
+    }
+}
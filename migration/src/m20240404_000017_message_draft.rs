@@ -0,0 +1,77 @@
+use sea_orm_migration::prelude::*;
+
+const FK_MESSAGE_DRAFT_CHANNEL: &str = "FK_MessageDraft_Channel";
+const FK_MESSAGE_DRAFT_USER: &str = "FK_MessageDraft_User";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum MessageDraft {
+    Table,
+    ChannelId,
+    UserId,
+    Content,
+    UpdatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One draft of the user per channel, the thread replies aren't kept
+        manager
+            .create_table(
+                Table::create()
+                    .table(MessageDraft::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(MessageDraft::ChannelId).uuid().not_null())
+                    .col(ColumnDef::new(MessageDraft::UserId).uuid().not_null())
+                    .col(ColumnDef::new(MessageDraft::Content).text().not_null())
+                    .col(
+                        ColumnDef::new(MessageDraft::UpdatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(MessageDraft::ChannelId)
+                            .col(MessageDraft::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_MESSAGE_DRAFT_CHANNEL)
+                            .from(MessageDraft::Table, MessageDraft::ChannelId)
+                            .to(Channel::Table, Channel::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_MESSAGE_DRAFT_USER)
+                            .from(MessageDraft::Table, MessageDraft::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MessageDraft::Table).to_owned())
+            .await
+    }
+}
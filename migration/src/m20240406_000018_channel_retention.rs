@@ -0,0 +1,94 @@
+use sea_orm_migration::prelude::*;
+
+const IDX_MESSAGE_EXPIRES_AT: &str = "IDX_Message_ExpiresAt";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    RetentionDays,
+    MessageTtlSecs,
+    LegalHold,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    ExpiresAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Without both the messages are kept forever
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Channel::Table)
+                    .add_column(ColumnDef::new(Channel::RetentionDays).integer())
+                    .add_column(ColumnDef::new(Channel::MessageTtlSecs).integer())
+                    .add_column(
+                        ColumnDef::new(Channel::LegalHold)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Set on send from the TTL of the channel, a later change of the TTL doesn't affect it
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(ColumnDef::new(Message::ExpiresAt).timestamp())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Used by the cleanup of the disappearing messages
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_MESSAGE_EXPIRES_AT)
+                    .table(Message::Table)
+                    .col(Message::ExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(IDX_MESSAGE_EXPIRES_AT)
+                    .table(Message::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::ExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Channel::Table)
+                    .drop_column(Channel::LegalHold)
+                    .drop_column(Channel::MessageTtlSecs)
+                    .drop_column(Channel::RetentionDays)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+const IDX_MESSAGE_SENDER_ID_NONCE: &str = "IDX_Message_SenderId_Nonce";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    SenderId,
+    Nonce,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Generated by the client, a retry of the send finds the message by it
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(ColumnDef::new(Message::Nonce).uuid())
+                    .to_owned(),
+            )
+            .await?;
+
+        // The messages without the nonce don't conflict, the nulls are distinct
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_MESSAGE_SENDER_ID_NONCE)
+                    .table(Message::Table)
+                    .col(Message::SenderId)
+                    .col(Message::Nonce)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(IDX_MESSAGE_SENDER_ID_NONCE)
+                    .table(Message::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::Nonce)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,220 @@
+use sea_orm_migration::prelude::*;
+
+const IDX_MESSAGE_CHANNEL_ID_SEQ: &str = "IDX_Message_ChannelId_Seq";
+
+/// The existing messages are numbered in the order they were shown
+const BACKFILL_MESSAGE_SEQ_SQL: &str = r#"
+UPDATE "message" SET "seq" = "numbered"."seq"
+FROM (
+    SELECT
+        "id",
+        row_number() OVER (PARTITION BY "channel_id" ORDER BY "created_at", "id") AS "seq"
+    FROM "message"
+) AS "numbered"
+WHERE "message"."id" = "numbered"."id"
+"#;
+
+const BACKFILL_CHANNEL_LAST_SEQ_SQL: &str = r#"
+UPDATE "channel" SET "last_seq" = coalesce(
+    (SELECT max("seq") FROM "message" WHERE "message"."channel_id" = "channel"."id"),
+    0
+)
+"#;
+
+/// The marker can point to a removed message, so the newest message up to it is found
+const BACKFILL_LAST_READ_SEQ_SQL: &str = r#"
+UPDATE "channel_read_state" SET "last_read_seq" = coalesce(
+    (
+        SELECT max("message"."seq") FROM "message"
+        WHERE "message"."channel_id" = "channel_read_state"."channel_id"
+            AND ("message"."created_at", "message"."id")
+                <= ("channel_read_state"."last_read_at", "channel_read_state"."last_read_message_id")
+    ),
+    0
+)
+"#;
+
+const RESTORE_LAST_READ_MESSAGE_SQL: &str = r#"
+UPDATE "channel_read_state"
+SET "last_read_message_id" = "message"."id", "last_read_at" = "message"."created_at"
+FROM "message"
+WHERE "message"."channel_id" = "channel_read_state"."channel_id"
+    AND "message"."seq" = "channel_read_state"."last_read_seq"
+"#;
+
+const DELETE_UNRESTORED_READ_STATE_SQL: &str = r#"
+DELETE FROM "channel_read_state" WHERE "last_read_message_id" IS NULL
+"#;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    LastSeq,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    ChannelId,
+    Seq,
+}
+
+#[derive(DeriveIden)]
+enum ChannelReadState {
+    Table,
+    LastReadMessageId,
+    LastReadAt,
+    LastReadSeq,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // The counter of the channel, incremented in the transaction inserting the message,
+        // so the numbers have no gaps and only the sends to the same channel wait for each other
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Channel::Table)
+                    .add_column(
+                        ColumnDef::new(Channel::LastSeq)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(ColumnDef::new(Message::Seq).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        db.execute_unprepared(BACKFILL_MESSAGE_SEQ_SQL).await?;
+        db.execute_unprepared(BACKFILL_CHANNEL_LAST_SEQ_SQL).await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .modify_column(ColumnDef::new(Message::Seq).big_integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // The history, the pagination and the catch-up after a reconnect
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_MESSAGE_CHANNEL_ID_SEQ)
+                    .table(Message::Table)
+                    .col(Message::ChannelId)
+                    .col(Message::Seq)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // The read marker is the number of the last read message
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChannelReadState::Table)
+                    .add_column(
+                        ColumnDef::new(ChannelReadState::LastReadSeq)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        db.execute_unprepared(BACKFILL_LAST_READ_SEQ_SQL).await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChannelReadState::Table)
+                    .drop_column(ChannelReadState::LastReadMessageId)
+                    .drop_column(ChannelReadState::LastReadAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChannelReadState::Table)
+                    .add_column(ColumnDef::new(ChannelReadState::LastReadMessageId).uuid())
+                    .add_column(ColumnDef::new(ChannelReadState::LastReadAt).timestamp())
+                    .to_owned(),
+            )
+            .await?;
+
+        // The markers of the removed messages are lost
+        db.execute_unprepared(RESTORE_LAST_READ_MESSAGE_SQL).await?;
+        db.execute_unprepared(DELETE_UNRESTORED_READ_STATE_SQL)
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ChannelReadState::Table)
+                    .modify_column(
+                        ColumnDef::new(ChannelReadState::LastReadMessageId)
+                            .uuid()
+                            .not_null(),
+                    )
+                    .modify_column(
+                        ColumnDef::new(ChannelReadState::LastReadAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .drop_column(ChannelReadState::LastReadSeq)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(IDX_MESSAGE_CHANNEL_ID_SEQ)
+                    .table(Message::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::Seq)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Channel::Table)
+                    .drop_column(Channel::LastSeq)
+                    .to_owned(),
+            )
+            .await
+    }
+}
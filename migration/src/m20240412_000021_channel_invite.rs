@@ -0,0 +1,161 @@
+use sea_orm_migration::prelude::*;
+
+const FK_CHANNEL_INVITE_CHANNEL: &str = "FK_ChannelInvite_Channel";
+const FK_CHANNEL_INVITE_CREATED_BY: &str = "FK_ChannelInvite_CreatedBy";
+const FK_CHANNEL_INVITE_USE_INVITE: &str = "FK_ChannelInviteUse_Invite";
+const FK_CHANNEL_INVITE_USE_USER: &str = "FK_ChannelInviteUse_User";
+const IDX_CHANNEL_INVITE_CHANNEL_ID_CREATED_AT: &str = "IDX_ChannelInvite_ChannelId_CreatedAt";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ChannelInvite {
+    Table,
+    Id,
+    ChannelId,
+    TokenHash,
+    CreatedBy,
+    CreatedAt,
+    ExpiresAt,
+    MaxUses,
+    UseCount,
+    RevokedAt,
+}
+
+#[derive(DeriveIden)]
+enum ChannelInviteUse {
+    Table,
+    InviteId,
+    UserId,
+    UsedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only the hash of the token is stored, like the recovery codes
+        manager
+            .create_table(
+                Table::create()
+                    .table(ChannelInvite::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ChannelInvite::Id)
+                            .uuid()
+                            .not_null()
+                            .primary_key()
+                            .default(SimpleExpr::Custom("gen_random_uuid()".to_owned())),
+                    )
+                    .col(ColumnDef::new(ChannelInvite::ChannelId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(ChannelInvite::TokenHash)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(ChannelInvite::CreatedBy).uuid())
+                    .col(
+                        ColumnDef::new(ChannelInvite::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(ChannelInvite::ExpiresAt).timestamp())
+                    .col(ColumnDef::new(ChannelInvite::MaxUses).integer())
+                    .col(
+                        ColumnDef::new(ChannelInvite::UseCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(ChannelInvite::RevokedAt).timestamp())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_CHANNEL_INVITE_CHANNEL)
+                            .from(ChannelInvite::Table, ChannelInvite::ChannelId)
+                            .to(Channel::Table, Channel::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_CHANNEL_INVITE_CREATED_BY)
+                            .from(ChannelInvite::Table, ChannelInvite::CreatedBy)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_CHANNEL_INVITE_CHANNEL_ID_CREATED_AT)
+                    .table(ChannelInvite::Table)
+                    .col(ChannelInvite::ChannelId)
+                    .col(ChannelInvite::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Who joined with the invite
+        manager
+            .create_table(
+                Table::create()
+                    .table(ChannelInviteUse::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(ChannelInviteUse::InviteId).uuid().not_null())
+                    .col(ColumnDef::new(ChannelInviteUse::UserId).uuid().not_null())
+                    .col(
+                        ColumnDef::new(ChannelInviteUse::UsedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(ChannelInviteUse::InviteId)
+                            .col(ChannelInviteUse::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_CHANNEL_INVITE_USE_INVITE)
+                            .from(ChannelInviteUse::Table, ChannelInviteUse::InviteId)
+                            .to(ChannelInvite::Table, ChannelInvite::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_CHANNEL_INVITE_USE_USER)
+                            .from(ChannelInviteUse::Table, ChannelInviteUse::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ChannelInviteUse::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ChannelInvite::Table).to_owned())
+            .await
+    }
+}
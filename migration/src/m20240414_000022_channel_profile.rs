@@ -0,0 +1,95 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{EnumIter, Iterable},
+    sea_query::extension::postgres::Type,
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Channel {
+    Table,
+    Topic,
+    Description,
+    Icon,
+}
+
+#[derive(Iden, EnumIter)]
+enum MessageKind {
+    Table,
+    User,
+    System,
+}
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Kind,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Channel::Table)
+                    .add_column(ColumnDef::new(Channel::Topic).string())
+                    .add_column(ColumnDef::new(Channel::Description).text())
+                    .add_column(ColumnDef::new(Channel::Icon).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(MessageKind::Table)
+                    .values(MessageKind::iter().skip(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        // The system messages are posted by the server on behalf of the sender
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(
+                        ColumnDef::new(Message::Kind)
+                            .enumeration(MessageKind::Table, MessageKind::iter().skip(1))
+                            .not_null()
+                            .default("user"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::Kind)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(MessageKind::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Channel::Table)
+                    .drop_column(Channel::Topic)
+                    .drop_column(Channel::Description)
+                    .drop_column(Channel::Icon)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+
+/// The purge used to leave the content empty
+const BACKFILL_PURGED_AT_SQL: &str = r#"
+UPDATE "message" SET "purged_at" = "deleted_at"
+WHERE "deleted_at" IS NOT NULL AND "content" = ''
+"#;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    PurgedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The purged tombstones aren't selected by the next purge
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .add_column(ColumnDef::new(Message::PurgedAt).timestamp())
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(BACKFILL_PURGED_AT_SQL).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Message::Table)
+                    .drop_column(Message::PurgedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
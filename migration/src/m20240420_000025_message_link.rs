@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+const FK_MESSAGE_LINK_MESSAGE: &str = "FK_MessageLink_Message";
+const IDX_MESSAGE_LINK_URL: &str = "IDX_MessageLink_Url";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Message {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum MessageLink {
+    Table,
+    MessageId,
+    Url,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The links of the messages, so the purge finds the unused previews by the url.
+        // The older messages have none, their previews are fetched again after the purge.
+        manager
+            .create_table(
+                Table::create()
+                    .table(MessageLink::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(MessageLink::MessageId).uuid().not_null())
+                    .col(ColumnDef::new(MessageLink::Url).text().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(MessageLink::MessageId)
+                            .col(MessageLink::Url),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name(FK_MESSAGE_LINK_MESSAGE)
+                            .from(MessageLink::Table, MessageLink::MessageId)
+                            .to(Message::Table, Message::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(IDX_MESSAGE_LINK_URL)
+                    .table(MessageLink::Table)
+                    .col(MessageLink::Url)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MessageLink::Table).to_owned())
+            .await
+    }
+}
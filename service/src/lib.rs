@@ -1,10 +1,11 @@
 use strum_macros::Display;
 
+pub mod markdown;
 pub mod mutation;
 pub mod query;
 
 #[derive(Debug, Display)]
 pub enum RegistrationType {
     Email,
-    Google,
+    OAuth,
 }
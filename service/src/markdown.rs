@@ -0,0 +1,178 @@
+use pulldown_cmark::{Event, Options, Parser, Tag};
+
+/// The links open outside of the app and don't pass the referrer
+const LINK_ATTRIBUTES: &str = r#" rel="noopener noreferrer nofollow" target="_blank""#;
+const SAFE_URL_SCHEMES: &[&str] = &["http:", "https:", "mailto:"];
+
+pub struct RenderedContent {
+    /// Only the supported subset, everything else is escaped
+    pub html: String,
+
+    /// Without the formatting, for the search and the notifications
+    pub text: String,
+}
+
+/// Renders bold, italics, code spans and blocks, links and quotes.
+/// The other blocks (headings and lists) are kept as plain paragraphs
+pub fn render(content: &str) -> RenderedContent {
+    let mut html = String::with_capacity(content.len() * 2);
+    let mut text = String::with_capacity(content.len());
+
+    // The next number of each nested list, `None` for the bullets
+    let mut lists: Vec<Option<u64>> = Vec::new();
+
+    for event in Parser::new_ext(content, Options::empty()) {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph | Tag::Heading(..) => html.push_str("<p>"),
+                Tag::BlockQuote => html.push_str("<blockquote>"),
+                Tag::CodeBlock(_) => html.push_str("<pre><code>"),
+                Tag::List(start) => lists.push(start),
+                Tag::Item => {
+                    let marker = match lists.last_mut() {
+                        Some(Some(number)) => {
+                            *number += 1;
+                            format!("{}. ", *number - 1)
+                        }
+                        _ => "• ".to_owned(),
+                    };
+
+                    html.push_str("<div>");
+                    html.push_str(&marker);
+                    text.push_str(&marker);
+                }
+                Tag::Emphasis => html.push_str("<em>"),
+                Tag::Strong => html.push_str("<strong>"),
+                Tag::Link(_, url, _) if is_safe_url(&url) => {
+                    html.push_str("<a href=\"");
+                    escape(&mut html, &url);
+                    html.push('"');
+                    html.push_str(LINK_ATTRIBUTES);
+                    html.push('>');
+                }
+                _ => (),
+            },
+            Event::End(tag) => {
+                match &tag {
+                    Tag::Paragraph | Tag::Heading(..) => html.push_str("</p>"),
+                    Tag::BlockQuote => html.push_str("</blockquote>"),
+                    Tag::CodeBlock(_) => html.push_str("</code></pre>"),
+                    Tag::List(_) => {
+                        lists.pop();
+                    }
+                    Tag::Item => html.push_str("</div>"),
+                    Tag::Emphasis => html.push_str("</em>"),
+                    Tag::Strong => html.push_str("</strong>"),
+                    Tag::Link(_, url, _) if is_safe_url(url) => html.push_str("</a>"),
+                    _ => (),
+                }
+
+                let is_block = matches!(
+                    tag,
+                    Tag::Paragraph | Tag::Heading(..) | Tag::CodeBlock(_) | Tag::Item
+                );
+                if is_block && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+            }
+            // The raw HTML is shown as typed
+            Event::Text(value) | Event::Html(value) => {
+                escape(&mut html, &value);
+                text.push_str(&value);
+            }
+            Event::Code(value) => {
+                html.push_str("<code>");
+                escape(&mut html, &value);
+                html.push_str("</code>");
+                text.push_str(&value);
+            }
+            // The chat messages keep the line breaks as typed
+            Event::SoftBreak | Event::HardBreak => {
+                html.push_str("<br>");
+                text.push('\n');
+            }
+            _ => (),
+        }
+    }
+
+    RenderedContent {
+        html,
+        text: text.trim_end().to_owned(),
+    }
+}
+
+fn is_safe_url(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+
+    SAFE_URL_SCHEMES
+        .iter()
+        .any(|scheme| url.starts_with(scheme))
+        && !url.chars().any(|ch| ch.is_whitespace() || ch.is_control())
+}
+
+pub(crate) fn escape(output: &mut String, value: &str) {
+    for ch in value.chars() {
+        match ch {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            ch => output.push(ch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let rendered = render("**bold** _it_ `a < b`\n> quote");
+        assert_eq!(
+            rendered.html,
+            "<p><strong>bold</strong> <em>it</em> <code>a &lt; b</code></p>\
+             <blockquote><p>quote</p></blockquote>"
+        );
+        assert_eq!(rendered.text, "bold it a < b\nquote");
+
+        let rendered = render("```\nfn main() {}\n```");
+        assert_eq!(rendered.html, "<pre><code>fn main() {}\n</code></pre>");
+        assert_eq!(rendered.text, "fn main() {}");
+
+        let rendered = render("- a\n- b");
+        assert_eq!(rendered.html, "<div>• a</div><div>• b</div>");
+        assert_eq!(rendered.text, "• a\n• b");
+
+        assert_eq!(render("line\nnext").html, "<p>line<br>next</p>");
+        assert_eq!(render("# title").html, "<p>title</p>");
+    }
+
+    #[test]
+    fn test_render_links() {
+        assert_eq!(
+            render("[site](https://example.com/?a=1&b=\"2\")").html,
+            format!(
+                "<p><a href=\"https://example.com/?a=1&amp;b=&quot;2&quot;\"{LINK_ATTRIBUTES}>site</a></p>"
+            )
+        );
+
+        // The unsafe links keep only the text
+        assert_eq!(render("[x](javascript:alert(1))").html, "<p>x</p>");
+        assert_eq!(render("[x](jav&#x61;script:alert(1))").html, "<p>x</p>");
+        assert_eq!(
+            render("![img](https://example.com/a.png)").html,
+            "<p>img</p>"
+        );
+    }
+
+    #[test]
+    fn test_render_escapes_html() {
+        let rendered = render("<script>alert(1)</script> <b onclick=\"x\">b</b>");
+        assert!(rendered.html.contains("&lt;script&gt;"));
+        assert!(!rendered.html.contains("<script"));
+        assert!(!rendered.html.contains("<b "));
+        assert!(rendered.text.contains("<script>"));
+    }
+}
@@ -1,7 +1,124 @@
-use ::entity::{channel, channel::Entity as Channel, message, user, user::Entity as User};
-use sea_orm::{prelude::Uuid, *};
+use ::entity::{
+    attachment,
+    attachment::Entity as Attachment,
+    channel,
+    channel::Entity as Channel,
+    channel_invite,
+    channel_invite::Entity as ChannelInvite,
+    channel_invite_use, channel_member,
+    channel_member::Entity as ChannelMember,
+    channel_read_state,
+    channel_read_state::Entity as ChannelReadState,
+    link_preview,
+    link_preview::Entity as LinkPreview,
+    message,
+    message::Entity as Message,
+    message_draft,
+    message_draft::Entity as MessageDraft,
+    message_link,
+    message_link::Entity as MessageLink,
+    message_mention,
+    message_mention::Entity as MessageMention,
+    message_reaction,
+    message_reaction::Entity as MessageReaction,
+    message_revision,
+    message_revision::Entity as MessageRevision,
+    passkey,
+    passkey::Entity as Passkey,
+    pinned_message,
+    pinned_message::Entity as PinnedMessage,
+    recovery_code,
+    recovery_code::Entity as RecoveryCode,
+    scheduled_message,
+    scheduled_message::Entity as ScheduledMessage,
+    sea_orm_active_enums::{ChannelRole, MessageKind},
+    user,
+    user::Entity as User,
+};
+use chrono::Utc;
+use common::parse_mentions;
+use sea_orm::{
+    prelude::{DateTime, Json, Uuid},
+    sea_query::{self, Expr, Func, LockBehavior, LockType, OnConflict, SelectStatement},
+    *,
+};
 use thiserror::Error;
 
+use crate::markdown;
+
+/// The thread roots are deleted after their replies, which are subtracted from the reply
+/// count unless already deleted, the last reply time is taken from the replies left. The CTEs
+/// share a snapshot, so the deleted replies are excluded by their ids. The messages with the
+/// files are deleted after the files, the cascade would leave the blobs behind. The roots with the pending scheduled replies wait for them too,
+/// the cascade would drop the replies unsent.
+const DELETE_EXPIRED_MESSAGES_SQL: &str = r#"
+WITH "deleted" AS (
+    DELETE FROM "message"
+    WHERE "message"."id" IN (
+        SELECT "expired"."id" FROM "message" AS "expired"
+        WHERE "expired"."id" IN (
+                SELECT "message"."id" FROM "message"
+                WHERE "message"."expires_at" < $1
+                UNION ALL
+                SELECT "message"."id" FROM "channel"
+                JOIN "message" ON "message"."channel_id" = "channel"."id"
+                WHERE "channel"."retention_days" IS NOT NULL
+                    AND "message"."created_at"
+                        < $1 - make_interval(days => "channel"."retention_days")
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM "channel"
+                WHERE "channel"."id" = "expired"."channel_id" AND "channel"."legal_hold"
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM "message" AS "reply" WHERE "reply"."parent_id" = "expired"."id"
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM "attachment" WHERE "attachment"."message_id" = "expired"."id"
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM "scheduled_message"
+                WHERE "scheduled_message"."parent_id" = "expired"."id"
+            )
+        LIMIT $2
+        FOR UPDATE SKIP LOCKED
+    )
+    RETURNING "message"."id", "message"."channel_id", "message"."parent_id", "message"."deleted_at"
+),
+"thread" AS (
+    UPDATE "message"
+    SET "reply_count" = greatest("message"."reply_count" - "replies"."count", 0),
+        "last_reply_at" = (
+            SELECT max("reply"."created_at") FROM "message" AS "reply"
+            WHERE "reply"."parent_id" = "message"."id"
+                AND "reply"."deleted_at" IS NULL
+                AND "reply"."id" NOT IN (SELECT "id" FROM "deleted")
+        )
+    FROM (
+        SELECT "parent_id", count(*) AS "count" FROM "deleted"
+        WHERE "parent_id" IS NOT NULL AND "deleted_at" IS NULL
+        GROUP BY "parent_id"
+    ) AS "replies"
+    WHERE "message"."id" = "replies"."parent_id"
+)
+SELECT "id", "channel_id" FROM "deleted"
+"#;
+
+/// The newest reply left in the thread of the updated message
+const LAST_REPLY_AT_SQL: &str = r#"(
+    SELECT max("reply"."created_at") FROM "message" AS "reply"
+    WHERE "reply"."parent_id" = "message"."id" AND "reply"."deleted_at" IS NULL
+)"#;
+
+/// The deleted messages don't show their previews
+const UNUSED_LINK_PREVIEW_SQL: &str = r#"
+NOT EXISTS (
+    SELECT 1 FROM "message_link"
+    INNER JOIN "message" ON "message"."id" = "message_link"."message_id"
+    WHERE "message_link"."url" = "link_preview"."url" AND "message"."deleted_at" IS NULL
+)
+"#;
+
 pub struct Mutation;
 
 pub struct CreateUserData {
@@ -11,9 +128,106 @@ pub struct CreateUserData {
 }
 
 pub struct CreateMessageData {
+    /// Set by the scheduler to the id of the scheduled message. It's deleted with the send,
+    /// so a retry can't send it twice.
+    pub id: Option<Uuid>,
+
+    /// Generated by the client, unique per sender
+    pub nonce: Option<Uuid>,
+    pub sender_id: Uuid,
+    pub channel_id: Uuid,
+    pub content: String,
+    pub parent_id: Option<Uuid>,
+
+    /// The pending uploads of the sender in the channel
+    pub attachment_ids: Vec<Uuid>,
+
+    /// The distinct links of the content, their previews are kept while linked
+    pub urls: Vec<String>,
+}
+
+/// Both unset keep the messages forever
+pub struct ChannelRetentionData {
+    /// The messages older than that are deleted
+    pub retention_days: Option<i32>,
+
+    /// The new messages disappear after that
+    pub message_ttl_secs: Option<i32>,
+}
+
+/// Replaces the whole profile, the unset fields are cleared
+pub struct ChannelProfileData {
+    pub topic: Option<String>,
+    pub description: Option<String>,
+
+    /// An emoji
+    pub icon: Option<String>,
+}
+
+pub struct CreateInviteData {
+    pub channel_id: Uuid,
+    pub created_by: Uuid,
+
+    /// The hash of the token, the token itself is only shown to the creator
+    pub token_hash: String,
+    pub expires_at: Option<DateTime>,
+    pub max_uses: Option<i32>,
+}
+
+#[derive(Clone, Debug, FromQueryResult, PartialEq)]
+pub struct ExpiredMessage {
+    pub id: Uuid,
+    pub channel_id: Uuid,
+}
+
+pub struct ScheduleMessageData {
     pub sender_id: Uuid,
     pub channel_id: Uuid,
     pub content: String,
+    pub parent_id: Option<Uuid>,
+    pub send_at: DateTime,
+}
+
+pub struct EditScheduledMessageData {
+    pub id: Uuid,
+    pub sender_id: Uuid,
+    pub channel_id: Uuid,
+    pub content: String,
+    pub send_at: DateTime,
+}
+
+pub struct CreateAttachmentData {
+    pub channel_id: Uuid,
+    pub uploader_id: Uuid,
+    pub name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub has_thumbnail: bool,
+}
+
+pub struct LinkPreviewData {
+    pub url: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub site_name: Option<String>,
+}
+
+pub struct EditMessageData {
+    pub message_id: Uuid,
+    pub channel_id: Uuid,
+    pub editor_id: Uuid,
+    pub content: String,
+
+    /// The distinct links of the new content
+    pub urls: Vec<String>,
+}
+
+pub struct CreatePasskeyData {
+    pub user_id: Uuid,
+    pub credential_id: Vec<u8>,
+    pub name: String,
+    pub passkey: Json,
 }
 
 #[derive(Debug, Error)]
@@ -24,6 +238,54 @@ pub enum CreateMessageError {
     UserNotFound,
     #[error("channel with this id not found")]
     ChannelNotFound,
+    #[error("root message with this id not found")]
+    ParentNotFound,
+    #[error("attachment with this id not found or already sent")]
+    AttachmentNotFound,
+    #[error("message with this nonce already sent")]
+    DuplicateNonce,
+    #[error("scheduled message with this id not found or already sent")]
+    ScheduledNotFound,
+}
+
+#[derive(Debug, Error)]
+pub enum EditMessageError {
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+    #[error("message with this id not found")]
+    MessageNotFound,
+    #[error("only the sender can edit the message")]
+    NotSender,
+}
+
+#[derive(Debug, Error)]
+pub enum ScheduleMessageError {
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+    #[error("root message with this id not found")]
+    ParentNotFound,
+    #[error("the user has the maximum number of scheduled messages")]
+    TooManyScheduled,
+}
+
+#[derive(Debug, Error)]
+pub enum PinMessageError {
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+    #[error("message with this id not found")]
+    MessageNotFound,
+    #[error("the message is already pinned")]
+    AlreadyPinned,
+    #[error("the channel has the maximum number of pins")]
+    TooManyPins,
+}
+
+#[derive(Debug, Error)]
+pub enum JoinByInviteError {
+    #[error("db error ({0})")]
+    Db(#[from] DbErr),
+    #[error("invite not found, revoked, expired or used up")]
+    InviteNotFound,
 }
 
 impl Mutation {
@@ -41,37 +303,1180 @@ impl Mutation {
         .await
     }
 
-    pub async fn create_channel(db: &DbConn, name: String) -> Result<channel::ActiveModel, DbErr> {
-        channel::ActiveModel {
-            name: Set(name),
+    /// Stores the new (not yet confirmed) TOTP secret, the previous one stays disabled
+    pub async fn set_user_totp_secret(
+        db: &DbConn,
+        user_id: Uuid,
+        totp_secret: Vec<u8>,
+    ) -> Result<user::Model, DbErr> {
+        user::ActiveModel {
+            id: Unchanged(user_id),
+            totp_secret: Set(Some(totp_secret)),
+            totp_enabled: Set(false),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+    }
+
+    /// Enables the confirmed TOTP secret and replaces the recovery codes, the confirmation code's
+    /// time step is stored so it can't be used to sign in
+    pub async fn enable_user_totp(
+        db: &DbConn,
+        user_id: Uuid,
+        totp_step: i64,
+        recovery_code_hashes: Vec<String>,
+    ) -> Result<(), DbErr> {
+        let txn = db.begin().await?;
+
+        user::ActiveModel {
+            id: Unchanged(user_id),
+            totp_enabled: Set(true),
+            totp_last_step: Set(Some(totp_step)),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+
+        RecoveryCode::delete_many()
+            .filter(recovery_code::Column::UserId.eq(user_id))
+            .exec(&txn)
+            .await?;
+
+        RecoveryCode::insert_many(recovery_code_hashes.into_iter().map(|code_hash| {
+            recovery_code::ActiveModel {
+                user_id: Set(user_id),
+                code_hash: Set(code_hash),
+                ..Default::default()
+            }
+        }))
+        .exec(&txn)
+        .await?;
+
+        txn.commit().await
+    }
+
+    /// Returns `false` if the code does not exist or was already used
+    pub async fn use_recovery_code(
+        db: &DbConn,
+        user_id: Uuid,
+        code_hash: &str,
+    ) -> Result<bool, DbErr> {
+        let result = RecoveryCode::update_many()
+            .col_expr(
+                recovery_code::Column::UsedAt,
+                Expr::current_timestamp().into(),
+            )
+            .filter(recovery_code::Column::UserId.eq(user_id))
+            .filter(recovery_code::Column::CodeHash.eq(code_hash))
+            .filter(recovery_code::Column::UsedAt.is_null())
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+
+    /// Returns `false` if a code of the same or a later time step was already accepted
+    pub async fn use_totp_step(db: &DbConn, user_id: Uuid, totp_step: i64) -> Result<bool, DbErr> {
+        let result = User::update_many()
+            .col_expr(user::Column::TotpLastStep, Expr::value(totp_step))
+            .filter(user::Column::Id.eq(user_id))
+            .filter(
+                Condition::any()
+                    .add(user::Column::TotpLastStep.is_null())
+                    .add(user::Column::TotpLastStep.lt(totp_step)),
+            )
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+
+    pub async fn create_passkey(
+        db: &DbConn,
+        passkey_data: CreatePasskeyData,
+    ) -> Result<passkey::ActiveModel, DbErr> {
+        passkey::ActiveModel {
+            user_id: Set(passkey_data.user_id),
+            credential_id: Set(passkey_data.credential_id),
+            name: Set(passkey_data.name),
+            passkey: Set(passkey_data.passkey),
             ..Default::default()
         }
         .save(db)
         .await
     }
 
+    /// Stores the updated signature counter after the successful authentication
+    pub async fn update_passkey_usage(
+        db: &DbConn,
+        passkey_id: Uuid,
+        passkey: Option<Json>,
+    ) -> Result<(), DbErr> {
+        let mut update = Passkey::update_many()
+            .col_expr(
+                passkey::Column::LastUsedAt,
+                Expr::current_timestamp().into(),
+            )
+            .filter(passkey::Column::Id.eq(passkey_id));
+
+        if let Some(passkey) = passkey {
+            update = update.col_expr(passkey::Column::Passkey, Expr::value(passkey));
+        }
+
+        update.exec(db).await?;
+        Ok(())
+    }
+
+    /// Creates the channel with the creator as its owner
+    pub async fn create_channel(
+        db: &DbConn,
+        name: String,
+        is_private: bool,
+        owner_id: Uuid,
+    ) -> Result<channel::Model, DbErr> {
+        let txn = db.begin().await?;
+
+        let channel = channel::ActiveModel {
+            name: Set(name),
+            is_private: Set(is_private),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        channel_member::ActiveModel {
+            channel_id: Set(channel.id),
+            user_id: Set(owner_id),
+            role: Set(ChannelRole::Owner),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok(channel)
+    }
+
+    /// Does nothing if the user is already a member
+    pub async fn join_channel(db: &DbConn, channel_id: Uuid, user_id: Uuid) -> Result<(), DbErr> {
+        ChannelMember::insert(channel_member::ActiveModel {
+            channel_id: Set(channel_id),
+            user_id: Set(user_id),
+            role: Set(ChannelRole::Member),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::columns([
+                channel_member::Column::ChannelId,
+                channel_member::Column::UserId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Posts the system message listing the changes on behalf of the editor, nothing is
+    /// posted without them. Returns `None` if there is no such channel
+    pub async fn update_channel_profile(
+        db: &DbConn,
+        channel_id: Uuid,
+        editor_id: Uuid,
+        profile: ChannelProfileData,
+    ) -> Result<Option<(channel::Model, Option<message::Model>)>, DbErr> {
+        let txn = db.begin().await?;
+
+        // Locked for the diff and the message number, like on send
+        let Some(channel) = Channel::find_by_id(channel_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let mut changes = Vec::new();
+        if channel.topic != profile.topic {
+            changes.push(match &profile.topic {
+                Some(topic) => format!("changed the topic to \"{topic}\""),
+                None => "cleared the topic".to_owned(),
+            });
+        }
+        if channel.description != profile.description {
+            changes.push(match &profile.description {
+                Some(_) => "changed the description".to_owned(),
+                None => "cleared the description".to_owned(),
+            });
+        }
+        if channel.icon != profile.icon {
+            changes.push(match &profile.icon {
+                Some(icon) => format!("changed the icon to {icon}"),
+                None => "removed the icon".to_owned(),
+            });
+        }
+
+        if changes.is_empty() {
+            return Ok(Some((channel, None)));
+        }
+
+        let channel = channel::ActiveModel {
+            id: Unchanged(channel.id),
+            topic: Set(profile.topic),
+            description: Set(profile.description),
+            icon: Set(profile.icon),
+            last_seq: Set(channel.last_seq + 1),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+        let expires_at = channel
+            .message_ttl_secs
+            .map(|secs| Utc::now().naive_utc() + chrono::Duration::seconds(secs.into()));
+
+        let content = changes.join(", ");
+        let rendered = markdown::render(&content);
+        let message = message::ActiveModel {
+            sender_id: Set(editor_id),
+            channel_id: Set(channel.id),
+            content: Set(content),
+            content_html: Set(Some(rendered.html)),
+            content_text: Set(Some(rendered.text)),
+            expires_at: Set(expires_at),
+            seq: Set(channel.last_seq),
+            kind: Set(MessageKind::System),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok(Some((channel, Some(message))))
+    }
+
+    pub async fn create_invite(
+        db: &DbConn,
+        invite_data: CreateInviteData,
+    ) -> Result<channel_invite::Model, DbErr> {
+        channel_invite::ActiveModel {
+            channel_id: Set(invite_data.channel_id),
+            token_hash: Set(invite_data.token_hash),
+            created_by: Set(Some(invite_data.created_by)),
+            expires_at: Set(invite_data.expires_at),
+            max_uses: Set(invite_data.max_uses),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// Returns `false` if the invite does not exist or was already revoked
+    pub async fn revoke_invite(
+        db: &DbConn,
+        channel_id: Uuid,
+        invite_id: Uuid,
+    ) -> Result<bool, DbErr> {
+        let result = ChannelInvite::update_many()
+            .col_expr(
+                channel_invite::Column::RevokedAt,
+                Expr::current_timestamp().into(),
+            )
+            .filter(channel_invite::Column::Id.eq(invite_id))
+            .filter(channel_invite::Column::ChannelId.eq(channel_id))
+            .filter(channel_invite::Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+
+    /// The invite is locked, so the concurrent joins don't go over `max_uses`. A member
+    /// gets the channel without using the invite up
+    pub async fn join_channel_by_invite(
+        db: &DbConn,
+        token_hash: &str,
+        user_id: Uuid,
+    ) -> Result<channel::Model, JoinByInviteError> {
+        let txn = db.begin().await?;
+
+        let invite = ChannelInvite::find()
+            .filter(channel_invite::Column::TokenHash.eq(token_hash))
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or(JoinByInviteError::InviteNotFound)?;
+
+        let now = Utc::now().naive_utc();
+        if invite.revoked_at.is_some() || invite.expires_at.is_some_and(|at| at <= now) {
+            return Err(JoinByInviteError::InviteNotFound);
+        }
+
+        let channel = Channel::find_by_id(invite.channel_id)
+            .one(&txn)
+            .await?
+            .ok_or(JoinByInviteError::InviteNotFound)?;
+
+        if ChannelMember::find_by_id((channel.id, user_id))
+            .one(&txn)
+            .await?
+            .is_some()
+        {
+            txn.commit().await?;
+            return Ok(channel);
+        }
+
+        if invite
+            .max_uses
+            .is_some_and(|max_uses| invite.use_count >= max_uses)
+        {
+            return Err(JoinByInviteError::InviteNotFound);
+        }
+
+        channel_member::ActiveModel {
+            channel_id: Set(channel.id),
+            user_id: Set(user_id),
+            role: Set(ChannelRole::Member),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        channel_invite_use::ActiveModel {
+            invite_id: Set(invite.id),
+            user_id: Set(user_id),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        ChannelInvite::update_many()
+            .col_expr(
+                channel_invite::Column::UseCount,
+                Expr::col(channel_invite::Column::UseCount).add(1),
+            )
+            .filter(channel_invite::Column::Id.eq(invite.id))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(channel)
+    }
+
     pub async fn create_message(
         db: &DbConn,
         message_data: CreateMessageData,
-    ) -> Result<message::ActiveModel, CreateMessageError> {
+    ) -> Result<message::Model, CreateMessageError> {
         User::find_by_id(message_data.sender_id)
             .one(db)
             .await?
             .ok_or(CreateMessageError::UserNotFound)?;
 
-        Channel::find_by_id(message_data.channel_id)
-            .one(db)
+        let rendered = markdown::render(&message_data.content);
+        let txn = db.begin().await?;
+
+        if let Some(parent_id) = message_data.parent_id {
+            // Only the root messages have threads
+            Message::find_by_id(parent_id)
+                .filter(message::Column::ChannelId.eq(message_data.channel_id))
+                .filter(message::Column::ParentId.is_null())
+                .filter(message::Column::DeletedAt.is_null())
+                .filter(message::Column::Kind.eq(MessageKind::User))
+                .one(&txn)
+                .await?
+                .ok_or(CreateMessageError::ParentNotFound)?;
+        }
+
+        // The channel row stays locked until the commit, so the sends to the channel commit
+        // in the order of their numbers and a rollback leaves no gap
+        let channel = Channel::update_many()
+            .col_expr(
+                channel::Column::LastSeq,
+                Expr::col(channel::Column::LastSeq).add(1),
+            )
+            .filter(channel::Column::Id.eq(message_data.channel_id))
+            .exec_with_returning(&txn)
             .await?
+            .pop()
             .ok_or(CreateMessageError::ChannelNotFound)?;
+        let expires_at = channel
+            .message_ttl_secs
+            .map(|secs| Utc::now().naive_utc() + chrono::Duration::seconds(secs.into()));
 
-        message::ActiveModel {
+        let message = message::ActiveModel {
+            id: match message_data.id {
+                Some(id) => Set(id),
+                None => NotSet,
+            },
             sender_id: Set(message_data.sender_id),
             channel_id: Set(message_data.channel_id),
             content: Set(message_data.content),
+            content_html: Set(Some(rendered.html)),
+            content_text: Set(Some(rendered.text)),
+            parent_id: Set(message_data.parent_id),
+            expires_at: Set(expires_at),
+            nonce: Set(message_data.nonce),
+            seq: Set(channel.last_seq),
+            kind: Set(MessageKind::User),
             ..Default::default()
         }
-        .save(db)
+        .insert(&txn)
+        .await
+        .map_err(|err| match err.sql_err() {
+            // A concurrent retry sent it first
+            Some(SqlErr::UniqueConstraintViolation(_)) if message_data.nonce.is_some() => {
+                CreateMessageError::DuplicateNonce
+            }
+            _ => err.into(),
+        })?;
+
+        if !message_data.attachment_ids.is_empty() {
+            // A repeated id is linked once
+            let mut attachment_ids = message_data.attachment_ids;
+            attachment_ids.sort_unstable();
+            attachment_ids.dedup();
+            let expected = attachment_ids.len() as u64;
+
+            let linked = Attachment::update_many()
+                .col_expr(attachment::Column::MessageId, Expr::value(message.id))
+                .filter(attachment::Column::Id.is_in(attachment_ids))
+                .filter(attachment::Column::ChannelId.eq(message.channel_id))
+                .filter(attachment::Column::UploaderId.eq(message.sender_id))
+                .filter(attachment::Column::MessageId.is_null())
+                .exec(&txn)
+                .await?
+                .rows_affected;
+
+            // Dropping the transaction rolls it back
+            if linked != expected {
+                return Err(CreateMessageError::AttachmentNotFound);
+            }
+        }
+
+        insert_mentions(&txn, &message).await?;
+        insert_links(&txn, message.id, message_data.urls).await?;
+
+        if let Some(id) = message_data.id {
+            let deleted = ScheduledMessage::delete_by_id(id).exec(&txn).await?;
+            if deleted.rows_affected == 0 {
+                return Err(CreateMessageError::ScheduledNotFound);
+            }
+        }
+
+        if let Some(parent_id) = message.parent_id {
+            Message::update_many()
+                .col_expr(
+                    message::Column::ReplyCount,
+                    Expr::col(message::Column::ReplyCount).add(1),
+                )
+                .col_expr(
+                    message::Column::LastReplyAt,
+                    Expr::value(message.created_at),
+                )
+                .filter(message::Column::Id.eq(parent_id))
+                .exec(&txn)
+                .await?;
+        }
+
+        txn.commit().await?;
+        Ok(message)
+    }
+
+    /// Replaces the content, the previous one is kept as a revision
+    pub async fn edit_message(
+        db: &DbConn,
+        message_data: EditMessageData,
+    ) -> Result<message::Model, EditMessageError> {
+        let txn = db.begin().await?;
+
+        // Locks the message, so the concurrent edits don't lose revisions
+        let message = Message::find_by_id(message_data.message_id)
+            .filter(message::Column::ChannelId.eq(message_data.channel_id))
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .filter(|message| message.deleted_at.is_none() && message.kind == MessageKind::User)
+            .ok_or(EditMessageError::MessageNotFound)?;
+
+        if message.sender_id != message_data.editor_id {
+            return Err(EditMessageError::NotSender);
+        }
+
+        if message.content == message_data.content {
+            return Ok(message);
+        }
+
+        message_revision::ActiveModel {
+            message_id: Set(message.id),
+            content: Set(message.content.clone()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let rendered = markdown::render(&message_data.content);
+        let message = message::ActiveModel {
+            id: Unchanged(message.id),
+            content: Set(message_data.content),
+            content_html: Set(Some(rendered.html)),
+            content_text: Set(Some(rendered.text)),
+            edited_at: Set(Some(Utc::now().naive_utc())),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+
+        MessageMention::delete_many()
+            .filter(message_mention::Column::MessageId.eq(message.id))
+            .exec(&txn)
+            .await?;
+        insert_mentions(&txn, &message).await?;
+
+        MessageLink::delete_many()
+            .filter(message_link::Column::MessageId.eq(message.id))
+            .exec(&txn)
+            .await?;
+        insert_links(&txn, message.id, message_data.urls).await?;
+
+        txn.commit().await?;
+        Ok(message)
+    }
+
+    /// Leaves the tombstone, the content is kept until the purge. The pin is removed.
+    /// Returns `None` if the message was already deleted.
+    pub async fn delete_message(
+        db: &DbConn,
+        message_id: Uuid,
+        deleted_by: Uuid,
+    ) -> Result<Option<message::Model>, DbErr> {
+        let txn = db.begin().await?;
+
+        // The concurrent deletions don't overwrite the first one
+        let result = Message::update_many()
+            .col_expr(
+                message::Column::DeletedAt,
+                Expr::value(Utc::now().naive_utc()),
+            )
+            .col_expr(message::Column::DeletedBy, Expr::value(deleted_by))
+            .filter(message::Column::Id.eq(message_id))
+            .filter(message::Column::DeletedAt.is_null())
+            .exec(&txn)
+            .await?;
+
+        if result.rows_affected == 0 {
+            return Ok(None);
+        }
+
+        let message = Message::find_by_id(message_id).one(&txn).await?;
+
+        // The deleted replies aren't counted, the last reply is the newest one left
+        if let Some(parent_id) = message.as_ref().and_then(|message| message.parent_id) {
+            Message::update_many()
+                .col_expr(
+                    message::Column::ReplyCount,
+                    Expr::cust(r#"greatest("message"."reply_count" - 1, 0)"#),
+                )
+                .col_expr(message::Column::LastReplyAt, Expr::cust(LAST_REPLY_AT_SQL))
+                .filter(message::Column::Id.eq(parent_id))
+                .exec(&txn)
+                .await?;
+        }
+
+        PinnedMessage::delete_by_id(message_id).exec(&txn).await?;
+
+        txn.commit().await?;
+        Ok(message)
+    }
+
+    /// The channel is locked, so the concurrent pins don't go over `max_pins`
+    pub async fn pin_message(
+        db: &DbConn,
+        channel_id: Uuid,
+        message_id: Uuid,
+        pinned_by: Uuid,
+        max_pins: u64,
+    ) -> Result<pinned_message::Model, PinMessageError> {
+        let txn = db.begin().await?;
+
+        Channel::find_by_id(channel_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or(PinMessageError::MessageNotFound)?;
+
+        Message::find_by_id(message_id)
+            .filter(message::Column::ChannelId.eq(channel_id))
+            .filter(message::Column::DeletedAt.is_null())
+            .filter(message::Column::Kind.eq(MessageKind::User))
+            .one(&txn)
+            .await?
+            .ok_or(PinMessageError::MessageNotFound)?;
+
+        if PinnedMessage::find_by_id(message_id)
+            .one(&txn)
+            .await?
+            .is_some()
+        {
+            return Err(PinMessageError::AlreadyPinned);
+        }
+
+        let pin_count = PinnedMessage::find()
+            .filter(pinned_message::Column::ChannelId.eq(channel_id))
+            .count(&txn)
+            .await?;
+        if pin_count >= max_pins {
+            return Err(PinMessageError::TooManyPins);
+        }
+
+        let pin = pinned_message::ActiveModel {
+            message_id: Set(message_id),
+            channel_id: Set(channel_id),
+            pinned_by: Set(Some(pinned_by)),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok(pin)
+    }
+
+    /// Returns `false` if the message wasn't pinned
+    pub async fn unpin_message(
+        db: &DbConn,
+        channel_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<bool, DbErr> {
+        let result = PinnedMessage::delete_many()
+            .filter(pinned_message::Column::MessageId.eq(message_id))
+            .filter(pinned_message::Column::ChannelId.eq(channel_id))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Moves the read marker of the user to the message, never back
+    pub async fn mark_channel_read(
+        db: &DbConn,
+        user_id: Uuid,
+        message: &message::Model,
+    ) -> Result<(), DbErr> {
+        ChannelReadState::insert(channel_read_state::ActiveModel {
+            channel_id: Set(message.channel_id),
+            user_id: Set(user_id),
+            last_read_seq: Set(message.seq),
+        })
+        .on_conflict(
+            OnConflict::columns([
+                channel_read_state::Column::ChannelId,
+                channel_read_state::Column::UserId,
+            ])
+            .update_column(channel_read_state::Column::LastReadSeq)
+            .action_and_where(Expr::cust(
+                r#""channel_read_state"."last_read_seq" < "excluded"."last_read_seq""#,
+            ))
+            .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// The empty draft is deleted
+    pub async fn save_draft(
+        db: &DbConn,
+        user_id: Uuid,
+        channel_id: Uuid,
+        content: String,
+    ) -> Result<(), DbErr> {
+        if content.trim().is_empty() {
+            return Self::delete_draft(db, user_id, channel_id).await;
+        }
+
+        MessageDraft::insert(message_draft::ActiveModel {
+            channel_id: Set(channel_id),
+            user_id: Set(user_id),
+            content: Set(content),
+            updated_at: Set(Utc::now().naive_utc()),
+        })
+        .on_conflict(
+            OnConflict::columns([
+                message_draft::Column::ChannelId,
+                message_draft::Column::UserId,
+            ])
+            .update_columns([
+                message_draft::Column::Content,
+                message_draft::Column::UpdatedAt,
+            ])
+            .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_draft(db: &DbConn, user_id: Uuid, channel_id: Uuid) -> Result<(), DbErr> {
+        MessageDraft::delete_by_id((channel_id, user_id))
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns `false` if the user already reacted with the emoji
+    pub async fn add_reaction(
+        db: &DbConn,
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: String,
+    ) -> Result<bool, DbErr> {
+        let result = MessageReaction::insert(message_reaction::ActiveModel {
+            message_id: Set(message_id),
+            user_id: Set(user_id),
+            emoji: Set(emoji),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::columns([
+                message_reaction::Column::MessageId,
+                message_reaction::Column::UserId,
+                message_reaction::Column::Emoji,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+
+        Ok(result > 0)
+    }
+
+    /// Returns `false` if there was no such reaction
+    pub async fn remove_reaction(
+        db: &DbConn,
+        message_id: Uuid,
+        user_id: Uuid,
+        emoji: &str,
+    ) -> Result<bool, DbErr> {
+        let result = MessageReaction::delete_many()
+            .filter(message_reaction::Column::MessageId.eq(message_id))
+            .filter(message_reaction::Column::UserId.eq(user_id))
+            .filter(message_reaction::Column::Emoji.eq(emoji))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Erases the content, the revisions, the mentions and the links of the messages deleted
+    /// before `deleted_before`, returns the erased contents. The messages with the files left
+    /// are skipped until the files are deleted, see `find_deleted_attachments`.
+    pub async fn purge_deleted_messages(
+        db: &DbConn,
+        deleted_before: DateTime,
+        batch_size: u64,
+    ) -> Result<Vec<String>, DbErr> {
+        let txn = db.begin().await?;
+
+        let messages: Vec<(Uuid, String)> = Message::find()
+            .select_only()
+            .column(message::Column::Id)
+            .column(message::Column::Content)
+            .filter(message::Column::DeletedAt.lt(deleted_before))
+            .filter(message::Column::PurgedAt.is_null())
+            .filter(message::Column::ChannelId.not_in_subquery(held_channels()))
+            .filter(
+                message::Column::Id.not_in_subquery(
+                    sea_query::Query::select()
+                        .column(attachment::Column::MessageId)
+                        .from(Attachment)
+                        .and_where(attachment::Column::MessageId.is_not_null())
+                        .to_owned(),
+                ),
+            )
+            .order_by_asc(message::Column::DeletedAt)
+            .limit(batch_size)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .into_tuple()
+            .all(&txn)
+            .await?;
+
+        let (message_ids, contents): (Vec<_>, Vec<_>) = messages.into_iter().unzip();
+
+        MessageRevision::delete_many()
+            .filter(message_revision::Column::MessageId.is_in(message_ids.clone()))
+            .exec(&txn)
+            .await?;
+
+        MessageMention::delete_many()
+            .filter(message_mention::Column::MessageId.is_in(message_ids.clone()))
+            .exec(&txn)
+            .await?;
+
+        MessageLink::delete_many()
+            .filter(message_link::Column::MessageId.is_in(message_ids.clone()))
+            .exec(&txn)
+            .await?;
+
+        Message::update_many()
+            .col_expr(message::Column::Content, Expr::value(""))
+            .col_expr(message::Column::ContentHtml, Expr::value(""))
+            .col_expr(message::Column::ContentText, Expr::value(""))
+            .col_expr(
+                message::Column::PurgedAt,
+                Expr::value(Utc::now().naive_utc()),
+            )
+            .filter(message::Column::Id.is_in(message_ids))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(contents)
+    }
+
+    /// Deletes the previews of the links no longer in any message
+    pub async fn delete_unused_link_previews(db: &DbConn, urls: Vec<String>) -> Result<u64, DbErr> {
+        if urls.is_empty() {
+            return Ok(0);
+        }
+
+        let result = LinkPreview::delete_many()
+            .filter(link_preview::Column::Url.is_in(urls))
+            .filter(Expr::cust(UNUSED_LINK_PREVIEW_SQL))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+
+    /// Returns `None` if there is no such channel
+    pub async fn set_channel_retention(
+        db: &DbConn,
+        channel_id: Uuid,
+        retention_data: ChannelRetentionData,
+    ) -> Result<Option<channel::Model>, DbErr> {
+        let channel = Channel::update_many()
+            .col_expr(
+                channel::Column::RetentionDays,
+                Expr::value(retention_data.retention_days),
+            )
+            .col_expr(
+                channel::Column::MessageTtlSecs,
+                Expr::value(retention_data.message_ttl_secs),
+            )
+            .filter(channel::Column::Id.eq(channel_id))
+            .exec_with_returning(db)
+            .await?;
+
+        Ok(channel.into_iter().next())
+    }
+
+    /// Nothing is deleted from the channel under the hold, the deletion resumes after it
+    pub async fn set_channel_legal_hold(
+        db: &DbConn,
+        channel_id: Uuid,
+        legal_hold: bool,
+    ) -> Result<Option<channel::Model>, DbErr> {
+        let channel = Channel::update_many()
+            .col_expr(channel::Column::LegalHold, Expr::value(legal_hold))
+            .filter(channel::Column::Id.eq(channel_id))
+            .exec_with_returning(db)
+            .await?;
+
+        Ok(channel.into_iter().next())
+    }
+
+    /// Deletes up to `batch_size` messages past the retention period of the channel or
+    /// their TTL, the locked ones are skipped until the next batch
+    pub async fn delete_expired_messages(
+        db: &DbConn,
+        now: DateTime,
+        batch_size: u64,
+    ) -> Result<Vec<ExpiredMessage>, DbErr> {
+        let batch_size = i64::try_from(batch_size).unwrap_or(i64::MAX);
+
+        ExpiredMessage::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            DELETE_EXPIRED_MESSAGES_SQL,
+            [now.into(), batch_size.into()],
+        ))
+        .all(db)
+        .await
+    }
+
+    /// The sender is locked, so the concurrent schedules don't go over `max_scheduled`
+    pub async fn schedule_message(
+        db: &DbConn,
+        schedule_data: ScheduleMessageData,
+        max_scheduled: u64,
+    ) -> Result<scheduled_message::Model, ScheduleMessageError> {
+        let txn = db.begin().await?;
+
+        User::find_by_id(schedule_data.sender_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or(DbErr::RecordNotFound("user".to_owned()))?;
+
+        let scheduled = ScheduledMessage::find()
+            .filter(scheduled_message::Column::SenderId.eq(schedule_data.sender_id))
+            .count(&txn)
+            .await?;
+        if scheduled >= max_scheduled {
+            return Err(ScheduleMessageError::TooManyScheduled);
+        }
+
+        // Checked again on the delivery by `create_message`, the root can be deleted in between
+        if let Some(parent_id) = schedule_data.parent_id {
+            Message::find_by_id(parent_id)
+                .filter(message::Column::ChannelId.eq(schedule_data.channel_id))
+                .filter(message::Column::ParentId.is_null())
+                .filter(message::Column::DeletedAt.is_null())
+                .filter(message::Column::Kind.eq(MessageKind::User))
+                .one(&txn)
+                .await?
+                .ok_or(ScheduleMessageError::ParentNotFound)?;
+        }
+
+        let scheduled = scheduled_message::ActiveModel {
+            sender_id: Set(schedule_data.sender_id),
+            channel_id: Set(schedule_data.channel_id),
+            content: Set(schedule_data.content),
+            parent_id: Set(schedule_data.parent_id),
+            send_at: Set(schedule_data.send_at),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+        Ok(scheduled)
+    }
+
+    /// Returns `None` if there is no such message or it's being delivered
+    pub async fn edit_scheduled_message(
+        db: &DbConn,
+        edit_data: EditScheduledMessageData,
+    ) -> Result<Option<scheduled_message::Model>, DbErr> {
+        let scheduled = ScheduledMessage::update_many()
+            .col_expr(
+                scheduled_message::Column::Content,
+                Expr::value(edit_data.content),
+            )
+            .col_expr(
+                scheduled_message::Column::SendAt,
+                Expr::value(edit_data.send_at),
+            )
+            .filter(scheduled_message::Column::Id.eq(edit_data.id))
+            .filter(scheduled_message::Column::SenderId.eq(edit_data.sender_id))
+            .filter(scheduled_message::Column::ChannelId.eq(edit_data.channel_id))
+            .filter(unclaimed_scheduled_message())
+            .exec_with_returning(db)
+            .await?;
+
+        Ok(scheduled.into_iter().next())
+    }
+
+    /// Returns `false` if there is no such message or it's being delivered
+    pub async fn cancel_scheduled_message(
+        db: &DbConn,
+        scheduled_id: Uuid,
+        sender_id: Uuid,
+        channel_id: Uuid,
+    ) -> Result<bool, DbErr> {
+        let result = ScheduledMessage::delete_many()
+            .filter(scheduled_message::Column::Id.eq(scheduled_id))
+            .filter(scheduled_message::Column::SenderId.eq(sender_id))
+            .filter(scheduled_message::Column::ChannelId.eq(channel_id))
+            .filter(unclaimed_scheduled_message())
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected > 0)
+    }
+
+    /// The due messages for the delivery until `claimed_until`, the other instances skip them.
+    /// If the delivery fails, they are claimed again after that.
+    pub async fn claim_due_scheduled_messages(
+        db: &DbConn,
+        claimed_until: DateTime,
+        batch_size: u64,
+    ) -> Result<Vec<scheduled_message::Model>, DbErr> {
+        let txn = db.begin().await?;
+
+        let scheduled_ids: Vec<Uuid> = ScheduledMessage::find()
+            .select_only()
+            .column(scheduled_message::Column::Id)
+            .filter(scheduled_message::Column::SendAt.lte(Utc::now().naive_utc()))
+            .filter(unclaimed_scheduled_message())
+            .order_by_asc(scheduled_message::Column::SendAt)
+            .limit(batch_size)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
+            .into_tuple()
+            .all(&txn)
+            .await?;
+
+        let scheduled = ScheduledMessage::update_many()
+            .col_expr(
+                scheduled_message::Column::ClaimedUntil,
+                Expr::value(claimed_until),
+            )
+            .filter(scheduled_message::Column::Id.is_in(scheduled_ids))
+            .exec_with_returning(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(scheduled)
+    }
+
+    /// After the delivery
+    pub async fn delete_scheduled_message(db: &DbConn, scheduled_id: Uuid) -> Result<(), DbErr> {
+        ScheduledMessage::delete_by_id(scheduled_id)
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// A pending upload until `create_message` links it to the message
+    pub async fn create_attachment(
+        db: &DbConn,
+        attachment_data: CreateAttachmentData,
+    ) -> Result<attachment::Model, DbErr> {
+        attachment::ActiveModel {
+            channel_id: Set(attachment_data.channel_id),
+            uploader_id: Set(attachment_data.uploader_id),
+            name: Set(attachment_data.name),
+            content_type: Set(attachment_data.content_type),
+            size: Set(attachment_data.size),
+            has_thumbnail: Set(attachment_data.has_thumbnail),
+            ..Default::default()
+        }
+        .insert(db)
         .await
-        .map_err(CreateMessageError::from)
     }
+
+    pub async fn delete_attachments(db: &DbConn, attachment_ids: Vec<Uuid>) -> Result<u64, DbErr> {
+        let result = Attachment::delete_many()
+            .filter(attachment::Column::Id.is_in(attachment_ids))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+
+    /// Replaces the cached metadata of the URL
+    pub async fn save_link_preview(
+        db: &DbConn,
+        preview_data: LinkPreviewData,
+    ) -> Result<link_preview::Model, DbErr> {
+        let preview = link_preview::Model {
+            url: preview_data.url,
+            title: preview_data.title,
+            description: preview_data.description,
+            image_url: preview_data.image_url,
+            site_name: preview_data.site_name,
+            fetched_at: Utc::now().naive_utc(),
+        };
+
+        LinkPreview::insert(link_preview::ActiveModel::from(preview.clone()))
+            .on_conflict(
+                OnConflict::column(link_preview::Column::Url)
+                    .update_columns([
+                        link_preview::Column::Title,
+                        link_preview::Column::Description,
+                        link_preview::Column::ImageUrl,
+                        link_preview::Column::SiteName,
+                        link_preview::Column::FetchedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await?;
+
+        Ok(preview)
+    }
+}
+
+/// Only the members of the channel can be mentioned, the names are case-insensitive like in
+/// the autocomplete
+async fn insert_mentions(txn: &DatabaseTransaction, message: &message::Model) -> Result<(), DbErr> {
+    let names: Vec<String> = parse_mentions(&message.content)
+        .into_iter()
+        .map(str::to_lowercase)
+        .collect();
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let mentioned: Vec<Uuid> = User::find()
+        .select_only()
+        .column(user::Column::Id)
+        .inner_join(ChannelMember)
+        .filter(channel_member::Column::ChannelId.eq(message.channel_id))
+        .filter(Expr::expr(Func::lower(Expr::col((User, user::Column::Name)))).is_in(names))
+        .filter(user::Column::Id.ne(message.sender_id))
+        .into_tuple()
+        .all(txn)
+        .await?;
+
+    if !mentioned.is_empty() {
+        MessageMention::insert_many(mentioned.into_iter().map(|user_id| {
+            message_mention::ActiveModel {
+                message_id: Set(message.id),
+                user_id: Set(user_id),
+                channel_id: Set(message.channel_id),
+                created_at: Set(message.created_at),
+            }
+        }))
+        .exec_without_returning(txn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn insert_links(
+    txn: &DatabaseTransaction,
+    message_id: Uuid,
+    urls: Vec<String>,
+) -> Result<(), DbErr> {
+    if urls.is_empty() {
+        return Ok(());
+    }
+
+    MessageLink::insert_many(urls.into_iter().map(|url| message_link::ActiveModel {
+        message_id: Set(message_id),
+        url: Set(url),
+    }))
+    .on_conflict(
+        OnConflict::columns([message_link::Column::MessageId, message_link::Column::Url])
+            .do_nothing()
+            .to_owned(),
+    )
+    .exec_without_returning(txn)
+    .await?;
+
+    Ok(())
+}
+
+/// The channels under a legal hold, nothing is deleted from them
+pub(crate) fn held_channels() -> SelectStatement {
+    sea_query::Query::select()
+        .column(channel::Column::Id)
+        .from(Channel)
+        .and_where(channel::Column::LegalHold.eq(true))
+        .to_owned()
+}
+
+/// Not being delivered, or the delivery was abandoned
+fn unclaimed_scheduled_message() -> Condition {
+    Condition::any()
+        .add(scheduled_message::Column::ClaimedUntil.is_null())
+        .add(scheduled_message::Column::ClaimedUntil.lt(Utc::now().naive_utc()))
 }
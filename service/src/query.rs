@@ -1,8 +1,177 @@
-use ::entity::{user, user::Entity as User};
-use sea_orm::{prelude::Uuid, *};
+use std::collections::HashMap;
+
+use ::entity::{
+    attachment, attachment::Entity as Attachment, channel, channel::Entity as Channel,
+    channel_invite, channel_invite::Entity as ChannelInvite, channel_invite_use,
+    channel_invite_use::Entity as ChannelInviteUse, channel_member,
+    channel_member::Entity as ChannelMember, link_preview, link_preview::Entity as LinkPreview,
+    message, message::Entity as Message, message_draft, message_draft::Entity as MessageDraft,
+    message_mention, message_mention::Entity as MessageMention, message_reaction,
+    message_reaction::Entity as MessageReaction, message_revision,
+    message_revision::Entity as MessageRevision, passkey, passkey::Entity as Passkey,
+    pinned_message, pinned_message::Entity as PinnedMessage, scheduled_message,
+    scheduled_message::Entity as ScheduledMessage, user, user::Entity as User,
+};
+use sea_orm::{
+    prelude::{DateTime, Uuid},
+    sea_query::{Expr, Func, LikeExpr},
+    *,
+};
+
+use crate::{markdown, mutation::held_channels};
 
 pub struct Query;
 
+/// A channel of the user with the counts for the sidebar badges
+#[derive(Clone, Debug, FromQueryResult, PartialEq)]
+pub struct ChannelUnread {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime,
+    pub is_private: bool,
+    pub topic: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+
+    /// Not more than the limit passed to the query
+    pub unread_count: i64,
+    pub mention_count: i64,
+}
+
+/// Only the messages after the read marker are scanned (by the channel and the creation time
+/// index) and the scan stops at the limit, so a long unread history costs the same as a short one
+const CHANNEL_UNREADS_SQL: &str = r#"
+SELECT
+    "channel"."id",
+    "channel"."name",
+    "channel"."created_at",
+    "channel"."is_private",
+    "channel"."topic",
+    "channel"."description",
+    "channel"."icon",
+    (
+        SELECT count(*) FROM (
+            SELECT 1 FROM "message"
+            WHERE "message"."channel_id" = "channel"."id"
+                AND "message"."sender_id" <> $1
+                AND "message"."deleted_at" IS NULL
+                AND "message"."seq" > coalesce("read_state"."last_read_seq", 0)
+            LIMIT $2
+        ) AS "unread"
+    ) AS "unread_count",
+    (
+        SELECT count(*) FROM (
+            SELECT 1 FROM "message_mention"
+            JOIN "message" ON "message"."id" = "message_mention"."message_id"
+            WHERE "message_mention"."user_id" = $1
+                AND "message_mention"."channel_id" = "channel"."id"
+                AND "message"."deleted_at" IS NULL
+                AND "message"."seq" > coalesce("read_state"."last_read_seq", 0)
+            LIMIT $2
+        ) AS "mentions"
+    ) AS "mention_count"
+FROM "channel_member"
+JOIN "channel" ON "channel"."id" = "channel_member"."channel_id"
+LEFT JOIN "channel_read_state" AS "read_state"
+    ON "read_state"."channel_id" = "channel_member"."channel_id"
+    AND "read_state"."user_id" = "channel_member"."user_id"
+WHERE "channel_member"."user_id" = $1
+ORDER BY "channel"."name"
+"#;
+
+/// The reactions of a message grouped by the emoji
+#[derive(Clone, Debug, FromQueryResult, PartialEq)]
+pub struct ReactionCount {
+    pub message_id: Uuid,
+    pub emoji: String,
+    pub count: i64,
+
+    /// The user of the request is among the reacted
+    pub reacted: bool,
+}
+
+/// The highlights of the snippets are marked with the control characters, so the text can be
+/// escaped before they are replaced with the tags
+const SEARCH_HEADLINE_OPTIONS: &str =
+    "StartSel=\"\u{2}\", StopSel=\"\u{3}\", MaxWords=20, MinWords=5, MaxFragments=2, FragmentDelimiter=\" … \"";
+
+/// The page is ranked first, the snippets are built only for it. The text search configuration
+/// is the one of the generated `search` column, otherwise its index isn't used. The highlight
+/// markers are removed from the text, so a message can't forge them.
+const SEARCH_MESSAGES_SQL: &str = r#"
+SELECT
+    "hit"."id",
+    "hit"."rank",
+    ts_headline(
+        'english',
+        translate(coalesce("hit"."content_text", "hit"."content"), chr(2) || chr(3), ''),
+        websearch_to_tsquery('english', $2),
+        $9
+    ) AS "snippet"
+FROM (
+    SELECT
+        "message"."id",
+        "message"."content",
+        "message"."content_text",
+        "message"."created_at",
+        ts_rank("message"."search", "query") AS "rank"
+    FROM "message"
+    JOIN "channel_member"
+        ON "channel_member"."channel_id" = "message"."channel_id"
+        AND "channel_member"."user_id" = $1
+    CROSS JOIN websearch_to_tsquery('english', $2) AS "query"
+    WHERE "message"."search" @@ "query"
+        AND "message"."deleted_at" IS NULL
+        AND ($3::uuid IS NULL OR "message"."sender_id" = $3)
+        AND ($4::uuid IS NULL OR "message"."channel_id" = $4)
+        AND ($5::timestamp IS NULL OR "message"."created_at" >= $5)
+        AND ($6::timestamp IS NULL OR "message"."created_at" < $6)
+    ORDER BY "rank" DESC, "message"."created_at" DESC, "message"."id" DESC
+    LIMIT $7 OFFSET $8
+) AS "hit"
+ORDER BY "hit"."rank" DESC, "hit"."created_at" DESC, "hit"."id" DESC
+"#;
+
+const EXPIRED_ATTACHMENTS_SQL: &str = r#"
+SELECT "attachment".* FROM "attachment"
+JOIN "message" ON "message"."id" = "attachment"."message_id"
+JOIN "channel" ON "channel"."id" = "message"."channel_id"
+WHERE NOT "channel"."legal_hold"
+    AND (
+        "message"."expires_at" < $1
+        OR "message"."created_at" < $1 - make_interval(days => "channel"."retention_days")
+    )
+LIMIT $2
+"#;
+
+/// The optional filters of the message search, `from` is inclusive and `to` exclusive
+#[derive(Clone, Debug, Default)]
+pub struct MessageSearch {
+    pub query: String,
+    pub sender_id: Option<Uuid>,
+    pub channel_id: Option<Uuid>,
+    pub from: Option<DateTime>,
+    pub to: Option<DateTime>,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+#[derive(Clone, Debug, FromQueryResult, PartialEq)]
+struct SearchHit {
+    id: Uuid,
+    rank: f32,
+    snippet: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MessageSearchHit {
+    pub message: message::Model,
+    pub rank: f32,
+
+    /// Escaped, the matched words are in the `mark` tags
+    pub snippet_html: String,
+}
+
 impl Query {
     pub async fn find_user_by_id(db: &DbConn, id: Uuid) -> Result<Option<user::Model>, DbErr> {
         User::find_by_id(id).one(db).await
@@ -17,4 +186,520 @@ impl Query {
             .one(db)
             .await
     }
+
+    pub async fn find_passkeys_by_user_id(
+        db: &DbConn,
+        user_id: Uuid,
+    ) -> Result<Vec<passkey::Model>, DbErr> {
+        Passkey::find()
+            .filter(passkey::Column::UserId.eq(user_id))
+            .order_by_asc(passkey::Column::CreatedAt)
+            .all(db)
+            .await
+    }
+
+    pub async fn find_channel_by_id(
+        db: &DbConn,
+        id: Uuid,
+    ) -> Result<Option<channel::Model>, DbErr> {
+        Channel::find_by_id(id).one(db).await
+    }
+
+    /// The newest invite first, with the users who joined with it
+    pub async fn find_channel_invites(
+        db: &DbConn,
+        channel_id: Uuid,
+    ) -> Result<Vec<(channel_invite::Model, Vec<channel_invite_use::Model>)>, DbErr> {
+        ChannelInvite::find()
+            .filter(channel_invite::Column::ChannelId.eq(channel_id))
+            .order_by_desc(channel_invite::Column::CreatedAt)
+            .find_with_related(ChannelInviteUse)
+            .order_by_asc(channel_invite_use::Column::UsedAt)
+            .all(db)
+            .await
+    }
+
+    pub async fn find_channel_by_name(
+        db: &DbConn,
+        name: &str,
+    ) -> Result<Option<channel::Model>, DbErr> {
+        Channel::find()
+            .filter(channel::Column::Name.eq(name))
+            .one(db)
+            .await
+    }
+
+    pub async fn find_channel_member(
+        db: &DbConn,
+        channel_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<channel_member::Model>, DbErr> {
+        ChannelMember::find_by_id((channel_id, user_id))
+            .one(db)
+            .await
+    }
+
+    pub async fn find_channel_members(
+        db: &DbConn,
+        channel_id: Uuid,
+    ) -> Result<Vec<(channel_member::Model, Option<user::Model>)>, DbErr> {
+        ChannelMember::find()
+            .filter(channel_member::Column::ChannelId.eq(channel_id))
+            .find_also_related(User)
+            .order_by_asc(channel_member::Column::JoinedAt)
+            .all(db)
+            .await
+    }
+
+    /// Case-insensitive, for the mention autocomplete
+    pub async fn find_channel_members_by_name_prefix(
+        db: &DbConn,
+        channel_id: Uuid,
+        prefix: &str,
+        limit: u64,
+    ) -> Result<Vec<(channel_member::Model, Option<user::Model>)>, DbErr> {
+        let pattern = format!("{}%", escape_like(&prefix.to_lowercase()));
+
+        ChannelMember::find()
+            .filter(channel_member::Column::ChannelId.eq(channel_id))
+            .find_also_related(User)
+            .filter(
+                Expr::expr(Func::lower(Expr::col((User, user::Column::Name))))
+                    .like(LikeExpr::new(pattern).escape('\\')),
+            )
+            .order_by_asc(user::Column::Name)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// The channels of the user by name, with the unread messages and mentions counted
+    /// up to `max_count`
+    pub async fn find_channel_unreads(
+        db: &DbConn,
+        user_id: Uuid,
+        max_count: u64,
+    ) -> Result<Vec<ChannelUnread>, DbErr> {
+        let max_count = i64::try_from(max_count).unwrap_or(i64::MAX);
+
+        ChannelUnread::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            CHANNEL_UNREADS_SQL,
+            [user_id.into(), max_count.into()],
+        ))
+        .all(db)
+        .await
+    }
+
+    pub async fn find_message(
+        db: &DbConn,
+        channel_id: Uuid,
+        message_id: Uuid,
+    ) -> Result<Option<message::Model>, DbErr> {
+        Message::find_by_id(message_id)
+            .filter(message::Column::ChannelId.eq(channel_id))
+            .one(db)
+            .await
+    }
+
+    /// The message sent by the user with the nonce, in any channel
+    pub async fn find_message_by_nonce(
+        db: &DbConn,
+        sender_id: Uuid,
+        nonce: Uuid,
+    ) -> Result<Option<message::Model>, DbErr> {
+        Message::find()
+            .filter(message::Column::SenderId.eq(sender_id))
+            .filter(message::Column::Nonce.eq(nonce))
+            .one(db)
+            .await
+    }
+
+    /// Returns the newest messages first, `before` is the last message of the previous page
+    pub async fn find_channel_messages(
+        db: &DbConn,
+        channel_id: Uuid,
+        before: Option<Uuid>,
+        limit: u64,
+    ) -> Result<Vec<message::Model>, DbErr> {
+        // The replies are shown only in their threads
+        let mut query = Message::find()
+            .filter(message::Column::ChannelId.eq(channel_id))
+            .filter(message::Column::ParentId.is_null());
+
+        if let Some(before) = before {
+            let Some(cursor) = Self::find_message(db, channel_id, before).await? else {
+                return Ok(Vec::new());
+            };
+
+            query = query.filter(message::Column::Seq.lt(cursor.seq));
+        }
+
+        query
+            .order_by_desc(message::Column::Seq)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// The messages and replies numbered after `after_seq`, the oldest first
+    pub async fn find_channel_messages_after(
+        db: &DbConn,
+        channel_id: Uuid,
+        after_seq: i64,
+        limit: u64,
+    ) -> Result<Vec<message::Model>, DbErr> {
+        Message::find()
+            .filter(message::Column::ChannelId.eq(channel_id))
+            .filter(message::Column::Seq.gt(after_seq))
+            .order_by_asc(message::Column::Seq)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// Returns the oldest replies first, `after` is the last reply of the previous page
+    pub async fn find_thread_replies(
+        db: &DbConn,
+        root: &message::Model,
+        after: Option<Uuid>,
+        limit: u64,
+    ) -> Result<Vec<message::Model>, DbErr> {
+        let mut query = Message::find().filter(message::Column::ParentId.eq(root.id));
+
+        if let Some(after) = after {
+            let Some(cursor) = Self::find_message(db, root.channel_id, after).await? else {
+                return Ok(Vec::new());
+            };
+
+            query = query.filter(message::Column::Seq.gt(cursor.seq));
+        }
+
+        query
+            .order_by_asc(message::Column::Seq)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// The not deleted messages mentioning the user in the channels the user is still a member
+    /// of, the newest first
+    pub async fn find_mentions_of_user(
+        db: &DbConn,
+        user_id: Uuid,
+        before: Option<Uuid>,
+        limit: u64,
+    ) -> Result<Vec<message::Model>, DbErr> {
+        let mut query = Message::find()
+            .inner_join(MessageMention)
+            .join(
+                JoinType::InnerJoin,
+                message_mention::Entity::belongs_to(ChannelMember)
+                    .from((
+                        message_mention::Column::ChannelId,
+                        message_mention::Column::UserId,
+                    ))
+                    .to((
+                        channel_member::Column::ChannelId,
+                        channel_member::Column::UserId,
+                    ))
+                    .into(),
+            )
+            .filter(message_mention::Column::UserId.eq(user_id))
+            .filter(message::Column::DeletedAt.is_null());
+
+        if let Some(before) = before {
+            let Some(cursor) = Message::find_by_id(before).one(db).await? else {
+                return Ok(Vec::new());
+            };
+
+            query = query.filter(
+                Condition::any()
+                    .add(message_mention::Column::CreatedAt.lt(cursor.created_at))
+                    .add(
+                        Condition::all()
+                            .add(message_mention::Column::CreatedAt.eq(cursor.created_at))
+                            .add(message_mention::Column::MessageId.lt(cursor.id)),
+                    ),
+            );
+        }
+
+        query
+            .order_by_desc(message_mention::Column::CreatedAt)
+            .order_by_desc(message_mention::Column::MessageId)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// The matching messages of the channels of the user, the best ranked first
+    pub async fn search_messages(
+        db: &DbConn,
+        user_id: Uuid,
+        search: MessageSearch,
+    ) -> Result<Vec<MessageSearchHit>, DbErr> {
+        let limit = i64::try_from(search.limit).unwrap_or(i64::MAX);
+        let offset = i64::try_from(search.offset).unwrap_or(i64::MAX);
+
+        let hits = SearchHit::find_by_statement(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            SEARCH_MESSAGES_SQL,
+            [
+                user_id.into(),
+                search.query.into(),
+                search.sender_id.into(),
+                search.channel_id.into(),
+                search.from.into(),
+                search.to.into(),
+                limit.into(),
+                offset.into(),
+                SEARCH_HEADLINE_OPTIONS.into(),
+            ],
+        ))
+        .all(db)
+        .await?;
+
+        if hits.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut messages: HashMap<Uuid, message::Model> = Message::find()
+            .filter(message::Column::Id.is_in(hits.iter().map(|hit| hit.id)))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|message| (message.id, message))
+            .collect();
+
+        Ok(hits
+            .into_iter()
+            .filter_map(|hit| {
+                Some(MessageSearchHit {
+                    message: messages.remove(&hit.id)?,
+                    rank: hit.rank,
+                    snippet_html: highlight(&hit.snippet),
+                })
+            })
+            .collect())
+    }
+
+    pub async fn find_message_revisions(
+        db: &DbConn,
+        message_id: Uuid,
+    ) -> Result<Vec<message_revision::Model>, DbErr> {
+        MessageRevision::find()
+            .filter(message_revision::Column::MessageId.eq(message_id))
+            .order_by_asc(message_revision::Column::CreatedAt)
+            .all(db)
+            .await
+    }
+
+    /// The pins of the channel with their messages, the newest pin first
+    pub async fn find_channel_pins(
+        db: &DbConn,
+        channel_id: Uuid,
+    ) -> Result<Vec<(pinned_message::Model, message::Model)>, DbErr> {
+        let pins = PinnedMessage::find()
+            .find_also_related(Message)
+            .filter(pinned_message::Column::ChannelId.eq(channel_id))
+            .order_by_desc(pinned_message::Column::PinnedAt)
+            .all(db)
+            .await?;
+
+        Ok(pins
+            .into_iter()
+            .filter_map(|(pin, message)| Some((pin, message?)))
+            .collect())
+    }
+
+    /// The pending messages of the user in the channel, the soonest first
+    pub async fn find_scheduled_messages(
+        db: &DbConn,
+        sender_id: Uuid,
+        channel_id: Uuid,
+    ) -> Result<Vec<scheduled_message::Model>, DbErr> {
+        ScheduledMessage::find()
+            .filter(scheduled_message::Column::SenderId.eq(sender_id))
+            .filter(scheduled_message::Column::ChannelId.eq(channel_id))
+            .order_by_asc(scheduled_message::Column::SendAt)
+            .all(db)
+            .await
+    }
+
+    pub async fn find_draft(
+        db: &DbConn,
+        user_id: Uuid,
+        channel_id: Uuid,
+    ) -> Result<Option<message_draft::Model>, DbErr> {
+        MessageDraft::find_by_id((channel_id, user_id))
+            .one(db)
+            .await
+    }
+
+    /// The emojis of each message are in the order of the first reaction
+    pub async fn find_reaction_counts(
+        db: &DbConn,
+        message_ids: &[Uuid],
+        user_id: Uuid,
+    ) -> Result<Vec<ReactionCount>, DbErr> {
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        MessageReaction::find()
+            .select_only()
+            .column(message_reaction::Column::MessageId)
+            .column(message_reaction::Column::Emoji)
+            .column_as(Expr::col(message_reaction::Column::Id).count(), "count")
+            .column_as(
+                Expr::cust_with_values(r#"bool_or("user_id" = $1)"#, [user_id]),
+                "reacted",
+            )
+            .filter(message_reaction::Column::MessageId.is_in(message_ids.iter().copied()))
+            .group_by(message_reaction::Column::MessageId)
+            .group_by(message_reaction::Column::Emoji)
+            .order_by_asc(Expr::col(message_reaction::Column::CreatedAt).min())
+            .into_model::<ReactionCount>()
+            .all(db)
+            .await
+    }
+
+    pub async fn find_attachment(
+        db: &DbConn,
+        channel_id: Uuid,
+        attachment_id: Uuid,
+    ) -> Result<Option<attachment::Model>, DbErr> {
+        Attachment::find_by_id(attachment_id)
+            .filter(attachment::Column::ChannelId.eq(channel_id))
+            .one(db)
+            .await
+    }
+
+    pub async fn find_message_attachments(
+        db: &DbConn,
+        message_ids: &[Uuid],
+    ) -> Result<Vec<attachment::Model>, DbErr> {
+        if message_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        Attachment::find()
+            .filter(attachment::Column::MessageId.is_in(message_ids.iter().copied()))
+            .order_by_asc(attachment::Column::CreatedAt)
+            .all(db)
+            .await
+    }
+
+    pub async fn find_link_previews(
+        db: &DbConn,
+        urls: Vec<String>,
+    ) -> Result<Vec<link_preview::Model>, DbErr> {
+        if urls.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        LinkPreview::find()
+            .filter(link_preview::Column::Url.is_in(urls))
+            .all(db)
+            .await
+    }
+
+    /// The uploads never sent since `uploaded_before`
+    pub async fn find_abandoned_uploads(
+        db: &DbConn,
+        uploaded_before: DateTime,
+        limit: u64,
+    ) -> Result<Vec<attachment::Model>, DbErr> {
+        Attachment::find()
+            .filter(attachment::Column::MessageId.is_null())
+            .filter(attachment::Column::CreatedAt.lt(uploaded_before))
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// The files of the messages deleted before `deleted_before` out of the held channels,
+    /// they are deleted before the purge of the messages
+    pub async fn find_deleted_attachments(
+        db: &DbConn,
+        deleted_before: DateTime,
+        limit: u64,
+    ) -> Result<Vec<attachment::Model>, DbErr> {
+        Attachment::find()
+            .inner_join(Message)
+            .filter(message::Column::DeletedAt.lt(deleted_before))
+            .filter(message::Column::ChannelId.not_in_subquery(held_channels()))
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// The files of the messages past the retention period of the channel or their TTL,
+    /// they are deleted before the messages
+    pub async fn find_expired_attachments(
+        db: &DbConn,
+        now: DateTime,
+        limit: u64,
+    ) -> Result<Vec<attachment::Model>, DbErr> {
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+
+        Attachment::find()
+            .from_raw_sql(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                EXPIRED_ATTACHMENTS_SQL,
+                [now.into(), limit.into()],
+            ))
+            .all(db)
+            .await
+    }
+}
+
+/// The prefix is matched literally
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        if matches!(ch, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+
+    escaped
+}
+
+/// Escapes the snippet and replaces the highlight markers of `SEARCH_HEADLINE_OPTIONS`
+fn highlight(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+    let mut highlighted = false;
+
+    for (idx, part) in snippet.split(['\u{2}', '\u{3}']).enumerate() {
+        if idx > 0 {
+            html.push_str(if highlighted { "</mark>" } else { "<mark>" });
+            highlighted = !highlighted;
+        }
+        markdown::escape(&mut html, part);
+    }
+
+    if highlighted {
+        html.push_str("</mark>");
+    }
+
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight("a \u{2}b\u{3} <c> \u{2}d\u{3}"),
+            "a <mark>b</mark> &lt;c&gt; <mark>d</mark>"
+        );
+        assert_eq!(highlight("a & b"), "a &amp; b");
+
+        // The truncated snippet can end inside a highlight
+        assert_eq!(highlight("a \u{2}b"), "a <mark>b</mark>");
+    }
 }
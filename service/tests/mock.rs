@@ -1,9 +1,19 @@
 #![feature(lazy_cell)]
 
-use entity::user;
-use sea_orm::{prelude::Uuid, Set, Unchanged};
+use std::collections::BTreeMap;
+
+use entity::{
+    channel_invite, channel_member, message, message_draft, message_revision, pinned_message,
+    sea_orm_active_enums::ChannelRole, user,
+};
+use sea_orm::{
+    prelude::Uuid, DatabaseBackend, MockDatabase, Set, Statement, Transaction, Unchanged, Value,
+};
 use service::{
-    mutation::{CreateUserData, Mutation},
+    mutation::{
+        CreateMessageData, CreateMessageError, CreateUserData, EditMessageData, EditMessageError,
+        JoinByInviteError, Mutation, PinMessageError, ScheduleMessageData, ScheduleMessageError,
+    },
     query::Query,
 };
 
@@ -51,3 +61,382 @@ async fn main() {
         assert_eq!(user.avatar, Unchanged(None));
     }
 }
+
+#[tokio::test]
+async fn edit_message() {
+    let message = MESSAGE_MODEL.clone();
+    let created_at = message.created_at;
+
+    let edited = message::Model {
+        content: "new".to_owned(),
+        content_html: Some("<p>new</p>".to_owned()),
+        content_text: Some("new".to_owned()),
+        edited_at: Some(created_at),
+        ..message.clone()
+    };
+
+    let edit_data = |editor_id| EditMessageData {
+        message_id: message.id,
+        channel_id: message.channel_id,
+        editor_id,
+        content: "new".to_owned(),
+        urls: Vec::new(),
+    };
+
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[message.clone()]])
+        .append_query_results([[message_revision::Model {
+            id: Uuid::from_u128(3),
+            message_id: message.id,
+            content: "old".to_owned(),
+            created_at,
+        }]])
+        .append_query_results([[edited.clone()]])
+        // The mentions and the links of the old content are removed
+        .append_exec_results([exec_result(0), exec_result(0)])
+        .into_connection();
+
+    let result = Mutation::edit_message(&db, edit_data(FIRST_UUID))
+        .await
+        .unwrap();
+    assert_eq!(result, edited);
+
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[message]])
+        .into_connection();
+
+    let result = Mutation::edit_message(&db, edit_data(SECOND_UUID)).await;
+    assert!(matches!(result, Err(EditMessageError::NotSender)));
+}
+
+#[tokio::test]
+async fn add_reaction_once() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_exec_results([exec_result(1), exec_result(0)])
+        .into_connection();
+
+    let message_id = Uuid::from_u128(1);
+    let add = || Mutation::add_reaction(&db, message_id, FIRST_UUID, "👍".to_owned());
+
+    assert!(add().await.unwrap());
+    assert!(!add().await.unwrap());
+
+    // The repeated reaction is skipped by the unique index instead of failing
+    let insert = Transaction::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"INSERT INTO "message_reaction" ("message_id", "user_id", "emoji") VALUES ($1, $2, $3) ON CONFLICT ("message_id", "user_id", "emoji") DO NOTHING"#,
+        [message_id.into(), FIRST_UUID.into(), "👍".into()],
+    );
+    assert_eq!(db.into_transaction_log(), [insert.clone(), insert]);
+}
+
+#[tokio::test]
+async fn use_totp_step_once() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_exec_results([exec_result(1), exec_result(0)])
+        .into_connection();
+
+    assert!(Mutation::use_totp_step(&db, FIRST_UUID, 10).await.unwrap());
+    assert!(!Mutation::use_totp_step(&db, FIRST_UUID, 10).await.unwrap());
+
+    // The step is only stored if it's newer than the last accepted one
+    let update = Transaction::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"UPDATE "user" SET "totp_last_step" = $1 WHERE "user"."id" = $2 AND ("user"."totp_last_step" IS NULL OR "user"."totp_last_step" < $3)"#,
+        [10i64.into(), FIRST_UUID.into(), 10i64.into()],
+    );
+    assert_eq!(db.into_transaction_log(), [update.clone(), update]);
+}
+
+#[tokio::test]
+async fn delete_message_once() {
+    let deleted = message::Model {
+        deleted_at: Some(MESSAGE_MODEL.created_at),
+        deleted_by: Some(SECOND_UUID),
+        ..MESSAGE_MODEL.clone()
+    };
+
+    // The second update doesn't match, the message is already deleted
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_exec_results([exec_result(1), exec_result(0), exec_result(0)])
+        .append_query_results([[deleted.clone()]])
+        .into_connection();
+
+    let delete = || Mutation::delete_message(&db, MESSAGE_MODEL.id, SECOND_UUID);
+
+    assert_eq!(delete().await.unwrap(), Some(deleted));
+    assert_eq!(delete().await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn pin_message_cap() {
+    let max_pins = 2;
+    let count = BTreeMap::from([("num_items", Value::BigInt(Some(max_pins)))]);
+
+    // The channel, the message, no pin of it yet and the pins of the channel
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[CHANNEL_MODEL.clone()]])
+        .append_query_results([[MESSAGE_MODEL.clone()]])
+        .append_query_results([Vec::<pinned_message::Model>::new()])
+        .append_query_results([[count]])
+        .into_connection();
+
+    let result = Mutation::pin_message(
+        &db,
+        CHANNEL_MODEL.id,
+        MESSAGE_MODEL.id,
+        FIRST_UUID,
+        max_pins as u64,
+    )
+    .await;
+    assert!(matches!(result, Err(PinMessageError::TooManyPins)));
+}
+
+#[tokio::test]
+async fn schedule_message_cap() {
+    let max_scheduled = 2;
+    let count = BTreeMap::from([("num_items", Value::BigInt(Some(max_scheduled)))]);
+
+    // The locked sender and the count of the scheduled messages
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[USER_MODEL.clone()]])
+        .append_query_results([[count]])
+        .into_connection();
+
+    let schedule_data = ScheduleMessageData {
+        sender_id: FIRST_UUID,
+        channel_id: CHANNEL_MODEL.id,
+        content: "later".to_owned(),
+        parent_id: None,
+        send_at: MESSAGE_MODEL.created_at,
+    };
+
+    let result = Mutation::schedule_message(&db, schedule_data, max_scheduled as u64).await;
+    assert!(matches!(
+        result,
+        Err(ScheduleMessageError::TooManyScheduled)
+    ));
+}
+
+#[tokio::test]
+async fn send_scheduled_message_once() {
+    // The second delivery finds the scheduled message deleted by the first one
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[USER_MODEL.clone()]])
+        .append_query_results([[CHANNEL_MODEL.clone()]])
+        .append_query_results([[MESSAGE_MODEL.clone()]])
+        .append_query_results([[USER_MODEL.clone()]])
+        .append_query_results([[CHANNEL_MODEL.clone()]])
+        .append_query_results([[MESSAGE_MODEL.clone()]])
+        .append_exec_results([exec_result(1), exec_result(0)])
+        .into_connection();
+
+    let send = || {
+        Mutation::create_message(
+            &db,
+            CreateMessageData {
+                id: Some(MESSAGE_MODEL.id),
+                nonce: None,
+                sender_id: FIRST_UUID,
+                channel_id: CHANNEL_MODEL.id,
+                content: "old".to_owned(),
+                parent_id: None,
+                attachment_ids: Vec::new(),
+                urls: Vec::new(),
+            },
+        )
+    };
+
+    assert_eq!(send().await.unwrap(), *MESSAGE_MODEL);
+    assert!(matches!(
+        send().await,
+        Err(CreateMessageError::ScheduledNotFound)
+    ));
+}
+
+#[tokio::test]
+async fn save_and_delete_draft() {
+    let draft = message_draft::Model {
+        channel_id: CHANNEL_MODEL.id,
+        user_id: FIRST_UUID,
+        content: "draft".to_owned(),
+        updated_at: MESSAGE_MODEL.created_at,
+    };
+
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_exec_results([exec_result(1), exec_result(1)])
+        .append_query_results([[draft.clone()]])
+        .into_connection();
+
+    Mutation::save_draft(&db, FIRST_UUID, CHANNEL_MODEL.id, "draft".to_owned())
+        .await
+        .unwrap();
+
+    // The blank content deletes the draft instead of storing it
+    Mutation::save_draft(&db, FIRST_UUID, CHANNEL_MODEL.id, " \n".to_owned())
+        .await
+        .unwrap();
+
+    let found = Query::find_draft(&db, FIRST_UUID, CHANNEL_MODEL.id)
+        .await
+        .unwrap();
+    assert_eq!(found, Some(draft));
+
+    let delete = Transaction::from_sql_and_values(
+        DatabaseBackend::Postgres,
+        r#"DELETE FROM "message_draft" WHERE "message_draft"."channel_id" = $1 AND "message_draft"."user_id" = $2"#,
+        [CHANNEL_MODEL.id.into(), FIRST_UUID.into()],
+    );
+    let log = db.into_transaction_log();
+    assert_eq!(log.len(), 3);
+    assert_eq!(log[1], delete);
+}
+
+#[tokio::test]
+async fn number_messages_by_channel() {
+    let channel = entity::channel::Model {
+        last_seq: 8,
+        ..CHANNEL_MODEL.clone()
+    };
+    let message = message::Model {
+        seq: 8,
+        ..MESSAGE_MODEL.clone()
+    };
+
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[USER_MODEL.clone()]])
+        .append_query_results([[channel]])
+        .append_query_results([[message.clone()]])
+        .into_connection();
+
+    let sent = Mutation::create_message(
+        &db,
+        CreateMessageData {
+            id: None,
+            nonce: None,
+            sender_id: FIRST_UUID,
+            channel_id: CHANNEL_MODEL.id,
+            content: "old".to_owned(),
+            parent_id: None,
+            attachment_ids: Vec::new(),
+            urls: Vec::new(),
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(sent, message);
+
+    // The number comes from the counter bumped in the same transaction as the insert,
+    // so a rolled back send leaves no gap
+    let send = Transaction::many([
+        Statement::from_string(DatabaseBackend::Postgres, "BEGIN"),
+        Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"UPDATE "channel" SET "last_seq" = "last_seq" + $1 WHERE "channel"."id" = $2 RETURNING "id", "created_at", "name", "is_private", "retention_days", "message_ttl_secs", "legal_hold", "last_seq", "topic", "description", "icon""#,
+            [1i32.into(), CHANNEL_MODEL.id.into()],
+        ),
+        Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"INSERT INTO "message" ("sender_id", "channel_id", "content", "parent_id", "content_html", "content_text", "expires_at", "nonce", "seq", "kind") VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, CAST($10 AS message_kind)) RETURNING "id", "created_at", "sender_id", "channel_id", "content", "edited_at", "deleted_at", "deleted_by", "parent_id", "reply_count", "last_reply_at", "content_html", "content_text", "expires_at", "nonce", "seq", CAST("kind" AS text), "purged_at""#,
+            [
+                FIRST_UUID.into(),
+                CHANNEL_MODEL.id.into(),
+                "old".into(),
+                Value::Uuid(None),
+                "<p>old</p>".into(),
+                "old".into(),
+                Value::ChronoDateTime(None),
+                Value::Uuid(None),
+                8i64.into(),
+                "user".into(),
+            ],
+        ),
+        Statement::from_string(DatabaseBackend::Postgres, "COMMIT"),
+    ]);
+    let log = db.into_transaction_log();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[1], send);
+}
+
+fn invite() -> channel_invite::Model {
+    channel_invite::Model {
+        id: Uuid::from_u128(7),
+        channel_id: CHANNEL_MODEL.id,
+        token_hash: "hash".to_owned(),
+        created_by: Some(SECOND_UUID),
+        created_at: CHANNEL_MODEL.created_at,
+        expires_at: None,
+        max_uses: None,
+        use_count: 0,
+        revoked_at: None,
+    }
+}
+
+#[tokio::test]
+async fn join_by_invalid_invite() {
+    let expired = channel_invite::Model {
+        expires_at: Some(CHANNEL_MODEL.created_at),
+        ..invite()
+    };
+    let revoked = channel_invite::Model {
+        revoked_at: Some(CHANNEL_MODEL.created_at),
+        ..invite()
+    };
+
+    for invite in [expired, revoked] {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[invite]])
+            .into_connection();
+
+        let result = Mutation::join_channel_by_invite(&db, "hash", FIRST_UUID).await;
+        assert!(matches!(result, Err(JoinByInviteError::InviteNotFound)));
+    }
+}
+
+#[tokio::test]
+async fn join_by_used_up_invite() {
+    let used_up = channel_invite::Model {
+        max_uses: Some(1),
+        use_count: 1,
+        ..invite()
+    };
+    let member = channel_member::Model {
+        channel_id: CHANNEL_MODEL.id,
+        user_id: FIRST_UUID,
+        role: ChannelRole::Member,
+        joined_at: CHANNEL_MODEL.created_at,
+    };
+
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[used_up.clone()]])
+        .append_query_results([[CHANNEL_MODEL.clone()]])
+        .append_query_results([Vec::<channel_member::Model>::new()])
+        .into_connection();
+
+    let result = Mutation::join_channel_by_invite(&db, "hash", FIRST_UUID).await;
+    assert!(matches!(result, Err(JoinByInviteError::InviteNotFound)));
+
+    // A member gets the channel without using the invite
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[used_up]])
+        .append_query_results([[CHANNEL_MODEL.clone()]])
+        .append_query_results([[member]])
+        .into_connection();
+
+    let channel = Mutation::join_channel_by_invite(&db, "hash", FIRST_UUID)
+        .await
+        .unwrap();
+    assert_eq!(channel, *CHANNEL_MODEL);
+}
+
+#[tokio::test]
+async fn revoke_invite_once() {
+    let db = MockDatabase::new(DatabaseBackend::Postgres)
+        .append_exec_results([exec_result(1), exec_result(0)])
+        .into_connection();
+
+    let revoke = || Mutation::revoke_invite(&db, CHANNEL_MODEL.id, invite().id);
+
+    assert!(revoke().await.unwrap());
+    assert!(!revoke().await.unwrap());
+}
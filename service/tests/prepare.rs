@@ -1,6 +1,7 @@
 use std::str::FromStr;
 
-use ::entity::user;
+use ::entity::{channel, message, sea_orm_active_enums::MessageKind, user};
+use chrono::NaiveDateTime;
 use once_cell::sync::Lazy;
 use sea_orm::{prelude::Uuid, *};
 
@@ -13,8 +14,58 @@ pub static USER_MODEL: Lazy<user::Model> = Lazy::new(|| user::Model {
     password: "123".to_owned(),
     name: "a".to_owned(),
     avatar: None,
+    totp_secret: None,
+    totp_enabled: false,
+    totp_last_step: None,
 });
 
+pub static CHANNEL_MODEL: Lazy<channel::Model> = Lazy::new(|| channel::Model {
+    id: Uuid::from_u128(2),
+    created_at: NaiveDateTime::from_timestamp_opt(1_700_000_000, 0).unwrap(),
+    name: "general".to_owned(),
+    is_private: false,
+    retention_days: None,
+    message_ttl_secs: None,
+    legal_hold: false,
+    last_seq: 1,
+    topic: None,
+    description: None,
+    icon: None,
+});
+
+pub static MESSAGE_MODEL: Lazy<message::Model> = Lazy::new(|| {
+    let created_at = NaiveDateTime::from_timestamp_opt(1_700_000_000, 0).unwrap();
+
+    message::Model {
+        id: Uuid::from_u128(1),
+        created_at,
+        sender_id: FIRST_UUID,
+        channel_id: Uuid::from_u128(2),
+        content: "old".to_owned(),
+        content_html: Some("<p>old</p>".to_owned()),
+        content_text: Some("old".to_owned()),
+        edited_at: None,
+        deleted_at: None,
+        deleted_by: None,
+        parent_id: None,
+        reply_count: 0,
+        last_reply_at: None,
+        expires_at: None,
+        nonce: None,
+        seq: 1,
+        kind: MessageKind::User,
+        purged_at: None,
+    }
+});
+
+#[cfg(feature = "mock")]
+pub fn exec_result(rows_affected: u64) -> MockExecResult {
+    MockExecResult {
+        last_insert_id: 0,
+        rows_affected,
+    }
+}
+
 #[cfg(feature = "mock")]
 pub fn prepare_mock_db() -> DatabaseConnection {
     MockDatabase::new(DatabaseBackend::Postgres)
@@ -26,6 +77,9 @@ pub fn prepare_mock_db() -> DatabaseConnection {
                 password: "456".to_owned(),
                 name: "b".to_owned(),
                 avatar: None,
+                totp_secret: None,
+                totp_enabled: false,
+                totp_last_step: None,
             }],
         ])
         .into_connection()
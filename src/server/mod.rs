@@ -60,6 +60,7 @@ pub async fn run() -> anyhow::Result<()> {
     let routes = leptos_axum::generate_route_list(|| view! { <App/> });
 
     let state = ServerState::new(&environment, leptos_options.clone()).await?;
+    backend::task::spawn(&state, &environment);
 
     let app = Router::new()
         .nest("/api", backend::routes())